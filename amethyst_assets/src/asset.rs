@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{mem, sync::Arc};
 
 use amethyst_core::specs::storage::UnprotectedStorage;
 
@@ -27,6 +27,18 @@ pub trait Asset: Send + Sync + 'static {
 
     /// The ECS storage type to be used. You'll want to use `VecStorage` in most cases.
     type HandleStorage: UnprotectedStorage<Handle<Self>> + Send + Sync;
+
+    /// A hint of how many bytes of memory this asset uses, used to report the memory use of
+    /// asset storages in `AssetMemory` and to stay within the budget of an `LruCache`.
+    ///
+    /// Defaults to the size of the type itself; assets owning memory on the heap or the GPU,
+    /// like textures or sounds, should include it.
+    fn size_bytes(&self) -> usize
+    where
+        Self: Sized,
+    {
+        mem::size_of::<Self>()
+    }
 }
 
 /// A format, providing a conversion from bytes to asset data, which is then
@@ -48,6 +60,11 @@ pub trait Format<A: Asset>: Send + 'static {
     /// to reload assets if necessary (for hot reloading).
     /// You should only create this if `create_reload` is `true`.
     /// Also, the parameter is just a request, which means you can also return `None`.
+    ///
+    /// ## Dependencies
+    ///
+    /// If the data references other assets, record them with `record_dependency`, so this
+    /// asset is reloaded when one of them is hot reloaded.
     fn import(
         &self,
         name: String,
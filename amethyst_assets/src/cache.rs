@@ -2,7 +2,7 @@ use std::{borrow::Borrow, hash::Hash};
 
 use fnv::FnvHashMap;
 
-use crate::{Handle, WeakHandle};
+use crate::{Asset, AssetStorage, Handle, WeakHandle};
 
 /// A simple cache for asset handles of type `A`.
 /// This stores `WeakHandle`, so it doesn't keep the assets alive.
@@ -45,3 +45,227 @@ where
         self.map.clear();
     }
 }
+
+/// A cache keeping recently used assets of type `A` alive within a memory budget.
+///
+/// Unlike `Cache`, this stores `Handle`s, so cached assets stay loaded even if nothing else
+/// uses them. When the assets in the cache use more than the budget, `evict` drops the
+/// handles of the least recently used ones, according to `Asset::size_bytes`. Assets which
+/// are still in use elsewhere stay loaded after being evicted.
+///
+/// If an `LruCache<A>` resource exists, the `Processor<A>` evicts from it every frame.
+pub struct LruCache<A> {
+    budget: usize,
+    clock: u64,
+    entries: FnvHashMap<String, LruEntry<A>>,
+    // The size of the entries computed by the last `evict`, with the size generation of the
+    // storage at that time.
+    size: Option<(usize, u64)>,
+}
+
+struct LruEntry<A> {
+    handle: Handle<A>,
+    last_used: u64,
+}
+
+impl<A> LruCache<A>
+where
+    A: Asset,
+{
+    /// Creates an empty cache keeping at most `budget` bytes of assets alive.
+    pub fn new(budget: usize) -> Self {
+        LruCache {
+            budget,
+            clock: 0,
+            entries: Default::default(),
+            size: None,
+        }
+    }
+
+    /// Returns the budget in bytes.
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Changes the budget, taking effect on the next `evict`.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+    }
+
+    /// Inserts an asset with a given `key` as the most recently used one and returns the old
+    /// handle (if any).
+    pub fn insert<K: Into<String>>(&mut self, key: K, handle: Handle<A>) -> Option<Handle<A>> {
+        let last_used = self.tick();
+        self.size = None;
+        self.entries
+            .insert(key.into(), LruEntry { handle, last_used })
+            .map(|entry| entry.handle)
+    }
+
+    /// Retrieves an asset handle using a given `key`, marking it as the most recently used.
+    pub fn get<K>(&mut self, key: &K) -> Option<Handle<A>>
+    where
+        K: ?Sized + Hash + Eq,
+        String: Borrow<K>,
+    {
+        let last_used = self.tick();
+        self.entries.get_mut(key).map(|entry| {
+            entry.last_used = last_used;
+            entry.handle.clone()
+        })
+    }
+
+    /// Checks whether an asset is cached with a given `key`, without marking it as used.
+    pub fn contains_key<K>(&self, key: &K) -> bool
+    where
+        K: ?Sized + Hash + Eq,
+        String: Borrow<K>,
+    {
+        self.entries.contains_key(key)
+    }
+
+    /// Removes an asset from the cache and returns its handle.
+    pub fn remove<K>(&mut self, key: &K) -> Option<Handle<A>>
+    where
+        K: ?Sized + Hash + Eq,
+        String: Borrow<K>,
+    {
+        self.size = None;
+        self.entries.remove(key).map(|entry| entry.handle)
+    }
+
+    /// Returns the number of cached assets.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Clears all values.
+    pub fn clear(&mut self) {
+        self.size = None;
+        self.entries.clear();
+    }
+
+    /// Returns the memory used by the cached assets in bytes. Assets which are not loaded yet
+    /// don't count.
+    pub fn size_bytes(&self, storage: &AssetStorage<A>) -> usize {
+        self.entries
+            .values()
+            .map(|entry| storage.size_of(&entry.handle))
+            .sum()
+    }
+
+    /// Drops the least recently used assets until the cached assets fit into the budget and
+    /// returns the number of evicted assets.
+    ///
+    /// The size of the cached assets is only recomputed if the cache or the sizes in the
+    /// storage changed since the last call.
+    pub fn evict(&mut self, storage: &AssetStorage<A>) -> usize {
+        let generation = storage.size_generation();
+        let mut size = match self.size {
+            Some((size, last)) if last == generation => size,
+            _ => self.size_bytes(storage),
+        };
+        if size <= self.budget {
+            self.size = Some((size, generation));
+            return 0;
+        }
+
+        let mut entries = self
+            .entries
+            .iter()
+            .map(|(key, entry)| {
+                let bytes = storage.size_of(&entry.handle);
+                (entry.last_used, bytes, key.clone())
+            })
+            .collect::<Vec<_>>();
+        entries.sort();
+
+        let mut evicted = 0;
+        for (_, bytes, key) in entries {
+            if size <= self.budget {
+                break;
+            }
+            if bytes == 0 {
+                continue;
+            }
+            debug!("{:?}: Evicting {:?} from the cache", A::NAME, key);
+            self.entries.remove(&key);
+            size -= bytes;
+            evicted += 1;
+        }
+        self.size = Some((size, generation));
+
+        evicted
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use rayon::ThreadPoolBuilder;
+
+    use crate::{
+        fixtures::{loaded, Bytes},
+        AssetStorage, Loader,
+    };
+
+    use super::LruCache;
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let pool = Arc::new(ThreadPoolBuilder::default().build().unwrap());
+        let loader = Loader::new(".", pool.clone());
+        let mut storage = AssetStorage::<Bytes>::new();
+        let mut cache = LruCache::new(25);
+        for &name in &["a", "b", "c"] {
+            let handle = loader.load_from_data(vec![0; 10], (), &storage);
+            cache.insert(name, handle);
+        }
+        storage.process(loaded, 0, &pool, None);
+        assert_eq!(30, storage.memory_usage());
+        assert_eq!(30, cache.size_bytes(&storage));
+
+        cache.get("a");
+        assert_eq!(1, cache.evict(&storage));
+        assert!(cache.contains_key("a"));
+        assert!(!cache.contains_key("b"));
+        assert!(cache.contains_key("c"));
+
+        storage.process(loaded, 1, &pool, None);
+        assert_eq!(2, storage.len());
+        assert_eq!(20, storage.memory_usage());
+    }
+
+    #[test]
+    fn counts_resized_assets() {
+        let pool = Arc::new(ThreadPoolBuilder::default().build().unwrap());
+        let loader = Loader::new(".", pool.clone());
+        let mut storage = AssetStorage::<Bytes>::new();
+        let mut cache = LruCache::new(15);
+        let handle = loader.load_from_data(vec![0; 10], (), &storage);
+        cache.insert("a", handle.clone());
+        storage.process(loaded, 0, &pool, None);
+        assert_eq!(0, cache.evict(&storage));
+
+        storage
+            .get_mut(&handle)
+            .unwrap()
+            .0
+            .extend_from_slice(&[0; 10]);
+        storage.process(loaded, 1, &pool, None);
+        assert_eq!(20, storage.memory_usage());
+        assert_eq!(1, cache.evict(&storage));
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,332 @@
+//! Offline asset cooking: running formats ahead of time and storing the imported data in a
+//! binary cache, so loading skips the import.
+
+use std::{
+    ffi::OsStr,
+    fs::{self, File},
+    hash::Hasher,
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use fnv::FnvHasher;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    Asset, ErrorKind, Format, FormatValue, Reload, Result, ResultExt, SimpleFormat, SingleFile,
+    Source,
+};
+
+const MAGIC: [u8; 4] = *b"ACKD";
+const VERSION: u32 = 1;
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+struct Header {
+    magic: [u8; 4],
+    version: u32,
+    content: u64,
+}
+
+/// A directory of cooked assets, which is the data imported by a `SimpleFormat` stored in a
+/// compact binary form.
+///
+/// Every entry is keyed by the asset type, the format, the format options and the asset
+/// name, and stores a hash of the source bytes it was cooked from. Entries whose source
+/// changed since cooking are ignored. The entries are stored directly in the directory, named
+/// after the file name of the asset and a hash of the key.
+///
+/// Cook assets with `cook`, or with the `amethyst_cook` tool, and make the `Loader` use them
+/// with `Loader::set_cooked_cache` and `Loader::register_cooked`.
+#[derive(Clone, Debug)]
+pub struct CookedCache {
+    directory: PathBuf,
+}
+
+impl CookedCache {
+    /// Creates a cache storing its entries in `directory`.
+    pub fn new<P>(directory: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        CookedCache {
+            directory: directory.into(),
+        }
+    }
+
+    /// Returns the directory of the cache.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Imports an asset from `source` and writes the data to the cache.
+    ///
+    /// Returns `false` without importing if the entry is already up to date.
+    pub fn cook<A, F>(
+        &self,
+        name: &str,
+        format: &F,
+        options: F::Options,
+        source: &dyn Source,
+    ) -> Result<bool>
+    where
+        A: Asset,
+        A::Data: Serialize,
+        F: SimpleFormat<A>,
+        F::Options: Serialize,
+    {
+        let bytes = source.load(name).chain_err(|| ErrorKind::Source)?;
+        let path = self.path::<A, F>(name, &options)?;
+        let content = content_hash(&bytes);
+        let header = Header {
+            magic: MAGIC,
+            version: VERSION,
+            content,
+        };
+        if read_header(&path).map_or(false, |existing| existing == header) {
+            return Ok(false);
+        }
+
+        let data = SimpleFormat::import(format, bytes, options)
+            .chain_err(|| ErrorKind::Format(F::NAME))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .chain_err(|| format!("Failed to create directory {:?}", parent))?;
+        }
+
+        // The entry is written to a temporary file first, so an interrupted cook can't leave a
+        // valid header in front of truncated data.
+        let temp = path.with_extension("cooked.tmp");
+        let written = write_entry(&temp, &header, &data).and_then(|_| {
+            fs::rename(&temp, &path)
+                .chain_err(|| format!("Failed to move {:?} to {:?}", temp, path))
+        });
+        if written.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        written?;
+        debug!("{:?}: Cooked asset {:?} to {:?}", A::NAME, name, path);
+
+        Ok(true)
+    }
+
+    /// Reads the cooked data of an asset, if the cache has an entry cooked from `bytes`.
+    pub fn read<A, F>(&self, name: &str, bytes: &[u8], options: &F::Options) -> Option<A::Data>
+    where
+        A: Asset,
+        A::Data: DeserializeOwned,
+        F: SimpleFormat<A>,
+        F::Options: Serialize,
+    {
+        let path = self.path::<A, F>(name, options).ok()?;
+        let mut reader = BufReader::new(File::open(&path).ok()?);
+        let header: Header = bincode::deserialize_from(&mut reader).ok()?;
+        if header.magic != MAGIC || header.version != VERSION {
+            warn!("Ignoring cooked asset {:?} of an unknown version", path);
+            return None;
+        }
+        if header.content != content_hash(bytes) {
+            debug!("Cooked asset {:?} is outdated", path);
+            return None;
+        }
+
+        match bincode::deserialize_from(&mut reader) {
+            Ok(data) => Some(data),
+            Err(e) => {
+                warn!("Failed to read cooked asset {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// Returns the path of an entry. Only the file name of the asset is used, with unusual
+    /// characters replaced, so names with `..` or absolute names stay inside the cache; the
+    /// full name is part of the hash.
+    fn path<A, F>(&self, name: &str, options: &F::Options) -> Result<PathBuf>
+    where
+        A: Asset,
+        F: SimpleFormat<A>,
+        F::Options: Serialize,
+    {
+        let options =
+            bincode::serialize(options).chain_err(|| "Failed to serialize format options")?;
+        let mut hasher = FnvHasher::default();
+        hasher.write(A::NAME.as_bytes());
+        hasher.write(F::NAME.as_bytes());
+        hasher.write(&options);
+        hasher.write(name.as_bytes());
+
+        let file_name = Path::new(name)
+            .file_name()
+            .and_then(OsStr::to_str)
+            .unwrap_or("asset")
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+                _ => '_',
+            })
+            .collect::<String>();
+
+        Ok(self
+            .directory
+            .join(format!("{}.{:016x}.cooked", file_name, hasher.finish())))
+    }
+}
+
+fn content_hash(bytes: &[u8]) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+fn read_header(path: &Path) -> Option<Header> {
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    bincode::deserialize_from(&mut reader).ok()
+}
+
+fn write_entry<D>(path: &Path, header: &Header, data: &D) -> Result<()>
+where
+    D: Serialize,
+{
+    let file = File::create(path).chain_err(|| format!("Failed to create {:?}", path))?;
+    let mut writer = BufWriter::new(file);
+    bincode::serialize_into(&mut writer, header)
+        .and_then(|_| bincode::serialize_into(&mut writer, data))
+        .and_then(|_| writer.flush().map_err(Into::into))
+        .chain_err(|| format!("Failed to write cooked asset {:?}", path))
+}
+
+/// Imports an asset using its cooked data if the cache has a valid entry, otherwise with the
+/// format. Registered in the `Loader` by `Loader::register_cooked`.
+pub(crate) type ImportCooked<A, F> = fn(
+    &CookedCache,
+    &F,
+    String,
+    Arc<dyn Source>,
+    <F as Format<A>>::Options,
+    bool,
+) -> Result<FormatValue<A>>;
+
+pub(crate) fn import_cooked<A, F>(
+    cache: &CookedCache,
+    format: &F,
+    name: String,
+    source: Arc<dyn Source>,
+    options: F::Options,
+    create_reload: bool,
+) -> Result<FormatValue<A>>
+where
+    A: Asset,
+    A::Data: DeserializeOwned,
+    F: SimpleFormat<A> + Clone + Send + Sync + 'static,
+    F::Options: Serialize,
+{
+    let (bytes, modified) = source
+        .load_with_metadata(&name)
+        .chain_err(|| ErrorKind::Source)?;
+    let data = match cache.read::<A, F>(&name, &bytes, &options) {
+        Some(data) => {
+            debug!("{:?}: Using cooked data of asset {:?}", A::NAME, name);
+            data
+        }
+        None => SimpleFormat::import(format, bytes, options.clone())?,
+    };
+    let reload = if create_reload {
+        let reload = SingleFile::new(format.clone(), modified, options, name, source);
+        Some(Box::new(reload) as Box<dyn Reload<A>>)
+    } else {
+        None
+    };
+
+    Ok(FormatValue { data, reload })
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, process};
+
+    use crate::{
+        fixtures::{Text, UpperFormat},
+        Directory,
+    };
+
+    use super::{CookedCache, Header, MAGIC, VERSION};
+
+    #[test]
+    fn cooks_and_reads_entries() {
+        let directory = env::temp_dir().join(format!("amethyst_assets_cooked_{}", process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        let cache = CookedCache::new(&directory);
+        let source = Directory::new(format!("{}/tests/assets", env!("CARGO_MANIFEST_DIR")));
+        let bytes = fs::read(format!(
+            "{}/tests/assets/subdir/asset",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .unwrap();
+
+        assert!(cache
+            .cook::<Text, _>("subdir/asset", &UpperFormat, true, &source)
+            .unwrap());
+        assert!(!cache
+            .cook::<Text, _>("subdir/asset", &UpperFormat, true, &source)
+            .unwrap());
+
+        let expected = String::from_utf8(bytes.clone()).unwrap();
+        assert_eq!(
+            Some(expected.to_uppercase().trim().to_owned()),
+            cache.read::<Text, UpperFormat>("subdir/asset", &bytes, &true)
+        );
+        assert_eq!(
+            None,
+            cache.read::<Text, UpperFormat>("subdir/asset", &bytes, &false)
+        );
+        assert_eq!(
+            None,
+            cache.read::<Text, UpperFormat>("subdir/asset", b"changed", &true)
+        );
+
+        let _ = fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn recooks_entries_of_other_versions() {
+        let directory =
+            env::temp_dir().join(format!("amethyst_assets_cooked_version_{}", process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        let cache = CookedCache::new(&directory);
+        let source = Directory::new(format!("{}/tests/assets", env!("CARGO_MANIFEST_DIR")));
+
+        assert!(cache
+            .cook::<Text, _>("subdir/asset", &UpperFormat, true, &source)
+            .unwrap());
+        let path = cache
+            .path::<Text, UpperFormat>("subdir/asset", &true)
+            .unwrap();
+        let mut header: Header = bincode::deserialize(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(MAGIC, header.magic);
+        header.version = VERSION + 1;
+        fs::write(&path, bincode::serialize(&header).unwrap()).unwrap();
+
+        assert!(cache
+            .cook::<Text, _>("subdir/asset", &UpperFormat, true, &source)
+            .unwrap());
+        assert_eq!(1, fs::read_dir(&directory).unwrap().count());
+
+        let _ = fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn entries_stay_in_cache_directory() {
+        let directory = env::temp_dir().join(format!("amethyst_assets_cooked_{}", process::id()));
+        let cache = CookedCache::new(&directory);
+
+        for name in &["../../escape", "/tmp/escape", "subdir/..", "C:\\escape"] {
+            let path = cache.path::<Text, UpperFormat>(name, &true).unwrap();
+            assert_eq!(Some(directory.as_path()), path.parent(), "{:?}", name);
+        }
+        assert_ne!(
+            cache.path::<Text, UpperFormat>("a/asset", &true).unwrap(),
+            cache.path::<Text, UpperFormat>("b/asset", &true).unwrap()
+        );
+    }
+}
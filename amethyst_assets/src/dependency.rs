@@ -0,0 +1,330 @@
+//! Tracks which assets were imported or processed using other assets, so hot reloading an
+//! asset can reload the assets depending on it.
+
+use std::{
+    any::TypeId,
+    cell::RefCell,
+    collections::VecDeque,
+    fmt::{self, Write},
+    sync::Arc,
+};
+
+use fnv::{FnvHashMap, FnvHashSet};
+use parking_lot::RwLock;
+
+use crate::{Asset, Handle};
+
+/// Identifies an asset of any type by the type and the id of its handle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AssetId {
+    asset: &'static str,
+    id: u32,
+    type_id: TypeId,
+}
+
+impl AssetId {
+    /// Gets the id of the asset a handle points to.
+    pub fn of<A: Asset>(handle: &Handle<A>) -> Self {
+        AssetId::new::<A>(handle.id())
+    }
+
+    pub(crate) fn new<A: Asset>(id: u32) -> Self {
+        AssetId {
+            asset: A::NAME,
+            id,
+            type_id: TypeId::of::<A>(),
+        }
+    }
+
+    /// Returns `Asset::NAME` of the asset type.
+    pub fn asset(&self) -> &'static str {
+        self.asset
+    }
+
+    /// Returns the id of the handle.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl fmt::Display for AssetId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}", self.asset, self.id)
+    }
+}
+
+#[derive(Default)]
+struct Node {
+    name: Option<String>,
+    dependencies: FnvHashSet<AssetId>,
+    dependents: FnvHashSet<AssetId>,
+}
+
+#[derive(Default)]
+struct Graph {
+    nodes: FnvHashMap<AssetId, Node>,
+    forced: FnvHashSet<AssetId>,
+}
+
+/// The dependencies between assets, owned by the `Loader`.
+///
+/// Dependencies are recorded automatically for assets loaded through the `Loader` while
+/// another asset is processed, like the sub assets loaded by `PrefabData::load_sub_assets`.
+/// A `Format` referencing other assets records them with `record_dependency`.
+///
+/// When an asset is hot reloaded, all assets depending on it directly or indirectly are
+/// reloaded as well.
+#[derive(Default)]
+pub struct AssetDependencies {
+    graph: RwLock<Graph>,
+}
+
+impl AssetDependencies {
+    /// Records that `dependent` was imported or processed using `dependency`.
+    pub fn record(&self, dependent: AssetId, dependency: AssetId) {
+        if dependent == dependency {
+            return;
+        }
+
+        let mut graph = self.graph.write();
+        graph
+            .nodes
+            .entry(dependent)
+            .or_insert_with(Node::default)
+            .dependencies
+            .insert(dependency);
+        graph
+            .nodes
+            .entry(dependency)
+            .or_insert_with(Node::default)
+            .dependents
+            .insert(dependent);
+    }
+
+    /// Returns the assets `asset` depends on, sorted by type and id.
+    pub fn dependencies(&self, asset: AssetId) -> Vec<AssetId> {
+        self.collect(asset, |node| &node.dependencies)
+    }
+
+    /// Returns the assets depending on `asset`, sorted by type and id.
+    pub fn dependents(&self, asset: AssetId) -> Vec<AssetId> {
+        self.collect(asset, |node| &node.dependents)
+    }
+
+    /// Returns the name an asset was loaded with.
+    pub fn name(&self, asset: AssetId) -> Option<String> {
+        self.graph
+            .read()
+            .nodes
+            .get(&asset)
+            .and_then(|node| node.name.clone())
+    }
+
+    /// Describes the dependencies of an asset as an indented tree, one asset per line.
+    pub fn describe(&self, asset: AssetId) -> String {
+        let graph = self.graph.read();
+        let mut description = String::new();
+        let mut path = Vec::new();
+        describe(&graph, asset, &mut path, &mut description);
+
+        description
+    }
+
+    fn collect<F>(&self, asset: AssetId, edges: F) -> Vec<AssetId>
+    where
+        F: Fn(&Node) -> &FnvHashSet<AssetId>,
+    {
+        let graph = self.graph.read();
+        let mut assets = graph
+            .nodes
+            .get(&asset)
+            .map(|node| edges(node).iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        assets.sort();
+
+        assets
+    }
+
+    pub(crate) fn set_name(&self, asset: AssetId, name: &str) {
+        self.graph
+            .write()
+            .nodes
+            .entry(asset)
+            .or_insert_with(Node::default)
+            .name = Some(name.to_owned());
+    }
+
+    /// Removes an asset whose handle was freed.
+    pub(crate) fn remove(&self, asset: AssetId) {
+        let mut graph = self.graph.write();
+        graph.forced.remove(&asset);
+        let node = match graph.nodes.remove(&asset) {
+            Some(node) => node,
+            None => return,
+        };
+        for dependency in &node.dependencies {
+            if let Some(other) = graph.nodes.get_mut(dependency) {
+                other.dependents.remove(&asset);
+            }
+        }
+        for dependent in &node.dependents {
+            if let Some(other) = graph.nodes.get_mut(dependent) {
+                other.dependencies.remove(&asset);
+            }
+        }
+    }
+
+    /// Marks every asset depending directly or indirectly on `asset` for a reload.
+    pub(crate) fn invalidate_dependents(&self, asset: AssetId) {
+        let mut graph = self.graph.write();
+        let mut visited = FnvHashSet::default();
+        let mut queue = VecDeque::new();
+        queue.push_back(asset);
+        while let Some(next) = queue.pop_front() {
+            if let Some(node) = graph.nodes.get(&next) {
+                for &dependent in &node.dependents {
+                    if dependent != asset && visited.insert(dependent) {
+                        queue.push_back(dependent);
+                    }
+                }
+            }
+        }
+
+        for dependent in &visited {
+            debug!("{} depends on {} and will be reloaded", dependent, asset);
+        }
+        graph.forced.extend(visited);
+    }
+
+    /// Takes the handle ids of the assets of type `A` marked for a reload.
+    pub(crate) fn take_forced<A: Asset>(&self) -> Vec<u32> {
+        let type_id = TypeId::of::<A>();
+        {
+            let graph = self.graph.read();
+            if !graph.forced.iter().any(|asset| asset.type_id == type_id) {
+                return Vec::new();
+            }
+        }
+
+        let mut graph = self.graph.write();
+        let mut ids = Vec::new();
+        graph.forced.retain(|asset| {
+            if asset.type_id == type_id {
+                ids.push(asset.id);
+                false
+            } else {
+                true
+            }
+        });
+
+        ids
+    }
+}
+
+fn describe(graph: &Graph, asset: AssetId, path: &mut Vec<AssetId>, out: &mut String) {
+    let _ = write!(out, "{:indent$}{}", "", asset, indent = path.len() * 2);
+    let node = graph.nodes.get(&asset);
+    if let Some(name) = node.and_then(|node| node.name.as_ref()) {
+        let _ = write!(out, " {:?}", name);
+    }
+    if path.contains(&asset) {
+        out.push_str(" (cycle)\n");
+        return;
+    }
+    out.push('\n');
+
+    if let Some(node) = node {
+        let mut dependencies = node.dependencies.iter().cloned().collect::<Vec<_>>();
+        dependencies.sort();
+        path.push(asset);
+        for dependency in dependencies {
+            describe(graph, dependency, path, out);
+        }
+        path.pop();
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Vec<(AssetId, Arc<AssetDependencies>)>> = RefCell::new(Vec::new());
+}
+
+/// Makes `asset` the asset dependencies are recorded for on this thread, until dropped.
+pub(crate) struct Scope;
+
+impl Scope {
+    pub(crate) fn enter(asset: AssetId, dependencies: Arc<AssetDependencies>) -> Self {
+        CURRENT.with(|current| current.borrow_mut().push((asset, dependencies)));
+        Scope
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.borrow_mut().pop());
+    }
+}
+
+/// Records that the asset currently imported by a `Format` or processed by an `AssetStorage`
+/// depends on the asset of `dependency`. Outside of importing or processing this does nothing.
+pub fn record_dependency<A: Asset>(dependency: &Handle<A>) {
+    record_current(AssetId::of(dependency));
+}
+
+pub(crate) fn record_current(dependency: AssetId) {
+    CURRENT.with(|current| {
+        if let Some(&(dependent, ref dependencies)) = current.borrow().last() {
+            dependencies.record(dependent, dependency);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use amethyst_core::specs::prelude::VecStorage;
+
+    use super::{AssetDependencies, AssetId};
+    use crate::{Asset, Handle};
+
+    struct Texture;
+
+    impl Asset for Texture {
+        const NAME: &'static str = "Texture";
+        type Data = ();
+        type HandleStorage = VecStorage<Handle<Self>>;
+    }
+
+    struct Sheet;
+
+    impl Asset for Sheet {
+        const NAME: &'static str = "Sheet";
+        type Data = ();
+        type HandleStorage = VecStorage<Handle<Self>>;
+    }
+
+    #[test]
+    fn invalidates_transitive_dependents() {
+        let dependencies = AssetDependencies::default();
+        let texture = AssetId::new::<Texture>(0);
+        let sheet = AssetId::new::<Sheet>(0);
+        let other_sheet = AssetId::new::<Sheet>(1);
+        dependencies.record(sheet, texture);
+        dependencies.record(other_sheet, sheet);
+        dependencies.set_name(texture, "logo.png");
+
+        assert_eq!(vec![sheet], dependencies.dependents(texture));
+        assert_eq!(
+            "Sheet#1\n  Sheet#0\n    Texture#0 \"logo.png\"\n",
+            dependencies.describe(other_sheet)
+        );
+
+        dependencies.invalidate_dependents(texture);
+        assert!(dependencies.take_forced::<Texture>().is_empty());
+        let mut forced = dependencies.take_forced::<Sheet>();
+        forced.sort();
+        assert_eq!(vec![0, 1], forced);
+        assert!(dependencies.take_forced::<Sheet>().is_empty());
+
+        dependencies.remove(sheet);
+        assert!(dependencies.dependents(texture).is_empty());
+    }
+}
@@ -0,0 +1,140 @@
+//! Assets and formats shared by the unit tests.
+
+use std::sync::mpsc::Sender;
+
+use amethyst_core::specs::prelude::VecStorage;
+use fnv::FnvHashMap;
+use parking_lot::Mutex;
+
+use crate::{
+    Asset, Error, Handle, ProcessingState, Progress, Result, SimpleFormat, Source, Tracker,
+};
+
+/// An asset storing the bytes it was loaded from.
+pub(crate) struct Bytes(pub Vec<u8>);
+
+impl Asset for Bytes {
+    const NAME: &'static str = "Bytes";
+    type Data = Vec<u8>;
+    type HandleStorage = VecStorage<Handle<Self>>;
+
+    fn size_bytes(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// Processes the data of a `Bytes` asset.
+pub(crate) fn loaded(data: Vec<u8>) -> Result<ProcessingState<Bytes>> {
+    Ok(ProcessingState::Loaded(Bytes(data)))
+}
+
+/// Imports the bytes of a file unchanged.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct BytesFormat;
+
+impl SimpleFormat<Bytes> for BytesFormat {
+    const NAME: &'static str = "BYTES";
+    type Options = ();
+
+    fn import(&self, bytes: Vec<u8>, _: ()) -> Result<Vec<u8>> {
+        Ok(bytes)
+    }
+}
+
+/// A text asset.
+pub(crate) struct Text;
+
+impl Asset for Text {
+    const NAME: &'static str = "Text";
+    type Data = String;
+    type HandleStorage = VecStorage<Handle<Self>>;
+}
+
+/// Imports a UTF-8 text file.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct TextFormat;
+
+impl SimpleFormat<Text> for TextFormat {
+    const NAME: &'static str = "TEXT";
+    type Options = ();
+
+    fn import(&self, bytes: Vec<u8>, _: ()) -> Result<String> {
+        Ok(String::from_utf8(bytes)?)
+    }
+}
+
+/// Imports a UTF-8 text file in upper case, trimmed if the options are `true`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct UpperFormat;
+
+impl SimpleFormat<Text> for UpperFormat {
+    const NAME: &'static str = "UPPER";
+    type Options = bool;
+
+    fn import(&self, bytes: Vec<u8>, trim: bool) -> Result<String> {
+        let text = String::from_utf8(bytes)?.to_uppercase();
+        Ok(if trim { text.trim().to_owned() } else { text })
+    }
+}
+
+/// A source keeping its assets in memory, with a modification time counting the changes.
+pub(crate) struct Memory(Mutex<FnvHashMap<String, (Vec<u8>, u64)>>);
+
+impl Memory {
+    pub(crate) fn new(assets: &[(&str, &str)]) -> Self {
+        let assets = assets
+            .iter()
+            .map(|&(path, data)| (path.to_owned(), (data.as_bytes().to_vec(), 1)))
+            .collect();
+
+        Memory(Mutex::new(assets))
+    }
+
+    /// Marks an asset as modified.
+    pub(crate) fn touch(&self, path: &str) {
+        if let Some(asset) = self.0.lock().get_mut(path) {
+            asset.1 += 1;
+        }
+    }
+}
+
+impl Source for Memory {
+    fn modified(&self, path: &str) -> Result<u64> {
+        self.0
+            .lock()
+            .get(path)
+            .map(|asset| asset.1)
+            .ok_or_else(|| "Missing asset".into())
+    }
+
+    fn load(&self, path: &str) -> Result<Vec<u8>> {
+        self.0
+            .lock()
+            .get(path)
+            .map(|asset| asset.0.clone())
+            .ok_or_else(|| "Missing asset".into())
+    }
+}
+
+/// Reports the end of a load to a channel, `true` if it succeeded.
+pub(crate) struct Finished(pub Sender<bool>);
+
+impl Progress for Finished {
+    type Tracker = Finished;
+
+    fn add_assets(&mut self, _: usize) {}
+
+    fn create_tracker(self) -> Finished {
+        self
+    }
+}
+
+impl Tracker for Finished {
+    fn success(self: Box<Self>) {
+        self.0.send(true).unwrap();
+    }
+
+    fn fail(self: Box<Self>, _: u32, _: &'static str, _: String, _: Error) {
+        self.0.send(false).unwrap();
+    }
+}
@@ -0,0 +1,341 @@
+use std::{any::Any, marker::PhantomData};
+
+use amethyst_core::specs::prelude::World;
+
+use crate::{
+    Asset, AssetStorage, Completion, Format, Handle, LoadPriority, Loader, ProgressCounter, Result,
+    ResultExt,
+};
+
+/// A list of assets which are loaded together, usually stored as a RON file like
+///
+/// ```ron
+/// (
+///     assets: [
+///         Texture((name: "texture/grass.png", format: Png, options: (...))),
+///         Mesh((name: "mesh/tree.obj", format: Obj, options: ())),
+///     ],
+/// )
+/// ```
+///
+/// Load a manifest with `AssetGroup::load_manifest`.
+///
+/// ### Type parameters:
+///
+/// - `T`: `ManifestAsset`, usually an enum with a `ManifestEntry` for every asset type
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct AssetManifest<T> {
+    /// The listed assets.
+    pub assets: Vec<T>,
+}
+
+/// An asset listed in an `AssetManifest`.
+///
+/// Implemented by `ManifestEntry`. For manifests with several asset types, define an enum
+/// with a `ManifestEntry` for every type and delegate to it:
+///
+/// ```rust,ignore
+/// #[derive(Deserialize)]
+/// enum LevelAsset {
+///     Texture(ManifestEntry<Texture, TextureFormat>),
+///     Mesh(ManifestEntry<Mesh, ObjFormat>),
+/// }
+///
+/// impl ManifestAsset for LevelAsset {
+///     fn load(&self, group: &mut AssetGroup, world: &World) {
+///         match *self {
+///             LevelAsset::Texture(ref entry) => entry.load(group, world),
+///             LevelAsset::Mesh(ref entry) => entry.load(group, world),
+///         }
+///     }
+/// }
+/// ```
+pub trait ManifestAsset {
+    /// Starts loading the asset, adding its handle to `group`.
+    fn load(&self, group: &mut AssetGroup, world: &World);
+}
+
+/// An asset of type `A` loaded with `Format` `F`, listed in an `AssetManifest`.
+///
+/// ### Type parameters:
+///
+/// - `A`: `Asset`,
+/// - `F`: `Format` for loading `A`
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ManifestEntry<A, F>
+where
+    A: Asset,
+    F: Format<A>,
+{
+    /// The name of the asset.
+    pub name: String,
+    /// The format to load the asset with.
+    pub format: F,
+    /// The options passed to the format.
+    pub options: F::Options,
+    /// The id of the source to load from, the default directory source if empty.
+    #[serde(default)]
+    pub source: String,
+    /// The priority of the load, `Normal` if not given.
+    #[serde(default)]
+    pub priority: LoadPriority,
+    #[serde(skip)]
+    marker: PhantomData<A>,
+}
+
+impl<A, F> ManifestEntry<A, F>
+where
+    A: Asset,
+    F: Format<A>,
+{
+    /// Creates an entry loading from the default source.
+    pub fn new<N>(name: N, format: F, options: F::Options) -> Self
+    where
+        N: Into<String>,
+    {
+        ManifestEntry {
+            name: name.into(),
+            format,
+            options,
+            source: String::new(),
+            priority: LoadPriority::Normal,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<A, F> ManifestAsset for ManifestEntry<A, F>
+where
+    A: Asset,
+    F: Format<A> + Clone,
+    F::Options: Clone,
+{
+    fn load(&self, group: &mut AssetGroup, world: &World) {
+        group.load_from_with_priority(
+            self.name.as_str(),
+            self.format.clone(),
+            self.options.clone(),
+            &self.source,
+            self.priority,
+            world,
+        );
+    }
+}
+
+/// A group of assets which are loaded and unloaded together, like the assets of a level.
+///
+/// The group keeps the handles of its assets alive and tracks the combined progress of
+/// loading them. Dropping the group, or calling `unload`, releases the handles; assets are then
+/// freed by their storages unless other handles to them exist.
+///
+/// ### Example
+///
+/// ```rust,ignore
+/// impl SimpleState for Level {
+///     fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+///         let mut group = AssetGroup::new();
+///         group
+///             .load_manifest::<LevelAsset>("level/forest.ron", "", data.world)
+///             .expect("Failed to load level manifest");
+///         self.assets = Some(group);
+///     }
+///
+///     fn on_stop(&mut self, _: StateData<'_, GameData<'_, '_>>) {
+///         // Releases all assets of the level
+///         self.assets = None;
+///     }
+/// }
+/// ```
+#[derive(Derivative, Default)]
+#[derivative(Debug)]
+pub struct AssetGroup {
+    #[derivative(Debug = "ignore")]
+    handles: Vec<Box<dyn Any + Send + Sync>>,
+    #[derivative(Debug = "ignore")]
+    progress: ProgressCounter,
+}
+
+impl AssetGroup {
+    /// Creates an empty group.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Reads a manifest from a source synchronously and starts loading all its assets.
+    ///
+    /// `source` is the id of the source, the default directory source if empty.
+    pub fn load_manifest<T>(&mut self, name: &str, source: &str, world: &World) -> Result<()>
+    where
+        T: ManifestAsset + for<'a> serde::Deserialize<'a>,
+    {
+        let bytes = world
+            .read_resource::<Loader>()
+            .source(source)
+            .load(name)
+            .chain_err(|| format!("Failed to read asset manifest {:?}", name))?;
+        let manifest: AssetManifest<T> = ron::de::from_bytes(&bytes)
+            .chain_err(|| format!("Failed to parse asset manifest {:?}", name))?;
+        self.load_assets(&manifest, world);
+
+        Ok(())
+    }
+
+    /// Starts loading all assets of a manifest.
+    pub fn load_assets<T>(&mut self, manifest: &AssetManifest<T>, world: &World)
+    where
+        T: ManifestAsset,
+    {
+        for asset in &manifest.assets {
+            asset.load(self, world);
+        }
+    }
+
+    /// Starts loading an asset from the default source and adds it to the group.
+    pub fn load<A, F, N>(
+        &mut self,
+        name: N,
+        format: F,
+        options: F::Options,
+        world: &World,
+    ) -> Handle<A>
+    where
+        A: Asset,
+        F: Format<A>,
+        N: Into<String>,
+    {
+        self.load_from(name, format, options, "", world)
+    }
+
+    /// Starts loading an asset from a source and adds it to the group, see
+    /// `Loader::load_from`.
+    pub fn load_from<A, F, N>(
+        &mut self,
+        name: N,
+        format: F,
+        options: F::Options,
+        source: &str,
+        world: &World,
+    ) -> Handle<A>
+    where
+        A: Asset,
+        F: Format<A>,
+        N: Into<String>,
+    {
+        self.load_from_with_priority(name, format, options, source, LoadPriority::Normal, world)
+    }
+
+    /// Starts loading an asset from a source with a priority and adds it to the group, see
+    /// `Loader::load_from_with_priority`.
+    pub fn load_from_with_priority<A, F, N>(
+        &mut self,
+        name: N,
+        format: F,
+        options: F::Options,
+        source: &str,
+        priority: LoadPriority,
+        world: &World,
+    ) -> Handle<A>
+    where
+        A: Asset,
+        F: Format<A>,
+        N: Into<String>,
+    {
+        let handle = world.read_resource::<Loader>().load_from_with_priority(
+            name,
+            format,
+            options,
+            source,
+            priority,
+            &mut self.progress,
+            &world.read_resource::<AssetStorage<A>>(),
+        );
+        self.insert(handle.clone());
+
+        handle
+    }
+
+    /// Adds the handle of an asset which was loaded separately.
+    pub fn insert<A: Asset>(&mut self, handle: Handle<A>) {
+        self.handles.push(Box::new(handle));
+    }
+
+    /// Returns the handles of the assets of type `A` in this group.
+    pub fn handles<A: Asset>(&self) -> impl Iterator<Item = &Handle<A>> {
+        self.handles
+            .iter()
+            .filter_map(|handle| handle.downcast_ref::<Handle<A>>())
+    }
+
+    /// Returns the number of assets in this group.
+    pub fn len(&self) -> usize {
+        self.handles.len()
+    }
+
+    /// Checks whether this group has no assets.
+    pub fn is_empty(&self) -> bool {
+        self.handles.is_empty()
+    }
+
+    /// Returns the combined progress of loading the assets of this group.
+    ///
+    /// Only assets loaded through the group are tracked.
+    pub fn progress(&self) -> &ProgressCounter {
+        &self.progress
+    }
+
+    /// Returns whether all assets loaded through the group finished loading.
+    pub fn complete(&self) -> Completion {
+        self.progress.complete()
+    }
+
+    /// Releases the handles of all assets in this group and resets its progress.
+    pub fn unload(&mut self) {
+        debug!("Unloading asset group of {} assets", self.handles.len());
+        self.handles.clear();
+        self.progress = ProgressCounter::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rayon::ThreadPoolBuilder;
+
+    use amethyst_core::specs::prelude::World;
+
+    use crate::{
+        fixtures::{Bytes, BytesFormat},
+        AssetStorage, Loader,
+    };
+
+    use super::{AssetGroup, AssetManifest, ManifestEntry};
+
+    #[test]
+    fn unload_releases_handles() {
+        let mut world = World::new();
+        let pool = Arc::new(ThreadPoolBuilder::default().build().unwrap());
+        world.add_resource(Loader::new(
+            format!("{}/tests/assets", env!("CARGO_MANIFEST_DIR")),
+            pool,
+        ));
+        world.add_resource(AssetStorage::<Bytes>::new());
+
+        let manifest: AssetManifest<ManifestEntry<Bytes, BytesFormat>> =
+            ron::de::from_str("(assets: [(name: \"subdir/asset\", format: (), options: ())])")
+                .unwrap();
+        let mut group = AssetGroup::new();
+        group.load_assets(&manifest, &world);
+
+        assert_eq!(1, group.handles::<Bytes>().count());
+
+        let handle = world.read_resource::<AssetStorage<Bytes>>().allocate();
+        let weak = handle.downgrade();
+        group.insert(handle);
+        assert_eq!(2, group.len());
+
+        group.unload();
+        assert!(group.is_empty());
+        assert!(weak.is_dead());
+    }
+}
@@ -10,6 +10,7 @@
 #![warn(missing_docs, rust_2018_idioms, rust_2018_compatibility)]
 
 use amethyst_core;
+use bincode;
 
 #[macro_use]
 extern crate derivative;
@@ -23,6 +24,8 @@ extern crate log;
 extern crate serde;
 #[cfg(feature = "json")]
 extern crate serde_json;
+#[cfg(feature = "zip")]
+extern crate zip;
 use shred;
 #[macro_use]
 extern crate shred_derive;
@@ -33,15 +36,21 @@ extern crate thread_profiler;
 
 pub use crate::{
     asset::{Asset, Format, FormatValue, SimpleFormat},
-    cache::Cache,
+    cache::{Cache, LruCache},
+    cook::CookedCache,
+    dependency::{record_dependency, AssetDependencies, AssetId},
     error::{Error, ErrorKind, Result, ResultExt},
     formats::RonFormat,
+    group::{AssetGroup, AssetManifest, ManifestAsset, ManifestEntry},
     helper::AssetLoaderSystemData,
-    loader::Loader,
+    loader::{LoadPriority, Loader},
+    memory::{AssetMemory, AssetMemoryUsage},
+    meta::{meta_path, AssetMeta, META_EXTENSION},
     prefab::{AssetPrefab, Prefab, PrefabData, PrefabError, PrefabLoader, PrefabLoaderSystem},
     progress::{Completion, Progress, ProgressCounter, Tracker},
+    registry::AssetFormatRegistry,
     reload::{HotReloadBundle, HotReloadStrategy, HotReloadSystem, Reload, SingleFile},
-    source::{Directory, Source},
+    source::{Archive, Directory, Overlay, PakWriter, Source},
     storage::{AssetStorage, Handle, ProcessingState, Processor, WeakHandle},
 };
 #[cfg(feature = "json")]
@@ -49,12 +58,20 @@ pub use formats::JsonFormat;
 
 mod asset;
 mod cache;
+mod cook;
+mod dependency;
 mod error;
+#[cfg(test)]
+mod fixtures;
 mod formats;
+mod group;
 mod helper;
 mod loader;
+mod memory;
+mod meta;
 mod prefab;
 mod progress;
+mod registry;
 mod reload;
 mod source;
 mod storage;
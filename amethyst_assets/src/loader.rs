@@ -1,19 +1,107 @@
-use std::{borrow::Borrow, hash::Hash, path::PathBuf, sync::Arc};
+use std::{
+    any::{Any, TypeId},
+    borrow::Borrow,
+    collections::VecDeque,
+    hash::Hash,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
 
 use fnv::FnvHashMap;
+use parking_lot::Mutex;
 use rayon::ThreadPool;
+use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
+    cook::{self, CookedCache, ImportCooked},
+    dependency::{self, AssetDependencies, AssetId, Scope},
+    meta,
+    registry::AssetFormatRegistry,
     storage::{AssetStorage, Handle, Processed},
-    Asset, Directory, ErrorKind, Format, FormatValue, Progress, ResultExt, Source,
+    Asset, Directory, ErrorKind, Format, FormatValue, Progress, Result, ResultExt, SimpleFormat,
+    Source,
 };
 
+/// The priority of a load request. Queued loads with a higher priority are started first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum LoadPriority {
+    /// Assets needed immediately, like the textures of the first frame's UI.
+    Critical,
+    /// The default priority.
+    Normal,
+    /// Assets which can arrive late, like background music.
+    Background,
+}
+
+impl Default for LoadPriority {
+    fn default() -> Self {
+        LoadPriority::Normal
+    }
+}
+
+// Boxed `FnOnce` closures cannot be called, so the job is an `FnMut` running once.
+type LoadJob = Box<dyn FnMut() + Send>;
+
+/// The loads waiting for a worker thread, one queue per `LoadPriority`.
+#[derive(Default)]
+pub(crate) struct LoadQueue {
+    jobs: Mutex<[VecDeque<LoadJob>; 3]>,
+}
+
+impl LoadQueue {
+    /// Queues a job and spawns a task on the `pool` running the queued job with the highest
+    /// priority.
+    pub(crate) fn spawn<F>(
+        queue: &Arc<LoadQueue>,
+        pool: &ThreadPool,
+        priority: LoadPriority,
+        job: F,
+    ) where
+        F: FnOnce() + Send + 'static,
+    {
+        let mut job = Some(job);
+        queue.push(
+            priority,
+            Box::new(move || {
+                if let Some(job) = job.take() {
+                    job()
+                }
+            }),
+        );
+        let queue = queue.clone();
+        pool.spawn(move || queue.run_next());
+    }
+
+    fn push(&self, priority: LoadPriority, job: LoadJob) {
+        self.jobs.lock()[priority as usize].push_back(job);
+    }
+
+    /// Runs the queued load with the highest priority.
+    fn run_next(&self) {
+        let job = self
+            .jobs
+            .lock()
+            .iter_mut()
+            .filter_map(VecDeque::pop_front)
+            .next();
+        if let Some(mut job) = job {
+            job();
+        }
+    }
+}
+
 /// The asset loader, holding the sources and a reference to the `ThreadPool`.
 pub struct Loader {
+    cooked: Option<Arc<CookedCache>>,
+    cooked_formats: FnvHashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    dependencies: Arc<AssetDependencies>,
     directory: Arc<Directory>,
     hot_reload: bool,
     pool: Arc<ThreadPool>,
+    queue: Arc<LoadQueue>,
     sources: FnvHashMap<String, Arc<dyn Source>>,
+    watch: Option<Duration>,
 }
 
 impl Loader {
@@ -24,10 +112,15 @@ impl Loader {
         P: Into<PathBuf>,
     {
         Loader {
+            cooked: None,
+            cooked_formats: Default::default(),
+            dependencies: Default::default(),
             directory: Arc::new(Directory::new(directory)),
             hot_reload: true,
             pool,
+            queue: Default::default(),
             sources: Default::default(),
+            watch: None,
         }
     }
 
@@ -37,8 +130,11 @@ impl Loader {
         I: Into<String>,
         S: Source,
     {
-        self.sources
-            .insert(id.into(), Arc::new(source) as Arc<dyn Source>);
+        let id = id.into();
+        if let Some(debounce) = self.watch {
+            watch_source(&id, &source, debounce);
+        }
+        self.sources.insert(id, Arc::new(source) as Arc<dyn Source>);
     }
 
     /// If set to `true`, this `Loader` will ask formats to
@@ -50,6 +146,41 @@ impl Loader {
         self.hot_reload = value;
     }
 
+    /// Sets the cache of cooked assets used for the formats registered with `register_cooked`.
+    pub fn set_cooked_cache(&mut self, cache: CookedCache) {
+        self.cooked = Some(Arc::new(cache));
+    }
+
+    /// Makes loads of assets of type `A` with format `F` use the cooked data from the
+    /// `CookedCache` if it has a valid entry, skipping the import.
+    ///
+    /// The source of the asset is still read to check the entry is up to date, and assets
+    /// without a valid entry are imported by the format as usual.
+    pub fn register_cooked<A, F>(&mut self)
+    where
+        A: Asset,
+        A::Data: DeserializeOwned,
+        F: SimpleFormat<A> + Clone + Send + Sync + 'static,
+        F::Options: Serialize,
+    {
+        let import: ImportCooked<A, F> = cook::import_cooked::<A, F>;
+        self.cooked_formats
+            .insert(TypeId::of::<(A, F)>(), Box::new(import));
+    }
+
+    /// Returns the dependencies recorded between the loaded assets.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust,ignore
+    /// // Prints the assets a prefab was loaded with.
+    /// let dependencies = loader.dependencies();
+    /// println!("{}", dependencies.describe(AssetId::of(&prefab_handle)));
+    /// ```
+    pub fn dependencies(&self) -> &AssetDependencies {
+        &self.dependencies
+    }
+
     /// Loads an asset with a given format from the default (directory) source.
     /// If you want to load from a custom source instead, use `load_from`.
     ///
@@ -73,9 +204,35 @@ impl Loader {
         self.load_from::<A, F, _, _, _>(name, format, options, "", progress, storage)
     }
 
+    /// Loads an asset with a given format from the default (directory) source, started before
+    /// queued loads of a lower priority.
+    ///
+    /// See `load_from_with_priority` for more information.
+    pub fn load_with_priority<A, F, N, P>(
+        &self,
+        name: N,
+        format: F,
+        options: F::Options,
+        priority: LoadPriority,
+        progress: P,
+        storage: &AssetStorage<A>,
+    ) -> Handle<A>
+    where
+        A: Asset,
+        F: Format<A>,
+        N: Into<String>,
+        P: Progress,
+    {
+        self.load_from_with_priority::<A, F, _, _, _>(
+            name, format, options, "", priority, progress, storage,
+        )
+    }
+
     /// Loads an asset with a given id and format from a custom source.
     /// The actual work is done in a worker thread, thus this method immediately returns a handle.
     ///
+    /// The load has `LoadPriority::Normal`, see `load_from_with_priority`.
+    ///
     /// ## Parameters
     ///
     /// * `name`: this is just an identifier for the asset, most likely a file name e.g.
@@ -94,7 +251,43 @@ impl Loader {
         format: F,
         options: F::Options,
         source: &S,
-        mut progress: P,
+        progress: P,
+        storage: &AssetStorage<A>,
+    ) -> Handle<A>
+    where
+        A: Asset,
+        F: Format<A> + 'static,
+        N: Into<String>,
+        P: Progress,
+        S: AsRef<str> + Eq + Hash + ?Sized,
+        String: Borrow<S>,
+    {
+        self.load_from_with_priority(
+            name,
+            format,
+            options,
+            source,
+            LoadPriority::Normal,
+            progress,
+            storage,
+        )
+    }
+
+    /// Loads an asset with a given id and format from a custom source, like `load_from`.
+    ///
+    /// Loads are queued and started by the worker threads in order of their priority, then in
+    /// the order they were requested. If all handles to the asset are dropped before its
+    /// import is started, the load is cancelled; cancelled loads count as finished for the
+    /// `progress`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn load_from_with_priority<A, F, N, P, S>(
+        &self,
+        name: N,
+        format: F,
+        options: F::Options,
+        source: &S,
+        priority: LoadPriority,
+        progress: P,
         storage: &AssetStorage<A>,
     ) -> Handle<A>
     where
@@ -104,62 +297,238 @@ impl Loader {
         P: Progress,
         S: AsRef<str> + Eq + Hash + ?Sized,
         String: Borrow<S>,
+    {
+        let cooked = self.cooked.clone().and_then(|cache| {
+            self.cooked_formats
+                .get(&TypeId::of::<(A, F)>())
+                .and_then(|import| import.downcast_ref::<ImportCooked<A, F>>())
+                .map(|&import| (cache, import))
+        });
+
+        self.load_with(
+            name.into(),
+            source.as_ref(),
+            F::NAME,
+            priority,
+            progress,
+            storage,
+            move |name, source, create_reload| match cooked {
+                Some((cache, import)) => {
+                    import(&cache, &format, name, source, options, create_reload)
+                }
+                None => format.import(name, source, options, create_reload),
+            },
+        )
+    }
+
+    /// Loads an asset from the default (directory) source with the format and options declared
+    /// in its `.meta` file, see `AssetMeta`.
+    ///
+    /// `F` is usually a format enum covering all formats the asset could have, like
+    /// `TextureFormat`. If the meta file is missing or invalid, the load fails. Hot reloading
+    /// the asset also picks up changes of the meta file.
+    pub fn load_auto<A, F, N, P>(
+        &self,
+        name: N,
+        progress: P,
+        storage: &AssetStorage<A>,
+    ) -> Handle<A>
+    where
+        A: Asset,
+        F: Format<A> + DeserializeOwned,
+        F::Options: DeserializeOwned,
+        N: Into<String>,
+        P: Progress,
+    {
+        self.load_from_auto::<A, F, _, _, _>(name, "", LoadPriority::Normal, progress, storage)
+    }
+
+    /// Loads an asset from a custom source with the format and options declared in its
+    /// `.meta` file, like `load_auto`.
+    pub fn load_from_auto<A, F, N, P, S>(
+        &self,
+        name: N,
+        source: &S,
+        priority: LoadPriority,
+        progress: P,
+        storage: &AssetStorage<A>,
+    ) -> Handle<A>
+    where
+        A: Asset,
+        F: Format<A> + DeserializeOwned,
+        F::Options: DeserializeOwned,
+        N: Into<String>,
+        P: Progress,
+        S: AsRef<str> + Eq + Hash + ?Sized,
+        String: Borrow<S>,
+    {
+        self.load_with(
+            name.into(),
+            source.as_ref(),
+            F::NAME,
+            priority,
+            progress,
+            storage,
+            meta::import_with_meta::<A, F>,
+        )
+    }
+
+    /// Loads an asset from the default (directory) source with the format registered for the
+    /// extension of its name in `registry`, like `"sprites/hero.png"`.
+    ///
+    /// If no format is registered for the extension, the load fails.
+    pub fn load_by_extension<A, N, P>(
+        &self,
+        name: N,
+        registry: &AssetFormatRegistry,
+        progress: P,
+        storage: &AssetStorage<A>,
+    ) -> Handle<A>
+    where
+        A: Asset,
+        N: Into<String>,
+        P: Progress,
+    {
+        self.load_from_by_extension(name, registry, "", LoadPriority::Normal, progress, storage)
+    }
+
+    /// Loads an asset from a custom source with the format registered for the extension of
+    /// its name, like `load_by_extension`.
+    pub fn load_from_by_extension<A, N, P, S>(
+        &self,
+        name: N,
+        registry: &AssetFormatRegistry,
+        source: &S,
+        priority: LoadPriority,
+        progress: P,
+        storage: &AssetStorage<A>,
+    ) -> Handle<A>
+    where
+        A: Asset,
+        N: Into<String>,
+        P: Progress,
+        S: AsRef<str> + Eq + Hash + ?Sized,
+        String: Borrow<S>,
+    {
+        let name = name.into();
+        let format = registry.format::<A>(&name);
+        let format_name = format
+            .as_ref()
+            .map_or("[unregistered]", |format| format.name);
+
+        self.load_with(
+            name,
+            source.as_ref(),
+            format_name,
+            priority,
+            progress,
+            storage,
+            move |name, source, create_reload| match format {
+                Some(format) => (format.import)(name, source, create_reload),
+                None => Err(format!(
+                    "No format is registered for the extension of {:?} asset {:?}",
+                    A::NAME,
+                    name,
+                )
+                .into()),
+            },
+        )
+    }
+
+    /// Queues a load of an asset, which is imported by `import` on a worker thread.
+    #[allow(clippy::too_many_arguments)]
+    fn load_with<A, P, I>(
+        &self,
+        name: String,
+        source: &str,
+        format_name: &'static str,
+        priority: LoadPriority,
+        mut progress: P,
+        storage: &AssetStorage<A>,
+        import: I,
+    ) -> Handle<A>
+    where
+        A: Asset,
+        P: Progress,
+        I: FnOnce(String, Arc<dyn Source>, bool) -> Result<FormatValue<A>> + Send + 'static,
     {
         #[cfg(feature = "profiler")]
         profile_scope!("load_asset_from");
         use crate::progress::Tracker;
 
-        let name = name.into();
-        let source = source.as_ref();
-
-        let format_name = F::NAME;
         let source_name = match source {
             "" => "[default source]",
             other => other,
         };
 
         let handle = storage.allocate();
+        let id = AssetId::of(&handle);
+        self.dependencies.set_name(id, &name);
+        // Assets loaded while processing another asset are its dependencies.
+        dependency::record_current(id);
 
         debug!(
-            "{:?}: Loading asset {:?} with format {:?} from source {:?} (handle id: {:?})",
+            "{:?}: Loading asset {:?} with format {:?} from source {:?} (handle id: {:?}, \
+             priority: {:?})",
             A::NAME,
             name,
             format_name,
             source_name,
             handle,
+            priority,
         );
 
-        let source = match source {
-            "" => self.directory.clone(),
-            source => self.source(source),
-        };
+        let source_id = source.to_owned();
+        let source = self.source(source);
 
         progress.add_assets(1);
         let tracker = progress.create_tracker();
 
-        let handle_clone = handle.clone();
+        // The queued load doesn't keep the asset alive, so dropping all handles cancels it.
+        let handle_id = handle.id();
+        let weak_handle = handle.downgrade();
         let processed = storage.processed.clone();
 
         let hot_reload = self.hot_reload;
+        let dependencies = self.dependencies.clone();
+        let queue = self.queue.clone();
 
         let cl = move || {
             #[cfg(feature = "profiler")]
             profile_scope!("load_asset_from_worker");
-            let data = format
-                .import(name.clone(), source, options, hot_reload)
-                .chain_err(|| ErrorKind::Format(F::NAME));
             let tracker = Box::new(tracker) as Box<dyn Tracker>;
+            let handle = match weak_handle.upgrade() {
+                Some(handle) => handle,
+                None => {
+                    debug!(
+                        "{:?}: Cancelled loading asset {:?}, all handles were dropped",
+                        A::NAME,
+                        name,
+                    );
+                    dependencies.remove(id);
+                    processed.push(Processed::Cancelled(handle_id));
+                    tracker.success();
+                    return;
+                }
+            };
+
+            let _scope = Scope::enter(id, dependencies.clone());
+            let data = import(name.clone(), source, hot_reload)
+                .chain_err(|| ErrorKind::Format(format_name));
 
             processed.push(Processed::NewAsset {
                 data,
                 handle,
                 name,
+                source: source_id,
                 tracker,
+                dependencies,
+                queue,
             });
         };
-        self.pool.spawn(cl);
+        LoadQueue::spawn(&self.queue, &self.pool, priority, cl);
 
-        handle_clone
+        handle
     }
 
     /// Load an asset from data and return a handle.
@@ -177,20 +546,132 @@ impl Loader {
         let tracker = progress.create_tracker();
         let tracker = Box::new(tracker);
         let handle = storage.allocate();
+        dependency::record_current(AssetId::of(&handle));
         storage.processed.push(Processed::NewAsset {
             data: Ok(FormatValue::data(data)),
             handle: handle.clone(),
             name: "<Data>".into(),
+            source: String::new(),
             tracker,
+            dependencies: self.dependencies.clone(),
+            queue: self.queue.clone(),
         });
 
         handle
     }
 
-    fn source(&self, source: &str) -> Arc<dyn Source> {
-        self.sources
-            .get(source)
-            .expect("No such source. Maybe you forgot to add it with `Loader::add_source`?")
-            .clone()
+    /// Starts watching all sources for changes, including sources added later.
+    /// Sources which cannot be watched are skipped.
+    pub(crate) fn watch_sources(&mut self, debounce: Duration) {
+        self.watch = Some(debounce);
+        watch_source("[default source]", &*self.directory, debounce);
+        for (id, source) in &self.sources {
+            watch_source(id, &**source, debounce);
+        }
+    }
+
+    /// Takes the names of the assets which changed in any watched source, together with the
+    /// id of the source, `""` being the default directory source.
+    pub(crate) fn take_changes(&self) -> Vec<(String, String)> {
+        let mut changes = self
+            .directory
+            .take_changes()
+            .into_iter()
+            .map(|name| (String::new(), name))
+            .collect::<Vec<_>>();
+        for (id, source) in &self.sources {
+            changes.extend(
+                source
+                    .take_changes()
+                    .into_iter()
+                    .map(|name| (id.clone(), name)),
+            );
+        }
+
+        changes
+    }
+
+    /// Gets a source by its id, `""` being the default directory source.
+    pub(crate) fn source(&self, source: &str) -> Arc<dyn Source> {
+        match source {
+            "" => self.directory.clone(),
+            source => self
+                .sources
+                .get(source)
+                .expect("No such source. Maybe you forgot to add it with `Loader::add_source`?")
+                .clone(),
+        }
+    }
+}
+
+fn watch_source(id: &str, source: &dyn Source, debounce: Duration) {
+    match source.watch(debounce) {
+        Ok(true) => debug!("Watching source {:?} for changes", id),
+        Ok(false) => debug!(
+            "Source {:?} cannot be watched, its assets will be polled",
+            id
+        ),
+        Err(e) => warn!(
+            "Failed to watch source {:?}, its assets will be polled: {}",
+            id, e
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{mpsc::channel, Arc};
+
+    use parking_lot::Mutex;
+    use rayon::ThreadPoolBuilder;
+
+    use crate::{
+        fixtures::{loaded, Bytes, BytesFormat, Finished},
+        AssetStorage,
+    };
+
+    use super::{LoadPriority, LoadQueue, Loader};
+
+    #[test]
+    fn runs_higher_priority_first() {
+        let queue = LoadQueue::default();
+        let order = Arc::new(Mutex::new(Vec::new()));
+        for &(priority, name) in &[
+            (LoadPriority::Background, "music"),
+            (LoadPriority::Normal, "level"),
+            (LoadPriority::Critical, "font"),
+            (LoadPriority::Normal, "player"),
+        ] {
+            let order = order.clone();
+            queue.push(priority, Box::new(move || order.lock().push(name)));
+        }
+        for _ in 0..5 {
+            queue.run_next();
+        }
+
+        assert_eq!(vec!["font", "level", "player", "music"], *order.lock());
+    }
+
+    #[test]
+    fn cancelled_load_frees_handle_id() {
+        // A single thread, blocked until the handle was dropped.
+        let pool = Arc::new(ThreadPoolBuilder::new().num_threads(1).build().unwrap());
+        let (unblock, blocked) = channel::<()>();
+        pool.spawn(move || blocked.recv().unwrap());
+
+        let loader = Loader::new(
+            format!("{}/tests/assets", env!("CARGO_MANIFEST_DIR")),
+            pool.clone(),
+        );
+        let mut storage = AssetStorage::<Bytes>::new();
+        let (sender, finished) = channel();
+        let handle = loader.load("subdir/asset", BytesFormat, (), Finished(sender), &storage);
+        let id = handle.id();
+        drop(handle);
+        unblock.send(()).unwrap();
+        assert!(finished.recv().unwrap());
+
+        storage.process(loaded, 0, &pool, None);
+        assert_eq!(id, storage.allocate().id());
     }
 }
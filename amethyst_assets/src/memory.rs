@@ -0,0 +1,139 @@
+use std::{any::TypeId, mem};
+
+use fnv::FnvHashMap;
+use parking_lot::RwLock;
+
+use crate::{Asset, AssetStorage};
+
+/// The memory used by the assets of one type.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AssetMemoryUsage {
+    /// The number of loaded assets.
+    pub assets: usize,
+    /// The memory used by the loaded assets in bytes, according to `Asset::size_bytes`.
+    pub bytes: usize,
+}
+
+/// A resource reporting the memory used by each asset storage, updated by the `Processor`
+/// of every asset type.
+///
+/// The usage is locked internally, so all processors can update it in parallel.
+#[derive(Debug, Default)]
+pub struct AssetMemory {
+    usage: RwLock<FnvHashMap<TypeId, (&'static str, AssetMemoryUsage)>>,
+}
+
+impl AssetMemory {
+    /// Creates an empty report.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Records the current memory use of a storage.
+    pub fn update<A: Asset>(&self, storage: &AssetStorage<A>) {
+        let usage = AssetMemoryUsage {
+            assets: storage.len(),
+            bytes: storage.memory_usage(),
+        };
+        self.usage
+            .write()
+            .insert(TypeId::of::<A>(), (A::NAME, usage));
+    }
+
+    /// Returns the memory used by the assets of type `A`.
+    pub fn get<A: Asset>(&self) -> Option<AssetMemoryUsage> {
+        self.usage
+            .read()
+            .get(&TypeId::of::<A>())
+            .map(|&(_, usage)| usage)
+    }
+
+    /// Returns the memory used by all asset types with their `Asset::NAME`, sorted by the name.
+    pub fn usage(&self) -> Vec<(&'static str, AssetMemoryUsage)> {
+        let mut usage = self.usage.read().values().cloned().collect::<Vec<_>>();
+        usage.sort_by_key(|&(name, _)| name);
+
+        usage
+    }
+
+    /// Returns the memory used by the assets of all types in bytes.
+    pub fn total_bytes(&self) -> usize {
+        self.usage.read().values().map(|usage| usage.bytes).sum()
+    }
+}
+
+/// The sizes of the assets in a storage by handle id, updated whenever an asset is inserted,
+/// replaced or freed, so the total doesn't have to be recomputed every frame.
+#[derive(Debug, Default)]
+pub(crate) struct AssetSizes {
+    sizes: Vec<usize>,
+    total: usize,
+    generation: u64,
+}
+
+impl AssetSizes {
+    /// Records the size of the asset with the handle id `id`, `0` if it was freed.
+    pub(crate) fn set(&mut self, id: u32, size: usize) {
+        let id = id as usize;
+        if id >= self.sizes.len() {
+            self.sizes.resize(id + 1, 0);
+        }
+        let old = mem::replace(&mut self.sizes[id], size);
+        if old != size {
+            self.total = self.total - old + size;
+            self.generation += 1;
+        }
+    }
+
+    /// Returns the size of the asset with the handle id `id`.
+    pub(crate) fn get(&self, id: u32) -> usize {
+        self.sizes.get(id as usize).cloned().unwrap_or(0)
+    }
+
+    /// Returns the size of all assets.
+    pub(crate) fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Returns a counter which changes whenever a size changes.
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amethyst_core::specs::prelude::VecStorage;
+
+    use crate::{Asset, AssetStorage, Handle};
+
+    use super::{AssetMemory, AssetMemoryUsage};
+
+    struct First;
+
+    impl Asset for First {
+        const NAME: &'static str = "Same";
+        type Data = ();
+        type HandleStorage = VecStorage<Handle<Self>>;
+    }
+
+    struct Second;
+
+    impl Asset for Second {
+        const NAME: &'static str = "Same";
+        type Data = ();
+        type HandleStorage = VecStorage<Handle<Self>>;
+    }
+
+    #[test]
+    fn asset_types_with_the_same_name() {
+        let memory = AssetMemory::new();
+        memory.update(&AssetStorage::<First>::new());
+        memory.update(&AssetStorage::<Second>::new());
+
+        let empty = AssetMemoryUsage::default();
+        assert_eq!(Some(empty), memory.get::<First>());
+        assert_eq!(Some(empty), memory.get::<Second>());
+        assert_eq!(vec![("Same", empty), ("Same", empty)], memory.usage());
+    }
+}
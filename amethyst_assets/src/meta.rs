@@ -0,0 +1,198 @@
+//! Sidecar `.meta` files declaring the format and options an asset is imported with.
+
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+
+use crate::{Asset, ErrorKind, Format, FormatValue, Reload, Result, ResultExt, Source};
+
+/// The extension of meta files. The meta file of an asset is named like the asset with this
+/// extension appended, like `texture/logo.png.meta`.
+pub const META_EXTENSION: &str = "meta";
+
+/// Returns the name of the meta file of asset `name`.
+pub fn meta_path(name: &str) -> String {
+    format!("{}.{}", name, META_EXTENSION)
+}
+
+/// The contents of the `.meta` file next to an asset, a RON file declaring the format and
+/// the options to import the asset with, like
+///
+/// ```ron
+/// (
+///     format: Png,
+///     options: (
+///         mip_levels: 4,
+///         channel: Srgb,
+///     ),
+/// )
+/// ```
+///
+/// Assets with a meta file are loaded with `Loader::load_auto`, so the import settings are
+/// declared in one place and can be changed without changing code.
+///
+/// ### Type parameters:
+///
+/// - `F`: The format, usually an enum covering several formats like `TextureFormat`
+/// - `O`: The options of the format
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AssetMeta<F, O> {
+    /// The format to import the asset with.
+    pub format: F,
+    /// The options passed to the format.
+    pub options: O,
+}
+
+impl<F, O> AssetMeta<F, O>
+where
+    F: DeserializeOwned,
+    O: DeserializeOwned,
+{
+    /// Reads the meta file of asset `name` from a source.
+    pub fn load(source: &dyn Source, name: &str) -> Result<Self> {
+        let path = meta_path(name);
+        let bytes = source.load(&path).chain_err(|| ErrorKind::Source)?;
+        ron::de::from_bytes(&bytes).chain_err(|| format!("Failed to parse meta file {:?}", path))
+    }
+}
+
+type ImportMeta<A> = fn(String, Arc<dyn Source>, bool) -> Result<FormatValue<A>>;
+
+/// Imports an asset with the format and options of its meta file.
+pub(crate) fn import_with_meta<A, F>(
+    name: String,
+    source: Arc<dyn Source>,
+    create_reload: bool,
+) -> Result<FormatValue<A>>
+where
+    A: Asset,
+    F: Format<A> + DeserializeOwned,
+    F::Options: DeserializeOwned,
+{
+    let meta = AssetMeta::<F, F::Options>::load(&*source, &name)?;
+    let meta_modified = if create_reload {
+        source.modified(&meta_path(&name)).unwrap_or(0)
+    } else {
+        0
+    };
+    let FormatValue { data, reload } =
+        meta.format
+            .import(name.clone(), source.clone(), meta.options, create_reload)?;
+    let reload = reload.map(|inner| {
+        Box::new(MetaReload {
+            import: import_with_meta::<A, F>,
+            inner,
+            meta_modified,
+            name,
+            source,
+        }) as Box<dyn Reload<A>>
+    });
+
+    Ok(FormatValue { data, reload })
+}
+
+/// Wraps the reload object of an asset loaded with a meta file, so changes of the meta file
+/// reload the asset as well.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""))]
+struct MetaReload<A: Asset> {
+    import: ImportMeta<A>,
+    inner: Box<dyn Reload<A>>,
+    meta_modified: u64,
+    name: String,
+    source: Arc<dyn Source>,
+}
+
+impl<A: Asset> Reload<A> for MetaReload<A> {
+    fn needs_reload(&self) -> bool {
+        self.inner.needs_reload()
+            || (self.meta_modified != 0
+                && self.source.modified(&meta_path(&self.name)).unwrap_or(0) > self.meta_modified)
+    }
+
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn format(&self) -> &'static str {
+        self.inner.format()
+    }
+
+    fn is_watched(&self) -> bool {
+        self.inner.is_watched()
+    }
+
+    fn reload(self: Box<Self>) -> Result<FormatValue<A>> {
+        (self.import)(self.name, self.source, true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{mpsc::channel, Arc};
+
+    use rayon::ThreadPoolBuilder;
+
+    use crate::{
+        fixtures::{loaded, Bytes, BytesFormat, Finished, Memory},
+        AssetStorage, Directory, Loader, RonFormat, Source,
+    };
+
+    use super::{import_with_meta, meta_path, AssetMeta};
+
+    #[test]
+    fn reads_meta_file() {
+        let source = Directory::new(format!("{}/tests/assets", env!("CARGO_MANIFEST_DIR")));
+        assert_eq!("subdir/asset.meta", meta_path("subdir/asset"));
+
+        assert!(AssetMeta::<RonFormat, ()>::load(&source, "subdir/asset").is_ok());
+        assert!(AssetMeta::<RonFormat, ()>::load(&source, "missing").is_err());
+    }
+
+    #[test]
+    fn loads_with_meta_file() {
+        // A single thread runs the queued loads in order.
+        let pool = Arc::new(ThreadPoolBuilder::new().num_threads(1).build().unwrap());
+        let loader = Loader::new(
+            format!("{}/tests/assets", env!("CARGO_MANIFEST_DIR")),
+            pool.clone(),
+        );
+        let mut storage = AssetStorage::<Bytes>::new();
+        let (sender, finished) = channel();
+        let handle = loader.load_auto::<Bytes, BytesFormat, _, _>(
+            "subdir/asset",
+            Finished(sender.clone()),
+            &storage,
+        );
+        let missing =
+            loader.load_auto::<Bytes, BytesFormat, _, _>("missing", Finished(sender), &storage);
+        // Runs after the loads queued before.
+        pool.install(|| ());
+        storage.process(loaded, 0, &pool, None);
+
+        assert_eq!(vec![true, false], finished.try_iter().collect::<Vec<_>>());
+        assert_eq!(
+            Some(&b"data"[..]),
+            storage.get(&handle).map(|bytes| &bytes.0[..])
+        );
+        assert!(storage.get(&missing).is_none());
+    }
+
+    #[test]
+    fn reloads_when_meta_file_changes() {
+        let memory = Arc::new(Memory::new(&[
+            ("asset", "data"),
+            ("asset.meta", "(format: BytesFormat, options: ())"),
+        ]));
+        let source = memory.clone() as Arc<dyn Source>;
+        let reload = import_with_meta::<Bytes, BytesFormat>("asset".into(), source, true)
+            .unwrap()
+            .reload
+            .unwrap();
+        assert!(!reload.needs_reload());
+
+        memory.touch("asset.meta");
+        assert!(reload.needs_reload());
+        assert_eq!(b"data".to_vec(), reload.reload().unwrap().data);
+    }
+}
@@ -40,6 +40,9 @@ pub trait PrefabData<'a> {
 
     /// Trigger asset loading for any sub assets.
     ///
+    /// Assets loaded through the `Loader` are recorded as dependencies of the prefab, so the
+    /// prefab is reloaded when one of them is hot reloaded.
+    ///
     /// ### Parameters:
     ///
     /// - `progress`: Progress structure that needs to be used for tracking progress of sub loads
@@ -0,0 +1,161 @@
+use std::{
+    any::{Any, TypeId},
+    path::Path,
+    sync::Arc,
+};
+
+use fnv::FnvHashMap;
+
+use crate::{Asset, Format, FormatValue, Result, Source};
+
+type Import<A> = dyn Fn(String, Arc<dyn Source>, bool) -> Result<FormatValue<A>> + Send + Sync;
+
+/// A format registered for an extension, together with the options it is used with.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""))]
+pub(crate) struct RegisteredFormat<A: Asset> {
+    pub(crate) name: &'static str,
+    pub(crate) import: Arc<Import<A>>,
+}
+
+/// A resource mapping file extensions to formats for each asset type, used by
+/// `Loader::load_by_extension` to load assets without naming a format.
+///
+/// Every format is registered with the options it is used with. Extensions are matched
+/// case-insensitively and a later registration of an extension replaces the earlier one, so
+/// plugins can add or override formats at runtime.
+///
+/// ### Example
+///
+/// ```rust,ignore
+/// world
+///     .write_resource::<AssetFormatRegistry>()
+///     .register::<Texture, _>(&["png"], PngFormat, TextureMetadata::srgb_scale());
+///
+/// let handle = loader.load_by_extension(
+///     "sprites/hero.png",
+///     &world.read_resource::<AssetFormatRegistry>(),
+///     (),
+///     &world.read_resource::<AssetStorage<Texture>>(),
+/// );
+/// ```
+#[derive(Derivative, Default)]
+#[derivative(Debug)]
+pub struct AssetFormatRegistry {
+    #[derivative(Debug = "ignore")]
+    formats: FnvHashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl AssetFormatRegistry {
+    /// Creates a registry without any formats.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Registers a format, see `register`.
+    pub fn with<A, F>(mut self, extensions: &[&str], format: F, options: F::Options) -> Self
+    where
+        A: Asset,
+        F: Format<A> + Sync,
+        F::Options: Clone + Sync,
+    {
+        self.register::<A, F>(extensions, format, options);
+        self
+    }
+
+    /// Registers a format for loading assets of type `A` whose name has one of `extensions`,
+    /// given without the leading dot.
+    pub fn register<A, F>(&mut self, extensions: &[&str], format: F, options: F::Options)
+    where
+        A: Asset,
+        F: Format<A> + Sync,
+        F::Options: Clone + Sync,
+    {
+        let import = move |name, source, create_reload| {
+            format.import(name, source, options.clone(), create_reload)
+        };
+        let registered = RegisteredFormat {
+            name: F::NAME,
+            import: Arc::new(import) as Arc<Import<A>>,
+        };
+
+        let formats = self.formats_mut::<A>();
+        for extension in extensions {
+            let extension = extension.to_lowercase();
+            if let Some(previous) = formats.insert(extension.clone(), registered.clone()) {
+                debug!(
+                    "{:?}: Format {:?} replaces {:?} for extension {:?}",
+                    A::NAME,
+                    F::NAME,
+                    previous.name,
+                    extension,
+                );
+            }
+        }
+    }
+
+    /// Removes the format registered for an extension of assets of type `A`.
+    pub fn unregister<A: Asset>(&mut self, extension: &str) -> bool {
+        self.formats_mut::<A>()
+            .remove(&extension.to_lowercase())
+            .is_some()
+    }
+
+    /// Returns the name of the format an asset of type `A` named `name` would be loaded with.
+    pub fn format_name<A: Asset>(&self, name: &str) -> Option<&'static str> {
+        self.format::<A>(name).map(|format| format.name)
+    }
+
+    /// Returns the extensions registered for assets of type `A`, sorted.
+    pub fn extensions<A: Asset>(&self) -> Vec<String> {
+        let mut extensions = self
+            .formats::<A>()
+            .map(|formats| formats.keys().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        extensions.sort();
+
+        extensions
+    }
+
+    pub(crate) fn format<A: Asset>(&self, name: &str) -> Option<RegisteredFormat<A>> {
+        let extension = Path::new(name).extension()?.to_str()?.to_lowercase();
+        self.formats::<A>()?.get(&extension).cloned()
+    }
+
+    fn formats<A: Asset>(&self) -> Option<&FnvHashMap<String, RegisteredFormat<A>>> {
+        self.formats
+            .get(&TypeId::of::<A>())
+            .and_then(|formats| formats.downcast_ref())
+    }
+
+    fn formats_mut<A: Asset>(&mut self) -> &mut FnvHashMap<String, RegisteredFormat<A>> {
+        self.formats
+            .entry(TypeId::of::<A>())
+            .or_insert_with(|| Box::new(FnvHashMap::<String, RegisteredFormat<A>>::default()))
+            .downcast_mut()
+            .expect("Formats are stored by the `TypeId` of their asset type")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fixtures::{Text, TextFormat, UpperFormat};
+
+    use super::AssetFormatRegistry;
+
+    #[test]
+    fn detects_format_by_extension() {
+        let mut registry = AssetFormatRegistry::new()
+            .with::<Text, _>(&["txt", "md"], TextFormat, ())
+            .with::<Text, _>(&["MD"], UpperFormat, false);
+
+        assert_eq!(Some("TEXT"), registry.format_name::<Text>("notes/todo.TXT"));
+        assert_eq!(Some("UPPER"), registry.format_name::<Text>("README.md"));
+        assert_eq!(None, registry.format_name::<Text>("image.png"));
+        assert_eq!(None, registry.format_name::<Text>("LICENSE"));
+        assert_eq!(vec!["md", "txt"], registry.extensions::<Text>());
+
+        assert!(registry.unregister::<Text>("txt"));
+        assert_eq!(None, registry.format_name::<Text>("notes/todo.txt"));
+    }
+}
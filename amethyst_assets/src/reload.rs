@@ -1,14 +1,19 @@
 //! Defines the `Reload` trait.
 
-use std::{sync::Arc, time::Instant};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use fnv::{FnvHashMap, FnvHashSet};
 
 use amethyst_core as core;
 use amethyst_core::{
-    specs::prelude::{DispatcherBuilder, Read, Resources, System, Write},
+    specs::prelude::{DispatcherBuilder, Read, ReadExpect, Resources, System, Write},
     SystemBundle, Time,
 };
 
-use crate::{Asset, Format, FormatValue, Loader, Result, Source};
+use crate::{meta::meta_path, Asset, Format, FormatValue, Loader, Result, Source};
 
 /// This bundle activates hot reload for the `Loader`,
 /// adds a `HotReloadStrategy` and the `HotReloadSystem`.
@@ -79,6 +84,30 @@ impl HotReloadStrategy {
         }
     }
 
+    /// Watches the sources for changes and reloads the changed assets the frame after a
+    /// change was reported, without polling every asset. `debounce` is how long to wait after
+    /// the last change of a file, so editors saving a file in several steps cause a single
+    /// reload.
+    ///
+    /// Assets of sources which cannot be watched (see `Source::watch`) are polled every
+    /// `poll_interval` seconds instead, like with `every`.
+    ///
+    /// The sources are watched by the `HotReloadSystem` this strategy was passed to.
+    pub fn watch(debounce: Duration, poll_interval: u8) -> Self {
+        use std::u64::MAX;
+
+        HotReloadStrategy {
+            inner: HotReloadStrategyInner::Watch {
+                debounce,
+                changed: FnvHashMap::default(),
+                changed_frame: MAX,
+                poll_interval,
+                last_poll: Instant::now(),
+                poll_frame: MAX,
+            },
+        }
+    }
+
     /// Never do any hot-reloading.
     pub fn never() -> Self {
         HotReloadStrategy {
@@ -104,9 +133,52 @@ impl HotReloadStrategy {
         match self.inner {
             HotReloadStrategyInner::Every { frame_number, .. } => frame_number == current_frame,
             HotReloadStrategyInner::Trigger { frame_number, .. } => frame_number == current_frame,
+            HotReloadStrategyInner::Watch {
+                changed_frame,
+                poll_frame,
+                ..
+            } => changed_frame == current_frame || poll_frame == current_frame,
             HotReloadStrategyInner::Never => false,
         }
     }
+
+    /// Crate-internal method to check if a single asset needs to be reloaded,
+    /// only called if `needs_reload` returned `true`.
+    pub(crate) fn needs_reload_of<A: Asset>(
+        &self,
+        current_frame: u64,
+        source: &str,
+        reload: &dyn Reload<A>,
+    ) -> bool {
+        match self.inner {
+            HotReloadStrategyInner::Watch {
+                ref changed,
+                changed_frame,
+                poll_frame,
+                ..
+            } => {
+                // The watch event is authoritative, `Reload::needs_reload` compares modification
+                // times in whole seconds and misses changes made within the same second.
+                if reload.is_watched() {
+                    let name = reload.name();
+                    changed_frame == current_frame
+                        && changed.get(source).map_or(false, |changed| {
+                            changed.contains(&name) || changed.contains(&meta_path(&name))
+                        })
+                } else {
+                    poll_frame == current_frame && reload.needs_reload()
+                }
+            }
+            _ => reload.needs_reload(),
+        }
+    }
+
+    fn debounce(&self) -> Option<Duration> {
+        match self.inner {
+            HotReloadStrategyInner::Watch { debounce, .. } => Some(debounce),
+            _ => None,
+        }
+    }
 }
 
 impl Default for HotReloadStrategy {
@@ -126,6 +198,15 @@ enum HotReloadStrategyInner {
         triggered: bool,
         frame_number: u64,
     },
+    Watch {
+        debounce: Duration,
+        // The names of the changed assets by source id.
+        changed: FnvHashMap<String, FnvHashSet<String>>,
+        changed_frame: u64,
+        poll_interval: u8,
+        last_poll: Instant,
+        poll_frame: u64,
+    },
     Never,
 }
 
@@ -144,9 +225,13 @@ impl HotReloadSystem {
 }
 
 impl<'a> System<'a> for HotReloadSystem {
-    type SystemData = (Read<'a, Time>, Write<'a, HotReloadStrategy>);
+    type SystemData = (
+        Read<'a, Time>,
+        Write<'a, HotReloadStrategy>,
+        ReadExpect<'a, Loader>,
+    );
 
-    fn run(&mut self, (time, mut strategy): Self::SystemData) {
+    fn run(&mut self, (time, mut strategy, loader): Self::SystemData) {
         match strategy.inner {
             HotReloadStrategyInner::Trigger {
                 ref mut triggered,
@@ -167,6 +252,32 @@ impl<'a> System<'a> for HotReloadSystem {
                     *last = Instant::now();
                 }
             }
+            HotReloadStrategyInner::Watch {
+                ref mut changed,
+                ref mut changed_frame,
+                poll_interval,
+                ref mut last_poll,
+                ref mut poll_frame,
+                ..
+            } => {
+                // Changes are kept until the frame they were queued for has passed, because
+                // the processors may run before this system.
+                if *changed_frame < time.frame_number() {
+                    changed.clear();
+                }
+                let changes = loader.take_changes();
+                if !changes.is_empty() {
+                    for (source, name) in changes {
+                        changed.entry(source).or_default().insert(name);
+                    }
+                    *changed_frame = time.frame_number() + 1;
+                }
+
+                if last_poll.elapsed().as_secs() > u64::from(poll_interval) {
+                    *poll_frame = time.frame_number() + 1;
+                    *last_poll = Instant::now();
+                }
+            }
             HotReloadStrategyInner::Never => {}
         }
     }
@@ -175,7 +286,11 @@ impl<'a> System<'a> for HotReloadSystem {
         use amethyst_core::specs::prelude::SystemData;
         Self::SystemData::setup(res);
         res.insert(self.initial_strategy.clone());
-        res.fetch_mut::<Loader>().set_hot_reload(true);
+        let mut loader = res.fetch_mut::<Loader>();
+        loader.set_hot_reload(true);
+        if let Some(debounce) = self.initial_strategy.debounce() {
+            loader.watch_sources(debounce);
+        }
     }
 }
 
@@ -189,6 +304,11 @@ pub trait Reload<A: Asset>: ReloadClone<A> + Send + Sync + 'static {
     fn format(&self) -> &'static str;
     /// Reloads the asset.
     fn reload(self: Box<Self>) -> Result<FormatValue<A>>;
+    /// Checks if the source of the asset is watched for changes, in which case
+    /// `needs_reload` is only called after a change of the asset was reported.
+    fn is_watched(&self) -> bool {
+        false
+    }
 }
 
 pub trait ReloadClone<A> {
@@ -275,6 +395,10 @@ where
         F::NAME
     }
 
+    fn is_watched(&self) -> bool {
+        self.source.is_watched()
+    }
+
     fn reload(self: Box<Self>) -> Result<FormatValue<A>> {
         #[cfg(feature = "profiler")]
         profile_scope!("reload_single_file");
@@ -291,3 +415,61 @@ where
         format.import(path, source, options, true)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::{fixtures::Text, FormatValue, Result};
+
+    use super::{HotReloadStrategy, HotReloadStrategyInner, Reload};
+
+    #[derive(Clone)]
+    struct Watched(&'static str);
+
+    impl Reload<Text> for Watched {
+        // Like a file changed within the second it was loaded in.
+        fn needs_reload(&self) -> bool {
+            false
+        }
+
+        fn name(&self) -> String {
+            self.0.to_owned()
+        }
+
+        fn format(&self) -> &'static str {
+            "TEXT"
+        }
+
+        fn reload(self: Box<Self>) -> Result<FormatValue<Text>> {
+            Ok(FormatValue::data(String::new()))
+        }
+
+        fn is_watched(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn watch_reloads_changes_of_the_same_source() {
+        let mut strategy = HotReloadStrategy::watch(Duration::from_millis(10), 1);
+        if let HotReloadStrategyInner::Watch {
+            ref mut changed,
+            ref mut changed_frame,
+            ..
+        } = strategy.inner
+        {
+            changed
+                .entry("mods".to_owned())
+                .or_default()
+                .insert("texture/logo.png".to_owned());
+            *changed_frame = 3;
+        }
+
+        assert!(strategy.needs_reload(3));
+        assert!(strategy.needs_reload_of(3, "mods", &Watched("texture/logo.png")));
+        assert!(!strategy.needs_reload_of(3, "", &Watched("texture/logo.png")));
+        assert!(!strategy.needs_reload_of(3, "mods", &Watched("texture/hero.png")));
+        assert!(!strategy.needs_reload_of(4, "mods", &Watched("texture/logo.png")));
+    }
+}
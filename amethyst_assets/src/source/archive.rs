@@ -0,0 +1,410 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use fnv::FnvHashMap;
+use parking_lot::Mutex;
+
+use crate::{source::Source, Error, ErrorKind, Result, ResultExt};
+
+const PAK_MAGIC: &[u8; 4] = b"APAK";
+const PAK_VERSION: u32 = 1;
+#[cfg(feature = "zip")]
+const ZIP_MAGIC: &[u8; 2] = b"PK";
+
+/// Archive source, reading assets from a single file instead of loose files.
+///
+/// Two kinds of archives are supported, detected from the start of the file:
+///
+/// * Pak files, a simple uncompressed format written by `PakWriter`.
+/// * Zip files, if the `zip` feature is enabled.
+///
+/// Entries are addressed by their path inside the archive, using `/` as separator. The
+/// modification time of an asset is the timestamp stored for its entry.
+///
+/// Register an archive with `Loader::add_source` or `ApplicationBuilder::with_source` and
+/// load from it with `Loader::load_from`.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct Archive {
+    path: PathBuf,
+    #[derivative(Debug = "ignore")]
+    kind: ArchiveKind,
+    #[derivative(Debug = "ignore")]
+    entries: FnvHashMap<String, Entry>,
+}
+
+enum ArchiveKind {
+    Pak(Mutex<File>),
+    #[cfg(feature = "zip")]
+    Zip(Mutex<zip::ZipArchive<File>>),
+}
+
+struct Entry {
+    /// The offset of the data for pak files, the index of the entry for zip files.
+    offset: u64,
+    size: u64,
+    modified: u64,
+}
+
+impl Archive {
+    /// Opens an archive, reading the index of its entries.
+    pub fn open<P>(path: P) -> Result<Self>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        let mut file = File::open(&path)
+            .chain_err(|| format!("Failed to open archive {:?}", path))
+            .chain_err(|| ErrorKind::Source)?;
+
+        let mut magic = [0; 4];
+        file.read_exact(&mut magic)
+            .chain_err(|| format!("Failed to read archive {:?}", path))
+            .chain_err(|| ErrorKind::Source)?;
+
+        let (kind, entries) = if &magic == PAK_MAGIC {
+            read_pak_index(BufReader::new(&mut file))
+                .map(|entries| (ArchiveKind::Pak(Mutex::new(file)), entries))
+                .chain_err(|| "Invalid pak archive")
+        } else {
+            open_zip(&magic, file)
+        }
+        .chain_err(|| format!("Failed to read the index of archive {:?}", path))
+        .chain_err(|| ErrorKind::Source)?;
+
+        Ok(Archive {
+            path,
+            kind,
+            entries,
+        })
+    }
+
+    /// Returns the path of the archive file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Checks whether the archive contains an entry.
+    pub fn contains(&self, path: &str) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    /// Returns the paths of all entries in the archive.
+    pub fn entries(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    fn entry(&self, path: &str) -> Result<&Entry> {
+        self.entries
+            .get(path)
+            .ok_or_else(|| Error::from(format!("Archive {:?} has no entry {:?}", self.path, path)))
+            .chain_err(|| ErrorKind::Source)
+    }
+}
+
+impl Source for Archive {
+    fn modified(&self, path: &str) -> Result<u64> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("archive_modified_asset");
+
+        self.entry(path).map(|entry| entry.modified)
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.contains(path)
+    }
+
+    fn load(&self, path: &str) -> Result<Vec<u8>> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("archive_load_asset");
+
+        let entry = self.entry(path)?;
+        let mut v = Vec::with_capacity(entry.size as usize);
+        let read = match self.kind {
+            ArchiveKind::Pak(ref file) => {
+                let mut file = file.lock();
+                file.seek(SeekFrom::Start(entry.offset))
+                    .and_then(|_| (&mut *file).take(entry.size).read_to_end(&mut v))
+            }
+            #[cfg(feature = "zip")]
+            ArchiveKind::Zip(ref archive) => archive
+                .lock()
+                .by_index(entry.offset as usize)
+                .map_err(io::Error::from)
+                .and_then(|mut file| file.read_to_end(&mut v)),
+        };
+        read.chain_err(|| format!("Failed to read {:?} from archive {:?}", path, self.path))
+            .chain_err(|| ErrorKind::Source)?;
+
+        Ok(v)
+    }
+}
+
+/// Writes pak archives, which can be read with `Archive`.
+///
+/// The pak format starts with the magic bytes `APAK`, followed by a version, the number of
+/// entries and an index with the path, offset, size and modification time of every entry.
+/// The uncompressed data of the entries follows the index. All numbers are little endian.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use amethyst_assets::PakWriter;
+/// let mut writer = PakWriter::new();
+/// writer.add_directory("assets").expect("Failed to read assets");
+/// writer.write("assets.pak").expect("Failed to write archive");
+/// ```
+#[derive(Debug, Default)]
+pub struct PakWriter {
+    entries: Vec<(String, Vec<u8>, u64)>,
+}
+
+impl PakWriter {
+    /// Creates a writer without any entries.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds an entry with the given path and modification time as seconds since `UNIX_EPOCH`,
+    /// replacing any entry with the same path.
+    pub fn add<P>(&mut self, path: P, data: Vec<u8>, modified: u64)
+    where
+        P: Into<String>,
+    {
+        let path = path.into();
+        self.entries
+            .retain(|&(ref existing, _, _)| *existing != path);
+        self.entries.push((path, data, modified));
+    }
+
+    /// Adds all files in a directory and its subdirectories, with their paths relative to
+    /// `directory`.
+    pub fn add_directory<P>(&mut self, directory: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        self.add_files(directory.as_ref(), "")
+    }
+
+    fn add_files(&mut self, directory: &Path, prefix: &str) -> Result<()> {
+        let read_dir = fs::read_dir(directory)
+            .chain_err(|| format!("Failed to read directory {:?}", directory))?;
+        for entry in read_dir {
+            let entry = entry.chain_err(|| format!("Failed to read directory {:?}", directory))?;
+            let name = entry
+                .file_name()
+                .into_string()
+                .map_err(|name| Error::from(format!("Invalid file name {:?}", name)))?;
+            let name = format!("{}{}", prefix, name);
+            let path = entry.path();
+            let metadata = entry
+                .metadata()
+                .chain_err(|| format!("Failed to fetch metadata for {:?}", path))?;
+
+            if metadata.is_dir() {
+                self.add_files(&path, &format!("{}/", name))?;
+            } else {
+                let modified = metadata
+                    .modified()
+                    .chain_err(|| "Could not get modification time")?
+                    .duration_since(UNIX_EPOCH)
+                    .chain_err(|| {
+                        "Anomalies with the system clock caused `duration_since` to fail"
+                    })?
+                    .as_secs();
+                let data = fs::read(&path).chain_err(|| format!("Failed to read {:?}", path))?;
+                self.add(name, data, modified);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the archive to a file.
+    pub fn write<P>(&self, path: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        File::create(path)
+            .and_then(|file| self.write_to(BufWriter::new(file)))
+            .chain_err(|| format!("Failed to write archive {:?}", path))
+    }
+
+    fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let index_size = self
+            .entries
+            .iter()
+            .map(|&(ref path, _, _)| 2 + path.len() as u64 + 3 * 8)
+            .sum::<u64>();
+        let mut offset = (PAK_MAGIC.len() + 4 + 4) as u64 + index_size;
+
+        writer.write_all(PAK_MAGIC)?;
+        write_le(&mut writer, u64::from(PAK_VERSION), 4)?;
+        write_le(&mut writer, self.entries.len() as u64, 4)?;
+        for &(ref path, ref data, modified) in &self.entries {
+            if path.len() > u16::max_value() as usize {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Entry path {:?} is too long", path),
+                ));
+            }
+            write_le(&mut writer, path.len() as u64, 2)?;
+            writer.write_all(path.as_bytes())?;
+            write_le(&mut writer, offset, 8)?;
+            write_le(&mut writer, data.len() as u64, 8)?;
+            write_le(&mut writer, modified, 8)?;
+            offset += data.len() as u64;
+        }
+        for &(_, ref data, _) in &self.entries {
+            writer.write_all(data)?;
+        }
+
+        writer.flush()
+    }
+}
+
+fn read_pak_index<R: Read>(mut reader: R) -> io::Result<FnvHashMap<String, Entry>> {
+    let version = read_le(&mut reader, 4)?;
+    if version != u64::from(PAK_VERSION) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Unsupported pak version {}", version),
+        ));
+    }
+
+    let count = read_le(&mut reader, 4)?;
+    let mut entries = FnvHashMap::default();
+    for _ in 0..count {
+        let length = read_le(&mut reader, 2)?;
+        let mut path = vec![0; length as usize];
+        reader.read_exact(&mut path)?;
+        let path =
+            String::from_utf8(path).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let entry = Entry {
+            offset: read_le(&mut reader, 8)?,
+            size: read_le(&mut reader, 8)?,
+            modified: read_le(&mut reader, 8)?,
+        };
+        entries.insert(path, entry);
+    }
+
+    Ok(entries)
+}
+
+#[cfg(feature = "zip")]
+fn open_zip(magic: &[u8], file: File) -> Result<(ArchiveKind, FnvHashMap<String, Entry>)> {
+    if !magic.starts_with(ZIP_MAGIC) {
+        bail!("Unknown archive format");
+    }
+
+    let mut archive = zip::ZipArchive::new(file).chain_err(|| "Invalid zip archive")?;
+    let mut entries = FnvHashMap::default();
+    for index in 0..archive.len() {
+        let file = archive
+            .by_index(index)
+            .chain_err(|| "Invalid zip archive")?;
+        if file.name().ends_with('/') {
+            continue;
+        }
+        let entry = Entry {
+            offset: index as u64,
+            size: file.size(),
+            modified: dos_time_to_unix(file.last_modified()),
+        };
+        entries.insert(file.name().to_owned(), entry);
+    }
+
+    Ok((ArchiveKind::Zip(Mutex::new(archive)), entries))
+}
+
+#[cfg(not(feature = "zip"))]
+fn open_zip(_: &[u8], _: File) -> Result<(ArchiveKind, FnvHashMap<String, Entry>)> {
+    bail!("Unknown archive format, zip archives require the `zip` feature")
+}
+
+/// Converts the timestamp of a zip entry, which is stored without a time zone, to seconds
+/// since `UNIX_EPOCH` assuming UTC.
+#[cfg(feature = "zip")]
+fn dos_time_to_unix(time: zip::DateTime) -> u64 {
+    // Days since 1970-01-01 of the civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let (month, day) = (u64::from(time.month()), u64::from(time.day()));
+    let year = u64::from(time.year()) - if month <= 2 { 1 } else { 0 };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    days * 86_400
+        + u64::from(time.hour()) * 3_600
+        + u64::from(time.minute()) * 60
+        + u64::from(time.second())
+}
+
+/// Reads a little endian number of `size` bytes.
+fn read_le<R: Read>(reader: &mut R, size: usize) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes[..size])?;
+    Ok(bytes[..size]
+        .iter()
+        .rev()
+        .fold(0, |value, &byte| value << 8 | u64::from(byte)))
+}
+
+/// Writes `value` as a little endian number of `size` bytes.
+fn write_le<W: Write>(writer: &mut W, value: u64, size: usize) -> io::Result<()> {
+    let mut bytes = [0; 8];
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        *byte = (value >> (index * 8)) as u8;
+    }
+    writer.write_all(&bytes[..size])
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs};
+
+    use crate::source::Source;
+
+    use super::{Archive, PakWriter};
+
+    #[test]
+    fn loads_assets_from_pak() {
+        let path = env::temp_dir().join(format!("amethyst_assets_{}.pak", std::process::id()));
+        let mut writer = PakWriter::new();
+        writer.add("subdir/asset", b"data".to_vec(), 10);
+        writer.add("other", b"other data".to_vec(), 20);
+        writer.write(&path).expect("Failed to write pak");
+
+        let archive = Archive::open(&path).expect("Failed to open pak");
+        fs::remove_file(&path).expect("Failed to remove pak");
+
+        assert_eq!(b"data".to_vec(), archive.load("subdir/asset").unwrap());
+        assert_eq!(b"other data".to_vec(), archive.load("other").unwrap());
+        assert_eq!(20, archive.modified("other").unwrap());
+        assert!(archive.load("missing").is_err());
+    }
+
+    #[test]
+    fn pak_contains_directory() {
+        let assets = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets");
+        let mut writer = PakWriter::new();
+        writer
+            .add_directory(assets)
+            .expect("Failed to read tests/assets");
+
+        let path = env::temp_dir().join(format!("amethyst_assets_dir_{}.pak", std::process::id()));
+        writer.write(&path).expect("Failed to write pak");
+        let archive = Archive::open(&path).expect("Failed to open pak");
+        fs::remove_file(&path).expect("Failed to remove pak");
+
+        assert!(archive.contains("subdir/asset"));
+        assert_eq!(b"data".to_vec(), archive.load("subdir/asset").unwrap());
+    }
+}
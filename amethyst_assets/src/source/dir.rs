@@ -1,9 +1,13 @@
 use std::{
     fs::File,
-    path::{Path, PathBuf},
-    time::UNIX_EPOCH,
+    path::{Component, Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+    time::{Duration, UNIX_EPOCH},
 };
 
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::Mutex;
+
 use crate::{source::Source, ErrorKind, Result, ResultExt};
 
 /// Directory source.
@@ -12,9 +16,51 @@ use crate::{source::Source, ErrorKind, Result, ResultExt};
 /// inside the `Loader`, which is automatically used when you call
 /// `load`. In case you want another, second, directory for assets,
 /// you can instantiate one yourself, too. Please use `Loader::load_from` then.
-#[derive(Debug)]
+///
+/// A directory can be watched for changes with filesystem notifications, see `Source::watch`.
+#[derive(Derivative)]
+#[derivative(Debug)]
 pub struct Directory {
     loc: PathBuf,
+    #[derivative(Debug = "ignore")]
+    watch: Mutex<Option<DirectoryWatch>>,
+}
+
+struct DirectoryWatch {
+    // Watching stops when the watcher is dropped.
+    _watcher: RecommendedWatcher,
+    root: PathBuf,
+    events: Receiver<DebouncedEvent>,
+}
+
+impl DirectoryWatch {
+    /// Returns the name of the asset changed by an event, if any.
+    fn changed_asset(&self, event: DebouncedEvent) -> Option<String> {
+        match event {
+            DebouncedEvent::Create(path)
+            | DebouncedEvent::Write(path)
+            | DebouncedEvent::Rename(_, path) => self.asset_name(&path),
+            DebouncedEvent::Error(err, path) => {
+                warn!("Error while watching {:?}: {}", path, err);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Converts a path reported by the watcher to the name of the asset.
+    fn asset_name(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+        let names = relative
+            .components()
+            .map(|component| match component {
+                Component::Normal(name) => name.to_str(),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(names.join("/"))
+    }
 }
 
 impl Directory {
@@ -23,7 +69,10 @@ impl Directory {
     where
         P: Into<PathBuf>,
     {
-        Directory { loc: loc.into() }
+        Directory {
+            loc: loc.into(),
+            watch: Mutex::new(None),
+        }
     }
 
     fn path(&self, s_path: &str) -> PathBuf {
@@ -68,11 +117,60 @@ impl Source for Directory {
 
         Ok(v)
     }
+
+    fn watch(&self, debounce: Duration) -> Result<bool> {
+        let mut watch = self.watch.lock();
+        if watch.is_some() {
+            return Ok(true);
+        }
+
+        let root = self
+            .loc
+            .canonicalize()
+            .chain_err(|| format!("Failed to canonicalize {:?}", self.loc))?;
+        // The debounced watcher only reports a file once it stopped changing for `debounce`.
+        let (sender, events) = channel();
+        let mut watcher: RecommendedWatcher =
+            Watcher::new(sender, debounce).chain_err(|| "Failed to create file watcher")?;
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .chain_err(|| format!("Failed to watch {:?}", root))?;
+
+        *watch = Some(DirectoryWatch {
+            _watcher: watcher,
+            root,
+            events,
+        });
+
+        Ok(true)
+    }
+
+    fn is_watched(&self) -> bool {
+        self.watch.lock().is_some()
+    }
+
+    fn take_changes(&self) -> Vec<String> {
+        let watch = self.watch.lock();
+        let watch = match *watch {
+            Some(ref watch) => watch,
+            None => return Vec::new(),
+        };
+
+        watch
+            .events
+            .try_iter()
+            .filter_map(|event| watch.changed_asset(event))
+            .collect()
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use std::path::Path;
+    use std::{
+        env, fs,
+        path::Path,
+        time::{Duration, Instant},
+    };
 
     use crate::source::Source;
 
@@ -91,6 +189,43 @@ mod test {
         );
     }
 
+    #[test]
+    fn watch_reports_changed_files() {
+        let dir = env::temp_dir().join(format!("amethyst_assets_watch_{}", std::process::id()));
+        fs::create_dir_all(dir.join("subdir")).expect("Failed to create test directory");
+        let directory = Directory::new(&dir);
+
+        assert!(!directory.is_watched());
+        assert!(directory
+            .watch(Duration::from_millis(10))
+            .expect("Failed to watch test directory"));
+        assert!(directory.is_watched());
+        assert!(directory.take_changes().is_empty());
+
+        fs::write(dir.join("subdir/asset"), "data").expect("Failed to write test asset");
+        // Block on the events instead of polling `take_changes`, the deadline only bounds a
+        // failing test.
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut changed = None;
+        {
+            let watch = directory.watch.lock();
+            let watch = watch.as_ref().unwrap();
+            while changed.is_none() {
+                let now = Instant::now();
+                if now >= deadline {
+                    break;
+                }
+                changed = match watch.events.recv_timeout(deadline - now) {
+                    Ok(event) => watch.changed_asset(event),
+                    Err(_) => break,
+                };
+            }
+        }
+        fs::remove_dir_all(&dir).expect("Failed to remove test directory");
+
+        assert_eq!(Some("subdir/asset".to_owned()), changed);
+    }
+
     #[cfg(windows)]
     #[test]
     fn tolerates_backslashed_location_with_forward_slashed_asset_paths() {
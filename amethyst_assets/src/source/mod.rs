@@ -1,8 +1,16 @@
+use std::time::Duration;
+
 use crate::Result;
 
-pub use self::dir::Directory;
+pub use self::{
+    archive::{Archive, PakWriter},
+    dir::Directory,
+    overlay::Overlay,
+};
 
+mod archive;
 mod dir;
+mod overlay;
 
 /// A trait for asset sources, which provides
 /// methods for loading bytes.
@@ -29,4 +37,33 @@ pub trait Source: Send + Sync + 'static {
 
         Ok((b, m))
     }
+
+    /// Starts watching this source for changes, which are then reported by `take_changes`.
+    /// `debounce` is how long to wait after the last change of a file before reporting it.
+    ///
+    /// Returns `false` if this source cannot be watched, in which case its assets have to be
+    /// polled with `modified`. The default implementation does not watch.
+    fn watch(&self, debounce: Duration) -> Result<bool> {
+        let _ = debounce;
+        Ok(false)
+    }
+
+    /// Checks whether this source is watched for changes.
+    fn is_watched(&self) -> bool {
+        false
+    }
+
+    /// Takes the paths of the assets which changed since the last call.
+    ///
+    /// Sources which are not watched return no changes.
+    fn take_changes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Checks whether the source has an asset with the given path.
+    ///
+    /// The default implementation checks if `modified` succeeds.
+    fn exists(&self, path: &str) -> bool {
+        self.modified(path).is_ok()
+    }
 }
@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use fnv::FnvHashMap;
+use parking_lot::Mutex;
+
+use crate::{source::Source, Error, ErrorKind, Result, ResultExt};
+
+/// Overlay source, stacking several sources by priority.
+///
+/// Every asset is loaded from the layer with the highest priority which has it, so mods and
+/// patches can replace single assets of the base game without changing the code loading them.
+/// Layers with the same priority are searched in the order they were added.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use amethyst_assets::{Archive, Directory, Overlay};
+/// let overlay = Overlay::new()
+///     .with_layer("base", 0, Directory::new("assets"))
+///     .with_layer("patch", 10, Archive::open("patch.pak").expect("Failed to open patch"))
+///     .with_layer("my_mod", 20, Directory::new("mods/my_mod"));
+/// ```
+#[derive(Derivative, Default)]
+#[derivative(Debug)]
+pub struct Overlay {
+    #[derivative(Debug = "ignore")]
+    layers: Vec<Layer>,
+    served: Mutex<FnvHashMap<String, String>>,
+}
+
+struct Layer {
+    name: String,
+    priority: i32,
+    source: Box<dyn Source>,
+}
+
+impl Overlay {
+    /// Creates an overlay without any layers.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a layer, see `add_layer`.
+    pub fn with_layer<N, S>(mut self, name: N, priority: i32, source: S) -> Self
+    where
+        N: Into<String>,
+        S: Source,
+    {
+        self.add_layer(name, priority, source);
+        self
+    }
+
+    /// Adds a layer with a name used in logs and by `served`. Layers with a higher priority
+    /// take precedence.
+    pub fn add_layer<N, S>(&mut self, name: N, priority: i32, source: S)
+    where
+        N: Into<String>,
+        S: Source,
+    {
+        let index = self
+            .layers
+            .iter()
+            .position(|layer| layer.priority < priority)
+            .unwrap_or_else(|| self.layers.len());
+        self.layers.insert(
+            index,
+            Layer {
+                name: name.into(),
+                priority,
+                source: Box::new(source),
+            },
+        );
+    }
+
+    /// Returns the names of the layers, starting with the highest priority.
+    pub fn layers(&self) -> impl Iterator<Item = &str> {
+        self.layers.iter().map(|layer| layer.name.as_str())
+    }
+
+    /// Returns the name of the layer an asset would be loaded from.
+    pub fn layer_of(&self, path: &str) -> Option<&str> {
+        self.find(path).map(|layer| layer.name.as_str())
+    }
+
+    /// Returns the path of every asset loaded so far with the name of the layer which
+    /// served it, sorted by path.
+    pub fn served(&self) -> Vec<(String, String)> {
+        let mut served = self
+            .served
+            .lock()
+            .iter()
+            .map(|(path, layer)| (path.clone(), layer.clone()))
+            .collect::<Vec<_>>();
+        served.sort();
+
+        served
+    }
+
+    fn find(&self, path: &str) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.source.exists(path))
+    }
+
+    fn layer(&self, path: &str) -> Result<&Layer> {
+        self.find(path)
+            .ok_or_else(|| Error::from(format!("No layer of the overlay has {:?}", path)))
+            .chain_err(|| ErrorKind::Source)
+    }
+
+    fn record(&self, path: &str, layer: &Layer) {
+        let previous = self
+            .served
+            .lock()
+            .insert(path.to_owned(), layer.name.clone());
+        if previous.as_ref() != Some(&layer.name) {
+            debug!("Overlay serves {:?} from layer {:?}", path, layer.name);
+        }
+    }
+}
+
+impl Source for Overlay {
+    fn modified(&self, path: &str) -> Result<u64> {
+        self.layer(path)?.source.modified(path)
+    }
+
+    fn load(&self, path: &str) -> Result<Vec<u8>> {
+        let layer = self.layer(path)?;
+        let bytes = layer.source.load(path)?;
+        self.record(path, layer);
+
+        Ok(bytes)
+    }
+
+    fn load_with_metadata(&self, path: &str) -> Result<(Vec<u8>, u64)> {
+        let layer = self.layer(path)?;
+        let loaded = layer.source.load_with_metadata(path)?;
+        self.record(path, layer);
+
+        Ok(loaded)
+    }
+
+    fn watch(&self, debounce: Duration) -> Result<bool> {
+        let mut watched = true;
+        for layer in &self.layers {
+            watched &= layer.source.watch(debounce)?;
+        }
+
+        Ok(watched && !self.layers.is_empty())
+    }
+
+    fn is_watched(&self) -> bool {
+        !self.layers.is_empty() && self.layers.iter().all(|layer| layer.source.is_watched())
+    }
+
+    fn take_changes(&self) -> Vec<String> {
+        self.layers
+            .iter()
+            .flat_map(|layer| layer.source.take_changes())
+            .collect()
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.find(path).is_some()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{fixtures::Memory, source::Source};
+
+    use super::Overlay;
+
+    #[test]
+    fn loads_from_highest_priority_layer() {
+        let overlay = Overlay::new()
+            .with_layer("base", 0, Memory::new(&[("a", "base"), ("b", "base")]))
+            .with_layer("mod", 10, Memory::new(&[("a", "mod")]))
+            .with_layer("patch", 5, Memory::new(&[("a", "patch"), ("b", "patch")]));
+
+        assert_eq!(
+            vec!["mod", "patch", "base"],
+            overlay.layers().collect::<Vec<_>>()
+        );
+        assert_eq!(b"mod".to_vec(), overlay.load("a").unwrap());
+        assert_eq!(b"patch".to_vec(), overlay.load("b").unwrap());
+        assert!(overlay.load("c").is_err());
+        assert_eq!(None, overlay.layer_of("c"));
+        assert_eq!(
+            vec![
+                ("a".to_owned(), "mod".to_owned()),
+                ("b".to_owned(), "patch".to_owned()),
+            ],
+            overlay.served()
+        );
+    }
+}
@@ -7,7 +7,7 @@ use std::{
 };
 
 use crossbeam::queue::MsQueue;
-use hibitset::BitSet;
+use hibitset::{BitSet, BitSetLike};
 use rayon::ThreadPool;
 
 use amethyst_core::{
@@ -20,7 +20,11 @@ use amethyst_core::{
 
 use crate::{
     asset::{Asset, FormatValue},
-    error::{Error, ErrorKind, Result, ResultExt},
+    cache::LruCache,
+    dependency::{AssetDependencies, AssetId, Scope},
+    error::{ErrorKind, Result, ResultExt},
+    loader::{LoadPriority, LoadQueue},
+    memory::{AssetMemory, AssetSizes},
     progress::Tracker,
     reload::{HotReloadStrategy, Reload},
 };
@@ -43,10 +47,17 @@ impl Allocator {
 pub struct AssetStorage<A: Asset> {
     assets: VecStorage<A>,
     bitset: BitSet,
+    dependencies: Option<Arc<AssetDependencies>>,
     handles: Vec<Handle<A>>,
     handle_alloc: Allocator,
     pub(crate) processed: Arc<MsQueue<Processed<A>>>,
-    reloads: Vec<(WeakHandle<A>, Box<dyn Reload<A>>)>,
+    // The load queue of the `Loader`, which also runs the hot reloads.
+    queue: Option<Arc<LoadQueue>>,
+    // The reload objects of the assets, with the id of the source they were loaded from.
+    reloads: Vec<(WeakHandle<A>, String, Box<dyn Reload<A>>)>,
+    // The assets borrowed mutably since the last `process`, which may have changed their size.
+    resized: BitSet,
+    sizes: AssetSizes,
     unused_handles: MsQueue<Handle<A>>,
     requeue: Mutex<Vec<Processed<A>>>,
 }
@@ -98,6 +109,7 @@ impl<A: Asset> AssetStorage<A> {
             let id = h.id();
             self.bitset.add(id);
             self.handles.push(h.clone());
+            self.sizes.set(id, asset.size_bytes());
 
             unsafe {
                 self.assets.insert(id, asset);
@@ -109,6 +121,34 @@ impl<A: Asset> AssetStorage<A> {
         }
     }
 
+    /// Returns the number of loaded assets.
+    pub fn len(&self) -> usize {
+        self.handles.len()
+    }
+
+    /// Checks whether no assets are loaded.
+    pub fn is_empty(&self) -> bool {
+        self.handles.is_empty()
+    }
+
+    /// Returns the memory used by the loaded assets in bytes, according to `Asset::size_bytes`.
+    ///
+    /// The sizes are measured when assets are loaded, so changes made through `get_mut` are only
+    /// counted after the next `process`.
+    pub fn memory_usage(&self) -> usize {
+        self.sizes.total()
+    }
+
+    /// Returns the memory used by an asset in bytes, `0` if it isn't loaded.
+    pub(crate) fn size_of(&self, handle: &Handle<A>) -> usize {
+        self.sizes.get(handle.id())
+    }
+
+    /// Returns a counter which changes whenever the memory used by an asset changes.
+    pub(crate) fn size_generation(&self) -> u64 {
+        self.sizes.generation()
+    }
+
     /// Get an asset from a given asset handle.
     pub fn get(&self, handle: &Handle<A>) -> Option<&A> {
         if self.bitset.contains(handle.id()) {
@@ -121,6 +161,7 @@ impl<A: Asset> AssetStorage<A> {
     /// Get an asset mutably from a given asset handle.
     pub fn get_mut(&mut self, handle: &Handle<A>) -> Option<&mut A> {
         if self.bitset.contains(handle.id()) {
+            self.resized.add(handle.id());
             Some(unsafe { self.assets.get_mut(handle.id()) })
         } else {
             None
@@ -153,6 +194,14 @@ impl<A: Asset> AssetStorage<A> {
         D: FnMut(A),
         F: FnMut(A::Data) -> Result<ProcessingState<A>>,
     {
+        for id in (&self.resized).iter() {
+            if self.bitset.contains(id) {
+                let size = unsafe { self.assets.get(id) }.size_bytes();
+                self.sizes.set(id, size);
+            }
+        }
+        self.resized.clear();
+
         {
             let requeue = self
                 .requeue
@@ -163,45 +212,66 @@ impl<A: Asset> AssetStorage<A> {
                 let bitset = &mut self.bitset;
                 let handles = &mut self.handles;
                 let reloads = &mut self.reloads;
+                let sizes = &mut self.sizes;
+                let storage_dependencies = &mut self.dependencies;
+                let storage_queue = &mut self.queue;
+                let unused_handles = &self.unused_handles;
 
                 let f = &mut f;
-                let (reload_obj, handle) = match processed {
+                let (reload_obj, handle, source) = match processed {
                     Processed::NewAsset {
                         data,
                         handle,
                         name,
+                        source,
                         tracker,
+                        dependencies,
+                        queue,
                     } => {
-                        let (asset, reload_obj) = match data
+                        if storage_dependencies.is_none() {
+                            *storage_dependencies = Some(dependencies.clone());
+                        }
+                        if storage_queue.is_none() {
+                            *storage_queue = Some(queue.clone());
+                        }
+                        // Assets loaded during processing are recorded as dependencies.
+                        let scope = Scope::enter(AssetId::of(&handle), dependencies.clone());
+                        let result = data
                             .map(|FormatValue { data, reload }| (data, reload))
                             .and_then(|(d, rel)| f(d).map(|a| (a, rel)))
-                            .chain_err(|| ErrorKind::Asset(name.clone()))
-                        {
+                            .chain_err(|| ErrorKind::Asset(name.clone()));
+                        drop(scope);
+
+                        let (asset, reload_obj) = match result {
                             Ok((ProcessingState::Loaded(x), r)) => {
-                                debug!(
-                                        "{:?}: Asset {:?} (handle id: {:?}) has been loaded successfully",
-                                        A::NAME,
-                                        name,
-                                        handle,
-                                    );
-                                // Add a warning if a handle is unique (i.e. asset does not
-                                // need to be loaded as it is not used by anything)
+                                tracker.success();
+                                // If the handle is unique, all other handles were dropped
+                                // while the asset was imported, so the load is cancelled.
                                 // https://github.com/amethyst/amethyst/issues/628
                                 if handle.is_unique() {
-                                    warn!(
-                                        "Loading unnecessary asset. Handle {} is unique ",
-                                        handle.id()
-                                    );
-                                    tracker.fail(
-                                        handle.id(),
+                                    debug!(
+                                        "{:?}: Cancelled loading asset {:?} (handle id: {:?}), \
+                                         all handles were dropped",
                                         A::NAME,
                                         name,
-                                        Error::from_kind(ErrorKind::UnusedHandle),
+                                        handle,
                                     );
-                                } else {
-                                    tracker.success();
+                                    if let Some(ref dependencies) = *storage_dependencies {
+                                        dependencies.remove(AssetId::of(&handle));
+                                    }
+                                    unused_handles.push(Handle {
+                                        id: Arc::new(handle.id()),
+                                        marker: PhantomData,
+                                    });
+                                    continue;
                                 }
 
+                                debug!(
+                                        "{:?}: Asset {:?} (handle id: {:?}) has been loaded successfully",
+                                        A::NAME,
+                                        name,
+                                        handle,
+                                    );
                                 (x, r)
                             }
                             Ok((ProcessingState::Loading(x), r)) => {
@@ -215,7 +285,10 @@ impl<A: Asset> AssetStorage<A> {
                                     data: Ok(FormatValue { data: x, reload: r }),
                                     handle,
                                     name,
+                                    source,
                                     tracker,
+                                    dependencies,
+                                    queue,
                                 });
                                 continue;
                             }
@@ -236,6 +309,7 @@ impl<A: Asset> AssetStorage<A> {
                         let id = handle.id();
                         bitset.add(id);
                         handles.push(handle.clone());
+                        sizes.set(id, asset.size_bytes());
 
                         // NOTE: the loader has to ensure that a handle will be used
                         // together with a `Data` only once.
@@ -243,19 +317,26 @@ impl<A: Asset> AssetStorage<A> {
                             assets.insert(id, asset);
                         }
 
-                        (reload_obj, handle)
+                        (reload_obj, handle, source)
                     }
                     Processed::HotReload {
                         data,
                         handle,
                         name,
+                        source,
                         old_reload,
+                        cascade,
                     } => {
-                        let (asset, reload_obj) = match data
+                        let scope = storage_dependencies
+                            .as_ref()
+                            .map(|deps| Scope::enter(AssetId::of(&handle), deps.clone()));
+                        let result = data
                             .map(|FormatValue { data, reload }| (data, reload))
                             .and_then(|(d, rel)| f(d).map(|a| (a, rel)))
-                            .chain_err(|| ErrorKind::Asset(name.clone()))
-                        {
+                            .chain_err(|| ErrorKind::Asset(name.clone()));
+                        drop(scope);
+
+                        let (asset, reload_obj) = match result {
                             Ok((ProcessingState::Loaded(x), r)) => (x, r),
                             Ok((ProcessingState::Loading(x), r)) => {
                                 debug!(
@@ -268,7 +349,9 @@ impl<A: Asset> AssetStorage<A> {
                                     data: Ok(FormatValue { data: x, reload: r }),
                                     handle,
                                     name,
+                                    source,
                                     old_reload,
+                                    cascade,
                                 });
                                 continue;
                             }
@@ -282,7 +365,7 @@ impl<A: Asset> AssetStorage<A> {
                                     e,
                                 );
 
-                                reloads.push((handle.downgrade(), old_reload));
+                                reloads.push((handle.downgrade(), source, old_reload));
 
                                 continue;
                             }
@@ -294,18 +377,34 @@ impl<A: Asset> AssetStorage<A> {
                             "Expected handle {:?} to be valid, but the asset storage says otherwise",
                             handle,
                         );
+                        sizes.set(id, asset.size_bytes());
                         unsafe {
                             let old = assets.get_mut(id);
                             *old = asset;
                         }
 
-                        (reload_obj, handle)
+                        // Reloads of dependents don't cascade again, because all
+                        // transitive dependents are reloaded at once.
+                        if cascade {
+                            if let Some(ref dependencies) = *storage_dependencies {
+                                dependencies.invalidate_dependents(AssetId::of(&handle));
+                            }
+                        }
+
+                        (reload_obj, handle, source)
+                    }
+                    Processed::Cancelled(id) => {
+                        unused_handles.push(Handle {
+                            id: Arc::new(id),
+                            marker: PhantomData,
+                        });
+                        continue;
                     }
                 };
 
                 // Add the reload obj if it is `Some`.
                 if let Some(reload_obj) = reload_obj {
-                    reloads.push((handle.downgrade(), reload_obj));
+                    reloads.push((handle.downgrade(), source, reload_obj));
                 }
             }
 
@@ -327,6 +426,10 @@ impl<A: Asset> AssetStorage<A> {
                 drop_fn(self.assets.remove(id));
             }
             self.bitset.remove(id);
+            self.sizes.set(id, 0);
+            if let Some(ref dependencies) = self.dependencies {
+                dependencies.remove(AssetId::new::<A>(id));
+            }
 
             // Can't reuse old handle here, because otherwise weak handles would still be valid.
             // TODO: maybe just store u32?
@@ -339,23 +442,45 @@ impl<A: Asset> AssetStorage<A> {
             debug!("{:?}: Freed {} handle ids", A::NAME, count,);
         }
 
-        if strategy
-            .map(|s| s.needs_reload(frame_number))
-            .unwrap_or(false)
-        {
+        if let Some(strategy) = strategy.filter(|s| s.needs_reload(frame_number)) {
             trace!("{:?}: Testing for asset reloads..", A::NAME);
-            self.hot_reload(pool);
+            self.hot_reload(pool, true, |_, source, rel| {
+                strategy.needs_reload_of(frame_number, source, rel)
+            });
+        }
+
+        let forced = self
+            .dependencies
+            .as_ref()
+            .map(|dependencies| dependencies.take_forced::<A>())
+            .unwrap_or_default();
+        if !forced.is_empty() {
+            trace!(
+                "{:?}: Reloading assets with reloaded dependencies..",
+                A::NAME
+            );
+            self.hot_reload(pool, false, |handle, _, _| {
+                handle
+                    .upgrade()
+                    .map_or(false, |handle| forced.contains(&handle.id()))
+            });
         }
     }
 
-    fn hot_reload(&mut self, pool: &ThreadPool) {
-        self.reloads.retain(|&(ref handle, _)| !handle.is_dead());
+    /// Reloads the assets selected by `needs_reload`. If `cascade` is `true`, the assets
+    /// depending on them are reloaded afterwards.
+    fn hot_reload<F>(&mut self, pool: &ThreadPool, cascade: bool, needs_reload: F)
+    where
+        F: Fn(&WeakHandle<A>, &str, &dyn Reload<A>) -> bool,
+    {
+        self.reloads.retain(|&(ref handle, _, _)| !handle.is_dead());
         while let Some(p) = self
             .reloads
             .iter()
-            .position(|&(_, ref rel)| rel.needs_reload())
+            .position(|&(ref handle, ref source, ref rel)| needs_reload(handle, source, &**rel))
         {
-            let (handle, rel): (WeakHandle<_>, Box<dyn Reload<_>>) = self.reloads.swap_remove(p);
+            let (handle, source, rel): (WeakHandle<_>, String, Box<dyn Reload<_>>) =
+                self.reloads.swap_remove(p);
 
             let name = rel.name();
             let format = rel.format();
@@ -371,15 +496,21 @@ impl<A: Asset> AssetStorage<A> {
 
             if let Some(handle) = handle {
                 let processed = self.processed.clone();
-                pool.spawn(move || {
+                let dependencies = self.dependencies.clone();
+                let queue = self.queue.get_or_insert_with(Default::default);
+                LoadQueue::spawn(queue, pool, LoadPriority::Background, move || {
+                    let _scope = dependencies
+                        .map(|dependencies| Scope::enter(AssetId::of(&handle), dependencies));
                     let old_reload = rel.clone();
                     let data = rel.reload().chain_err(|| ErrorKind::Format(format));
 
                     let p = Processed::HotReload {
                         data,
                         name,
+                        source,
                         handle,
                         old_reload,
+                        cascade,
                     };
                     processed.push(p);
                 });
@@ -393,10 +524,14 @@ impl<A: Asset> Default for AssetStorage<A> {
         AssetStorage {
             assets: Default::default(),
             bitset: Default::default(),
+            dependencies: None,
             handles: Default::default(),
             handle_alloc: Default::default(),
             processed: Arc::new(MsQueue::new()),
+            queue: None,
             reloads: Default::default(),
+            resized: Default::default(),
+            sizes: Default::default(),
             unused_handles: MsQueue::new(),
             requeue: Mutex::new(Vec::default()),
         }
@@ -416,6 +551,9 @@ impl<A: Asset> Drop for AssetStorage<A> {
 ///
 /// This system can only be used if the asset data implements
 /// `Into<Result<A, BoxedErr>>`.
+///
+/// It also evicts from the `LruCache<A>` resource if there is one and reports the memory
+/// used by the storage in the `AssetMemory` resource.
 pub struct Processor<A> {
     marker: PhantomData<A>,
 }
@@ -440,9 +578,11 @@ where
         ReadExpect<'a, Arc<ThreadPool>>,
         Read<'a, Time>,
         Option<Read<'a, HotReloadStrategy>>,
+        Option<Write<'a, LruCache<A>>>,
+        Read<'a, AssetMemory>,
     );
 
-    fn run(&mut self, (mut storage, pool, time, strategy): Self::SystemData) {
+    fn run(&mut self, (mut storage, pool, time, strategy, cache, memory): Self::SystemData) {
         use std::ops::Deref;
 
         storage.process(
@@ -451,6 +591,10 @@ where
             &**pool,
             strategy.as_ref().map(Deref::deref),
         );
+        if let Some(mut cache) = cache {
+            cache.evict(&storage);
+        }
+        memory.update(&storage);
     }
 }
 
@@ -505,14 +649,22 @@ pub(crate) enum Processed<A: Asset> {
         data: Result<FormatValue<A>>,
         handle: Handle<A>,
         name: String,
+        source: String,
         tracker: Box<dyn Tracker>,
+        dependencies: Arc<AssetDependencies>,
+        queue: Arc<LoadQueue>,
     },
     HotReload {
         data: Result<FormatValue<A>>,
         handle: Handle<A>,
         name: String,
+        source: String,
         old_reload: Box<dyn Reload<A>>,
+        cascade: bool,
     },
+    /// A load cancelled before importing because all handles were dropped, whose handle id
+    /// can be reused.
+    Cancelled(u32),
 }
 
 /// A weak handle, which is useful if you don't directly need the asset
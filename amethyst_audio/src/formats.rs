@@ -87,3 +87,11 @@ impl SimpleFormat<Audio> for AudioFormat {
         }
     }
 }
+
+/// Registers the audio formats for their file extensions.
+pub fn register_formats(registry: &mut AssetFormatRegistry) {
+    registry.register::<Audio, _>(&["wav"], WavFormat, ());
+    registry.register::<Audio, _>(&["ogg"], OggFormat, ());
+    registry.register::<Audio, _>(&["flac"], FlacFormat, ());
+    registry.register::<Audio, _>(&["mp3"], Mp3Format, ());
+}
@@ -14,7 +14,7 @@ extern crate thread_profiler;
 pub use self::{
     bundle::AudioBundle,
     components::*,
-    formats::{AudioFormat, FlacFormat, Mp3Format, OggFormat, WavFormat},
+    formats::{register_formats, AudioFormat, FlacFormat, Mp3Format, OggFormat, WavFormat},
     sink::AudioSink,
     source::{Source, SourceHandle},
     systems::*,
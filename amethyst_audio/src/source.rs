@@ -29,6 +29,10 @@ impl Asset for Source {
     const NAME: &'static str = "audio::Source";
     type Data = AudioData;
     type HandleStorage = VecStorage<SourceHandle>;
+
+    fn size_bytes(&self) -> usize {
+        self.bytes.len()
+    }
 }
 
 impl Into<Result<ProcessingState<Source>>> for AudioData {
@@ -1,7 +1,7 @@
 #[macro_use]
 extern crate serde_derive;
 
-use amethyst_config::Config;
+use amethyst_config::{Config, Validate, ValidationErrors};
 
 #[derive(Debug, Default, Deserialize, Serialize)]
 pub struct DisplayConfig {
@@ -32,6 +32,18 @@ pub struct ExampleConfig {
     pub logging: LoggingConfig,
 }
 
+impl Validate for ExampleConfig {
+    fn validate(&self, errors: &mut ValidationErrors) {
+        let (width, height) = self.display.dimensions;
+        if width == 0 || height == 0 {
+            errors.add(
+                "display.dimensions",
+                "width and height must be greater than 0",
+            );
+        }
+    }
+}
+
 fn main() {
     let path = format!("{}/examples/display_config.ron", env!("CARGO_MANIFEST_DIR"));
     let res = ExampleConfig::load_no_fallback(&path);
@@ -0,0 +1,149 @@
+//! The file formats configurations can be stored in.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ConfigError;
+
+/// A file format configurations can be read from and written to, detected from the file
+/// extension.
+///
+/// RON is always available, the other formats are enabled by the `json`, `toml` and `yaml`
+/// features.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// RON, with the extension `ron`.
+    Ron,
+    /// JSON, with the extension `json`.
+    #[cfg(feature = "json")]
+    Json,
+    /// TOML, with the extension `toml`.
+    #[cfg(feature = "toml")]
+    Toml,
+    /// YAML, with the extension `yaml` or `yml`.
+    #[cfg(feature = "yaml")]
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Gets the format using the given file extension, if it is enabled.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "ron" => Some(ConfigFormat::Ron),
+            #[cfg(feature = "json")]
+            "json" => Some(ConfigFormat::Json),
+            #[cfg(feature = "toml")]
+            "toml" => Some(ConfigFormat::Toml),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => Some(ConfigFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Gets the format of a file from its extension.
+    pub fn from_path(path: &Path) -> Result<Self, ConfigError> {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(ConfigFormat::from_extension)
+            .ok_or_else(|| ConfigError::Extension(path.to_path_buf()))
+    }
+
+    /// Gets the extensions of all enabled formats.
+    pub fn extensions() -> Vec<&'static str> {
+        let mut extensions = vec!["ron"];
+        if cfg!(feature = "json") {
+            extensions.push("json");
+        }
+        if cfg!(feature = "toml") {
+            extensions.push("toml");
+        }
+        if cfg!(feature = "yaml") {
+            extensions.extend_from_slice(&["yaml", "yml"]);
+        }
+        extensions
+    }
+
+    /// Deserializes a value in this format.
+    pub fn deserialize<T>(self, bytes: &[u8]) -> Result<T, ConfigError>
+    where
+        T: for<'a> Deserialize<'a>,
+    {
+        match self {
+            ConfigFormat::Ron => {
+                let mut de = ron::de::Deserializer::from_bytes(bytes)?;
+                let val = T::deserialize(&mut de)?;
+                de.end()?;
+
+                Ok(val)
+            }
+            #[cfg(feature = "json")]
+            ConfigFormat::Json => serde_json::from_slice(bytes).map_err(ConfigError::Json),
+            #[cfg(feature = "toml")]
+            ConfigFormat::Toml => toml::from_slice(bytes).map_err(ConfigError::TomlParser),
+            #[cfg(feature = "yaml")]
+            ConfigFormat::Yaml => serde_yaml::from_slice(bytes).map_err(ConfigError::Yaml),
+        }
+    }
+
+    /// Serializes a value in this format.
+    pub fn serialize<T>(self, value: &T) -> Result<String, ConfigError>
+    where
+        T: Serialize,
+    {
+        match self {
+            ConfigFormat::Ron => Ok(ron::ser::to_string_pretty(value, Default::default())?),
+            #[cfg(feature = "json")]
+            ConfigFormat::Json => {
+                serde_json::to_string_pretty(value).map_err(ConfigError::Json)
+            }
+            #[cfg(feature = "toml")]
+            ConfigFormat::Toml => {
+                toml::to_string_pretty(value).map_err(ConfigError::TomlSerializer)
+            }
+            #[cfg(feature = "yaml")]
+            ConfigFormat::Yaml => serde_yaml::to_string(value).map_err(ConfigError::Yaml),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde_derive::{Deserialize, Serialize};
+
+    use super::ConfigFormat;
+    use crate::ConfigError;
+
+    #[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+    struct Display {
+        title: String,
+        vsync: bool,
+    }
+
+    #[test]
+    fn detect_format() {
+        assert_eq!(
+            ConfigFormat::Ron,
+            ConfigFormat::from_path(Path::new("config.ron")).unwrap()
+        );
+        match ConfigFormat::from_path(Path::new("config.ini")) {
+            Err(ConfigError::Extension(_)) => {}
+            other => panic!("Expected an extension error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let display = Display {
+            title: "Game".to_owned(),
+            vsync: true,
+        };
+        for extension in ConfigFormat::extensions() {
+            let format = ConfigFormat::from_extension(extension).unwrap();
+            let written = format.serialize(&display).unwrap();
+            assert_eq!(display, format.deserialize::<Display>(written.as_bytes()).unwrap());
+        }
+    }
+}
@@ -0,0 +1,533 @@
+//! Loading a configuration from several layers of sources.
+
+use std::{collections::BTreeMap, env, fmt, marker::PhantomData, mem, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_value::Value;
+
+use crate::{
+    merge::merge,
+    reader,
+    value::{self, load_file},
+    ConfigError, Validate, ValidationErrors,
+};
+
+/// The layer that supplied a configuration value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// The value comes from the `Default` implementation of the configuration.
+    Default,
+    /// The value was read from a configuration file.
+    File(PathBuf),
+    /// The value was read from the given environment variable.
+    Environment(String),
+    /// The value was passed with `--set` on the command line.
+    CommandLine,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::File(ref path) => write!(f, "file {}", path.display()),
+            ConfigSource::Environment(ref var) => write!(f, "environment variable {}", var),
+            ConfigSource::CommandLine => write!(f, "command line"),
+        }
+    }
+}
+
+/// A configuration merged from several layers, together with the layer each value came from.
+#[derive(Clone, Debug)]
+pub struct LayeredConfig<T> {
+    /// The merged configuration.
+    pub config: T,
+    sources: BTreeMap<String, ConfigSource>,
+}
+
+impl<T> LayeredConfig<T> {
+    /// Gets the layer which supplied the value at `key`, a path of field names separated by
+    /// dots like `display.vsync`.
+    pub fn source(&self, key: &str) -> Option<&ConfigSource> {
+        self.sources.get(key)
+    }
+
+    /// Iterates over the keys of all values and the layers they came from, sorted by key.
+    pub fn sources(&self) -> impl Iterator<Item = (&str, &ConfigSource)> {
+        self.sources
+            .iter()
+            .map(|(key, source)| (key.as_str(), source))
+    }
+
+    /// Unwraps the merged configuration.
+    pub fn into_inner(self) -> T {
+        self.config
+    }
+}
+
+enum Layer {
+    File { path: PathBuf, required: bool },
+    Environment(String),
+    CommandLine(Vec<String>),
+}
+
+/// Loads a configuration by merging several layers, later layers overriding earlier ones:
+///
+/// 1. The `Default` implementation of the configuration.
+/// 2. Configuration files, in the order they were added.
+/// 3. Environment variables starting with a prefix, `GAME_DISPLAY__VSYNC=false` sets
+///    `display.vsync` for the prefix `GAME`.
+/// 4. `--set key=value` command line arguments, like `--set display.vsync=false`.
+///
+/// Values from the environment and the command line are parsed as RON, falling back to a
+/// string if they are not valid RON or a bare word like `Hello`.
+///
+/// Structs and maps are merged field by field, all other values like lists and enums are
+/// replaced by the later layer.
+///
+/// # Example
+/// ```rust,ignore
+/// let layered = ConfigLayers::<GameConfig>::new()
+///     .with_file("resources/config.ron")
+///     .with_optional_file(user_dir.join("config.ron"))
+///     .with_env_prefix("GAME")
+///     .with_args(std::env::args())
+///     .load()?;
+/// info!("vsync set by {}", layered.source("display.vsync").unwrap());
+/// ```
+pub struct ConfigLayers<T> {
+    layers: Vec<Layer>,
+    marker: PhantomData<T>,
+}
+
+impl<T> Default for ConfigLayers<T> {
+    fn default() -> Self {
+        ConfigLayers {
+            layers: Vec::new(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> ConfigLayers<T>
+where
+    T: for<'a> Deserialize<'a> + Serialize + Default,
+{
+    /// Creates a new set of layers, only containing the `Default` of the configuration.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a configuration file which has to exist, like the one shipped with the game.
+    pub fn with_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.layers.push(Layer::File {
+            path: path.into(),
+            required: true,
+        });
+        self
+    }
+
+    /// Adds a configuration file which is skipped if it does not exist, like a per-user
+    /// override file.
+    pub fn with_optional_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.layers.push(Layer::File {
+            path: path.into(),
+            required: false,
+        });
+        self
+    }
+
+    /// Adds the environment variables starting with `prefix` followed by an underscore.
+    /// Nested fields are separated by two underscores and matched in lowercase.
+    pub fn with_env_prefix(mut self, prefix: &str) -> Self {
+        self.layers.push(Layer::Environment(prefix.to_owned()));
+        self
+    }
+
+    /// Adds the `--set key=value` pairs found in the command line arguments, other arguments
+    /// are ignored.
+    pub fn with_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.layers.push(Layer::CommandLine(
+            args.into_iter().map(Into::into).collect(),
+        ));
+        self
+    }
+
+    /// Loads and merges all layers.
+    pub fn load(self) -> Result<LayeredConfig<T>, ConfigError> {
+        let mut layered = Layered {
+            value: serde_value::to_value(T::default()).map_err(layer_error)?,
+            config: None,
+            sources: BTreeMap::new(),
+        };
+        record_sources(
+            &layered.value,
+            &mut String::new(),
+            &ConfigSource::Default,
+            &mut layered.sources,
+        );
+
+        for layer in self.layers {
+            match layer {
+                Layer::File { path, required } => {
+                    if !required && !path.exists() {
+                        debug!("Skipping missing config file '{}'", path.display());
+                        continue;
+                    }
+                    let layer = load_file(&path)?;
+                    layered.apply(layer, ConfigSource::File(path))?;
+                }
+                Layer::Environment(prefix) => {
+                    let prefix = format!("{}_", prefix);
+                    let mut vars = env::vars()
+                        .filter(|&(ref var, _)| var.starts_with(&prefix))
+                        .collect::<Vec<_>>();
+                    vars.sort();
+                    for (var, raw) in vars {
+                        let key = var[prefix.len()..]
+                            .split("__")
+                            .map(str::to_lowercase)
+                            .collect::<Vec<_>>()
+                            .join(".");
+                        layered.set(&key, &raw, ConfigSource::Environment(var.clone()))?;
+                    }
+                }
+                Layer::CommandLine(args) => {
+                    let mut args = args.into_iter();
+                    while let Some(arg) = args.next() {
+                        let pair = if arg == "--set" {
+                            args.next().ok_or_else(|| {
+                                ConfigError::Layer("Missing key=value after --set".to_owned())
+                            })?
+                        } else if arg.starts_with("--set=") {
+                            arg["--set=".len()..].to_owned()
+                        } else {
+                            continue;
+                        };
+
+                        let split = pair.find('=').ok_or_else(|| {
+                            ConfigError::Layer(format!("Expected key=value after --set: {}", pair))
+                        })?;
+                        layered.set(
+                            &pair[..split],
+                            &pair[split + 1..],
+                            ConfigSource::CommandLine,
+                        )?;
+                    }
+                }
+            }
+        }
+
+        Ok(LayeredConfig {
+            config: layered.config.unwrap_or_default(),
+            sources: layered.sources,
+        })
+    }
+}
+
+/// The state of `ConfigLayers::load` between two layers.
+struct Layered<T> {
+    /// The configuration merged so far, as a tree of values.
+    value: Value,
+    /// The configuration merged so far, `None` if only the default was loaded.
+    config: Option<T>,
+    sources: BTreeMap<String, ConfigSource>,
+}
+
+impl<T> Layered<T>
+where
+    T: for<'a> Deserialize<'a> + Serialize,
+{
+    /// Merges the layer into the configuration. The configuration type decides which values
+    /// are merged and which are replaced, so enum variants can be changed by later layers.
+    fn apply(&mut self, layer: Value, source: ConfigSource) -> Result<(), ConfigError> {
+        let fallback = mem::replace(&mut self.value, Value::Unit);
+        let config: T =
+            merge(Some(layer), Some(fallback), &source, &mut self.sources).map_err(|err| {
+                ConfigError::Layer(format!("Invalid value in the {}: {}", source, err))
+            })?;
+        self.value = serde_value::to_value(&config).map_err(layer_error)?;
+        self.config = Some(config);
+        Ok(())
+    }
+
+    /// Sets the value at the dotted `key`, parsed from `raw`.
+    fn set(&mut self, key: &str, raw: &str, source: ConfigSource) -> Result<(), ConfigError> {
+        let fields = key.split('.').collect::<Vec<_>>();
+        let mut target = Some(&self.value);
+        for field in &fields {
+            if field.is_empty() {
+                return Err(ConfigError::Layer(format!("Invalid config key: {}", key)));
+            }
+            target = match target {
+                Some(&Value::Map(ref map)) => map.get(&value::key(field)),
+                Some(value) if !value::is_missing(value) => {
+                    return Err(ConfigError::Layer(format!(
+                        "Cannot set {} from {}, it is not inside a struct or map",
+                        key, source
+                    )));
+                }
+                _ => None,
+            };
+        }
+
+        let layer = fields
+            .iter()
+            .rev()
+            .fold(parse_override(raw), |layer, field| {
+                let mut map = BTreeMap::new();
+                map.insert(value::key(field), layer);
+                Value::Map(map)
+            });
+        self.apply(layer, source)
+    }
+}
+
+impl<T> ConfigLayers<T>
+where
+    T: for<'a> Deserialize<'a> + Serialize + Default + Validate,
+{
+    /// Loads and merges all layers, then validates the result. Invalid values report the file
+    /// they came from.
+    pub fn load_validated(self) -> Result<LayeredConfig<T>, ConfigError> {
+        let layered = self.load()?;
+        let mut errors = ValidationErrors::new();
+        layered.config.validate(&mut errors);
+        if errors.is_empty() {
+            return Ok(layered);
+        }
+
+        let errors = errors
+            .into_errors()
+            .into_iter()
+            .map(|mut error| {
+                if let Some(&ConfigSource::File(ref path)) = error
+                    .location
+                    .field
+                    .as_ref()
+                    .and_then(|field| layered.source(field))
+                {
+                    error.location.file = Some(path.clone());
+                }
+                error
+            })
+            .collect();
+        Err(ConfigError::Validation(errors))
+    }
+}
+
+fn layer_error<E: fmt::Display>(err: E) -> ConfigError {
+    ConfigError::Layer(err.to_string())
+}
+
+fn parse_override(raw: &str) -> Value {
+    reader::read(raw.as_bytes(), None).unwrap_or_else(|_| Value::String(raw.to_owned()))
+}
+
+fn record_sources(
+    value: &Value,
+    path: &mut String,
+    source: &ConfigSource,
+    sources: &mut BTreeMap<String, ConfigSource>,
+) {
+    match *value {
+        Value::Map(ref map) if !map.is_empty() => {
+            for (key, value) in map {
+                let length = path.len();
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(&value::key_name(key));
+                record_sources(value, path, source, sources);
+                path.truncate(length);
+            }
+        }
+        _ => {
+            sources.insert(path.clone(), source.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use serde_derive::{Deserialize, Serialize};
+
+    use super::{ConfigLayers, ConfigSource};
+    use crate::{Config, ConfigError, Validate, ValidationErrors};
+
+    #[derive(Debug, Default, Deserialize, Serialize)]
+    struct Display {
+        title: String,
+        vsync: bool,
+        dimensions: (u16, u16),
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    enum Mode {
+        Windowed,
+        Fullscreen,
+        Sized(u32),
+    }
+
+    impl Default for Mode {
+        fn default() -> Self {
+            Mode::Windowed
+        }
+    }
+
+    #[derive(Debug, Default, Deserialize, Serialize)]
+    struct Game {
+        display: Display,
+        mode: Mode,
+        volume: f32,
+    }
+
+    impl Validate for Game {
+        fn validate(&self, errors: &mut ValidationErrors) {
+            errors.range("volume", f64::from(self.volume), Some(0.0), Some(1.0));
+        }
+    }
+
+    #[test]
+    fn layers_override_in_order() {
+        let path = env::temp_dir().join("amethyst_config_layers.ron");
+        fs::write(
+            &path,
+            "(display: (title: \"Game\", vsync: true, dimensions: (800, 600)), mode: Fullscreen, volume: 1)",
+        )
+        .unwrap();
+        env::set_var("LAYERS_TEST_DISPLAY__VSYNC", "false");
+
+        let layered = ConfigLayers::<Game>::new()
+            .with_file(&path)
+            .with_optional_file(env::temp_dir().join("amethyst_config_missing.ron"))
+            .with_env_prefix("LAYERS_TEST")
+            .with_args(vec![
+                "game",
+                "--set",
+                "volume=0.5",
+                "--set=display.title=Other",
+            ])
+            .load()
+            .unwrap();
+
+        assert_eq!("Other", layered.config.display.title);
+        assert!(!layered.config.display.vsync);
+        assert_eq!((800, 600), layered.config.display.dimensions);
+        assert_eq!(Mode::Fullscreen, layered.config.mode);
+        assert!((layered.config.volume - 0.5).abs() < ::std::f32::EPSILON);
+
+        assert_eq!(
+            Some(&ConfigSource::Environment(
+                "LAYERS_TEST_DISPLAY__VSYNC".to_owned()
+            )),
+            layered.source("display.vsync")
+        );
+        assert_eq!(
+            Some(&ConfigSource::File(path.clone())),
+            layered.source("display.dimensions")
+        );
+        assert_eq!(
+            Some(&ConfigSource::CommandLine),
+            layered.source("display.title")
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn layers_enum_variants() {
+        let shipped =
+            env::temp_dir().join(format!("amethyst_config_shipped_{}.ron", process::id()));
+        let user = env::temp_dir().join(format!("amethyst_config_user_{}.ron", process::id()));
+        Game {
+            mode: Mode::Sized(3),
+            volume: 0.5,
+            ..Default::default()
+        }
+        .write(&shipped)
+        .unwrap();
+        fs::write(&user, "(mode: Fullscreen, display: (vsync: true))").unwrap();
+
+        let layered = ConfigLayers::<Game>::new()
+            .with_file(&shipped)
+            .load()
+            .unwrap();
+        assert_eq!(Mode::Sized(3), layered.config.mode);
+
+        let layered = ConfigLayers::<Game>::new()
+            .with_file(&shipped)
+            .with_file(&user)
+            .load()
+            .unwrap();
+        assert_eq!(Mode::Fullscreen, layered.config.mode);
+        assert!(layered.config.display.vsync);
+        assert!((layered.config.volume - 0.5).abs() < ::std::f32::EPSILON);
+        assert_eq!(
+            Some(&ConfigSource::File(user.clone())),
+            layered.source("mode")
+        );
+        assert_eq!(
+            Some(&ConfigSource::File(shipped.clone())),
+            layered.source("volume")
+        );
+
+        let layered = ConfigLayers::<Game>::new()
+            .with_file(&user)
+            .with_args(vec!["--set", "mode=Sized(4)"])
+            .load()
+            .unwrap();
+        assert_eq!(Mode::Sized(4), layered.config.mode);
+        assert_eq!(Some(&ConfigSource::CommandLine), layered.source("mode"));
+
+        fs::remove_file(shipped).unwrap();
+        fs::remove_file(user).unwrap();
+    }
+
+    #[test]
+    fn bare_words_are_strings() {
+        env::set_var("BARE_TEST_DISPLAY__TITLE", "Game");
+
+        let layered = ConfigLayers::<Game>::new()
+            .with_env_prefix("BARE_TEST")
+            .with_args(vec!["--set", "mode=Fullscreen"])
+            .load()
+            .unwrap();
+        assert_eq!("Game", layered.config.display.title);
+        assert_eq!(Mode::Fullscreen, layered.config.mode);
+
+        let layered = ConfigLayers::<Game>::new()
+            .with_env_prefix("BARE_TEST")
+            .with_args(vec!["--set", "display.title=Hello"])
+            .load()
+            .unwrap();
+        assert_eq!("Hello", layered.config.display.title);
+        assert_eq!(Mode::Windowed, layered.config.mode);
+    }
+
+    #[test]
+    fn validation_is_opt_in() {
+        let layers = || ConfigLayers::<Game>::new().with_args(vec!["--set", "volume=2"]);
+
+        assert!(layers().load().is_ok());
+        match layers().load_validated() {
+            Err(ConfigError::Validation(errors)) => {
+                assert_eq!(Some("volume".to_owned()), errors[0].location.field);
+            }
+            other => panic!("Expected validation errors, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn invalid_override() {
+        let result = ConfigLayers::<Game>::new()
+            .with_args(vec!["--set", "volume"])
+            .load();
+        assert!(result.is_err());
+    }
+}
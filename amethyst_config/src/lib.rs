@@ -1,4 +1,4 @@
-//! Loads RON files into a structure for easy / statically typed usage.
+//! Loads RON, JSON, TOML or YAML files into a structure for easy / statically typed usage.
 //!
 
 #![crate_name = "amethyst_config"]
@@ -9,6 +9,14 @@
 extern crate log;
 use ron;
 
+mod format;
+mod layered;
+mod merge;
+mod migration;
+mod reader;
+mod validate;
+mod value;
+
 use std::{
     error::Error,
     fmt, io,
@@ -18,6 +26,19 @@ use std::{
 use ron::{de::Error as DeError, ser::Error as SerError};
 use serde::{Deserialize, Serialize};
 
+pub use crate::format::ConfigFormat;
+pub use crate::layered::{ConfigLayers, ConfigSource, LayeredConfig};
+pub use crate::migration::{ConfigMigrations, Migration, VERSION_FIELD};
+pub use crate::validate::{ErrorLocation, Validate, ValidationError, ValidationErrors};
+
+/// A configuration, or a part of one, as a tree of values independent of its type and file
+/// format. Used by `ConfigLayers` to merge layers and by `Migration::custom` to change old
+/// configuration files.
+///
+/// Unit enum variants are stored as strings, other variants as a map with the variant name as
+/// its only key, like `{"Sized": 3}` for `Sized(3)`.
+pub use serde_value::Value as ConfigValue;
+
 /// Error related to anything that manages/creates configurations as well as
 /// "workspace"-related things.
 #[derive(Debug)]
@@ -28,8 +49,33 @@ pub enum ConfigError {
     Parser(DeError),
     /// Occurs if a value is ill-formed during serialization (like a poisoned mutex).
     Serializer(SerError),
+    /// Errors related to reading or writing JSON configuration files.
+    #[cfg(feature = "json")]
+    Json(serde_json::Error),
+    /// Errors related to parsing TOML configuration files.
+    #[cfg(feature = "toml")]
+    TomlParser(toml::de::Error),
+    /// Errors related to writing TOML configuration files.
+    #[cfg(feature = "toml")]
+    TomlSerializer(toml::ser::Error),
+    /// Errors related to reading or writing YAML configuration files.
+    #[cfg(feature = "yaml")]
+    Yaml(serde_yaml::Error),
     /// Related to the path of the file.
     Extension(PathBuf),
+    /// A layer of a `ConfigLayers` could not be applied.
+    Layer(String),
+    /// A configuration file could not be upgraded to the current version.
+    Migration(String),
+    /// An error which occurred at a known location in a configuration file.
+    Located {
+        /// Where the error occurred.
+        location: ErrorLocation,
+        /// The error.
+        error: Box<ConfigError>,
+    },
+    /// The configuration contains invalid values, found by `Validate`.
+    Validation(Vec<ValidationError>),
 }
 
 impl fmt::Display for ConfigError {
@@ -38,6 +84,14 @@ impl fmt::Display for ConfigError {
             ConfigError::File(ref err) => write!(f, "{}", err),
             ConfigError::Parser(ref msg) => write!(f, "{}", msg),
             ConfigError::Serializer(ref msg) => write!(f, "{}", msg),
+            #[cfg(feature = "json")]
+            ConfigError::Json(ref err) => write!(f, "{}", err),
+            #[cfg(feature = "toml")]
+            ConfigError::TomlParser(ref err) => write!(f, "{}", err),
+            #[cfg(feature = "toml")]
+            ConfigError::TomlSerializer(ref err) => write!(f, "{}", err),
+            #[cfg(feature = "yaml")]
+            ConfigError::Yaml(ref err) => write!(f, "{}", err),
             ConfigError::Extension(ref path) => {
                 let found = match path.extension() {
                     Some(extension) => format!("{:?}", extension),
@@ -46,11 +100,25 @@ impl fmt::Display for ConfigError {
 
                 write!(
                     f,
-                    "{}: Invalid path extension, expected \"ron\", got {}.",
+                    "{}: Invalid path extension, expected one of {:?}, got {}.",
                     path.display().to_string(),
+                    ConfigFormat::extensions(),
                     found,
                 )
             }
+            ConfigError::Layer(ref msg) => write!(f, "{}", msg),
+            ConfigError::Migration(ref msg) => write!(f, "{}", msg),
+            ConfigError::Located {
+                ref location,
+                ref error,
+            } => write!(f, "{}: {}", location, error),
+            ConfigError::Validation(ref errors) => {
+                write!(f, "Invalid config values:")?;
+                for error in errors {
+                    write!(f, "\n{}", error)?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -79,13 +147,34 @@ impl Error for ConfigError {
             ConfigError::File(_) => "Project file error",
             ConfigError::Parser(_) => "Project parser error",
             ConfigError::Serializer(_) => "Project serializer error",
+            #[cfg(feature = "json")]
+            ConfigError::Json(_) => "Project JSON error",
+            #[cfg(feature = "toml")]
+            ConfigError::TomlParser(_) => "Project TOML parser error",
+            #[cfg(feature = "toml")]
+            ConfigError::TomlSerializer(_) => "Project TOML serializer error",
+            #[cfg(feature = "yaml")]
+            ConfigError::Yaml(_) => "Project YAML error",
             ConfigError::Extension(_) => "Invalid extension or directory for a file",
+            ConfigError::Layer(_) => "Config layer error",
+            ConfigError::Migration(_) => "Config migration error",
+            ConfigError::Located { .. } => "Project config error",
+            ConfigError::Validation(_) => "Invalid config values",
         }
     }
 
     fn cause(&self) -> Option<&dyn Error> {
         match *self {
             ConfigError::File(ref err) => Some(err),
+            #[cfg(feature = "json")]
+            ConfigError::Json(ref err) => Some(err),
+            ConfigError::Located { ref error, .. } => Some(&**error),
+            #[cfg(feature = "toml")]
+            ConfigError::TomlParser(ref err) => Some(err),
+            #[cfg(feature = "toml")]
+            ConfigError::TomlSerializer(ref err) => Some(err),
+            #[cfg(feature = "yaml")]
+            ConfigError::Yaml(ref err) => Some(err),
             _ => None,
         }
     }
@@ -100,19 +189,23 @@ where
     /// Defaults if the file fails in any way.
     fn load<P: AsRef<Path>>(path: P) -> Self;
 
-    /// Loads a configuration structure from a file.
+    /// Loads a configuration structure from a file, in the format matching its extension, and
+    /// validates it.
     fn load_no_fallback<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError>;
 
-    /// Loads configuration structure from raw bytes.
+    /// Loads configuration structure from raw RON bytes and validates it.
     fn load_bytes(bytes: &[u8]) -> Result<Self, ConfigError>;
 
-    /// Writes a configuration structure to a file.
+    /// Loads configuration structure from raw bytes in the given format and validates it.
+    fn load_bytes_with_format(bytes: &[u8], format: ConfigFormat) -> Result<Self, ConfigError>;
+
+    /// Writes a configuration structure to a file, in the format matching its extension.
     fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError>;
 }
 
 impl<T> Config for T
 where
-    T: for<'a> Deserialize<'a> + Serialize + Default,
+    T: for<'a> Deserialize<'a> + Serialize + Default + Validate,
 {
     fn load<P: AsRef<Path>>(path: P) -> Self {
         Self::load_no_fallback(path.as_ref()).unwrap_or_else(|e| {
@@ -127,42 +220,39 @@ where
     }
 
     fn load_no_fallback<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
-        use std::fs::File;
-        use std::io::Read;
-
         let path = path.as_ref();
+        let content = read_file(path)?;
 
-        let content = {
-            let mut file = File::open(path)?;
-            let mut buffer = Vec::new();
-            file.read_to_end(&mut buffer)?;
-
-            buffer
-        };
-
-        if path.extension().and_then(|e| e.to_str()) == Some("ron") {
-            Self::load_bytes(&content)
-        } else {
-            Err(ConfigError::Extension(path.to_path_buf()))
-        }
+        validate::load_validated(&content, ConfigFormat::from_path(path)?, Some(path))
     }
 
     fn load_bytes(bytes: &[u8]) -> Result<Self, ConfigError> {
-        let mut de = ron::de::Deserializer::from_bytes(bytes)?;
-        let val = T::deserialize(&mut de)?;
-        de.end()?;
+        Self::load_bytes_with_format(bytes, ConfigFormat::Ron)
+    }
 
-        Ok(val)
+    fn load_bytes_with_format(bytes: &[u8], format: ConfigFormat) -> Result<Self, ConfigError> {
+        validate::load_validated(bytes, format, None)
     }
 
     fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
-        use ron::ser::to_string_pretty;
         use std::fs::File;
         use std::io::Write;
 
-        let s = to_string_pretty(self, Default::default())?;
+        let path = path.as_ref();
+        let s = ConfigFormat::from_path(path)?.serialize(self)?;
         File::create(path)?.write_all(s.as_bytes())?;
 
         Ok(())
     }
 }
+
+fn read_file(path: &Path) -> Result<Vec<u8>, ConfigError> {
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut file = File::open(path)?;
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer)?;
+
+    Ok(buffer)
+}
@@ -0,0 +1,274 @@
+//! Merging a layer of a configuration into the values of the previous layers.
+//!
+//! The merge is guided by the configuration type: structs and maps are merged field by field,
+//! every other value, including enum variants, is replaced by the layer as a whole.
+
+use std::{collections::BTreeMap, vec};
+
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer, MapAccess, Visitor};
+use serde_value::{DeserializerError, Value, ValueDeserializer};
+
+use crate::{layered::ConfigSource, value};
+
+/// Deserializes `layer` on top of `fallback`, recording the keys set by the layer as coming
+/// from `source`.
+pub(crate) fn merge<T>(
+    layer: Option<Value>,
+    fallback: Option<Value>,
+    source: &ConfigSource,
+    sources: &mut BTreeMap<String, ConfigSource>,
+) -> Result<T, DeserializerError>
+where
+    T: for<'a> Deserialize<'a>,
+{
+    T::deserialize(Merge {
+        layer,
+        fallback,
+        path: String::new(),
+        source,
+        sources,
+    })
+}
+
+/// Deserializes a value read from a configuration file.
+///
+/// Unlike `Value::deserialize_into` this accepts struct names and the RON encoding of newtype
+/// structs.
+pub(crate) fn from_value<T>(value: Value) -> Result<T, DeserializerError>
+where
+    T: for<'a> Deserialize<'a>,
+{
+    merge(
+        Some(value),
+        None,
+        &ConfigSource::Default,
+        &mut BTreeMap::new(),
+    )
+}
+
+/// Checks whether `key` is `path` or nested inside of it.
+pub(crate) fn is_within(key: &str, path: &str) -> bool {
+    path.is_empty() || key == path || (key.starts_with(path) && key[path.len()..].starts_with('.'))
+}
+
+struct Merge<'a> {
+    layer: Option<Value>,
+    fallback: Option<Value>,
+    path: String,
+    source: &'a ConfigSource,
+    sources: &'a mut BTreeMap<String, ConfigSource>,
+}
+
+impl<'a> Merge<'a> {
+    /// Picks the value of the layer if it has one, otherwise the value of the previous layers.
+    fn winner(self) -> ValueDeserializer<DeserializerError> {
+        let value = match self.layer {
+            Some(layer) => {
+                let path = self.path;
+                self.sources.retain(|key, _| !is_within(key, &path));
+                self.sources.insert(path, self.source.clone());
+                layer
+            }
+            None => self.fallback.unwrap_or(Value::Unit),
+        };
+        ValueDeserializer::new(value)
+    }
+
+    fn child(&mut self, key: &Value, layer: Option<Value>, fallback: Option<Value>) -> Merge<'_> {
+        let mut path = self.path.clone();
+        if !path.is_empty() {
+            path.push('.');
+        }
+        path.push_str(&value::key_name(key));
+        Merge {
+            layer,
+            fallback,
+            path,
+            source: self.source,
+            sources: &mut *self.sources,
+        }
+    }
+}
+
+/// Removes the name RON may write in front of a struct, like in `Display(vsync: true)`.
+fn strip_name(value: Value, name: &str) -> Value {
+    match value {
+        Value::Map(mut map) if map.len() == 1 && map.contains_key(&value::key(name)) => {
+            map.remove(&value::key(name)).unwrap()
+        }
+        value => value,
+    }
+}
+
+macro_rules! forward_to_winner {
+    ($($method:ident($($arg:ident: $ty:ty),*))*) => {
+        $(
+            fn $method<V: Visitor<'de>>(
+                self,
+                $($arg: $ty,)*
+                visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                self.winner().$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> Deserializer<'de> for Merge<'a> {
+    type Error = DeserializerError;
+
+    fn deserialize_map<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Self::Error> {
+        let layer = match self.layer.take() {
+            Some(Value::Map(layer)) => layer,
+            layer => {
+                self.layer = layer;
+                return self.winner().deserialize_map(visitor);
+            }
+        };
+        let mut fallback = match self.fallback.take() {
+            Some(Value::Map(fallback)) => fallback,
+            _ => BTreeMap::new(),
+        };
+
+        let mut entries = Vec::new();
+        for (key, layer) in layer {
+            let fallback = fallback.remove(&key);
+            entries.push((key, Some(layer), fallback));
+        }
+        entries.extend(
+            fallback
+                .into_iter()
+                .map(|(key, fallback)| (key, None, Some(fallback))),
+        );
+
+        visitor.visit_map(MergeMap {
+            merge: self,
+            entries: entries.into_iter(),
+            value: None,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        mut self,
+        name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.layer = self.layer.map(|layer| strip_name(layer, name));
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Self::Error> {
+        let layer = match self.layer.take() {
+            None => return self.winner().deserialize_option(visitor),
+            Some(Value::Option(None)) | Some(Value::Unit) => {
+                self.layer = Some(Value::Unit);
+                return self.winner().deserialize_option(visitor);
+            }
+            Some(Value::Option(Some(layer))) => *layer,
+            Some(layer) => layer,
+        };
+        let fallback = match self.fallback.take() {
+            Some(Value::Option(Some(fallback))) => Some(*fallback),
+            _ => None,
+        };
+
+        self.layer = Some(layer);
+        self.fallback = fallback;
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        mut self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        // RON writes newtype structs like tuples with a single element.
+        self.layer = self.layer.map(|layer| match strip_name(layer, name) {
+            Value::Seq(mut values) if values.len() == 1 => values.pop().unwrap(),
+            Value::Newtype(value) => *value,
+            layer => layer,
+        });
+        self.fallback = self.fallback.map(|fallback| match fallback {
+            Value::Newtype(value) => *value,
+            fallback => fallback,
+        });
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        mut self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.layer = self.layer.map(|layer| strip_name(layer, name));
+        self.winner().deserialize_tuple_struct(name, len, visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        mut self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.layer = self.layer.map(|layer| match layer {
+            Value::String(ref layer) if layer == name => Value::Unit,
+            layer => layer,
+        });
+        self.winner().deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_winner! {
+        deserialize_any()
+        deserialize_bool() deserialize_char() deserialize_str() deserialize_string()
+        deserialize_i8() deserialize_i16() deserialize_i32() deserialize_i64()
+        deserialize_u8() deserialize_u16() deserialize_u32() deserialize_u64()
+        deserialize_f32() deserialize_f64()
+        deserialize_bytes() deserialize_byte_buf() deserialize_unit()
+        deserialize_seq() deserialize_tuple(len: usize) deserialize_identifier()
+        deserialize_enum(name: &'static str, variants: &'static [&'static str])
+    }
+}
+
+/// A key of a map, with its values in the layer and the previous layers.
+type Entry = (Value, Option<Value>, Option<Value>);
+
+struct MergeMap<'a> {
+    merge: Merge<'a>,
+    entries: vec::IntoIter<Entry>,
+    value: Option<Entry>,
+}
+
+impl<'de, 'a> MapAccess<'de> for MergeMap<'a> {
+    type Error = DeserializerError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.entries.next() {
+            Some(entry) => {
+                let key =
+                    seed.deserialize(ValueDeserializer::<Self::Error>::new(entry.0.clone()))?;
+                self.value = Some(entry);
+                Ok(Some(key))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<S>(&mut self, seed: S) -> Result<S::Value, Self::Error>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        let (key, layer, fallback) = self
+            .value
+            .take()
+            .ok_or_else(|| <Self::Error as de::Error>::custom("Value requested before its key"))?;
+        seed.deserialize(self.merge.child(&key, layer, fallback))
+    }
+}
@@ -0,0 +1,425 @@
+//! Upgrading configuration files written for older versions of a configuration struct.
+
+use std::{collections::BTreeMap, fs::File, io::Write, path::Path};
+
+use serde::{Deserialize, Serialize};
+use serde_value::Value;
+
+use crate::{
+    merge,
+    value::{self, load_file},
+    ConfigError, ConfigFormat,
+};
+
+/// The name of the field holding the version of a configuration file.
+pub const VERSION_FIELD: &str = "version";
+
+enum Change {
+    Rename(String, String),
+    Add(String, Value),
+    Remove(String),
+    Custom(
+        String,
+        Box<dyn Fn(&mut Value) -> Result<(), String> + Send + Sync>,
+    ),
+}
+
+/// The changes upgrading a configuration file from one version to the next.
+///
+/// Fields are addressed by their path of field names separated by dots, like `display.vsync`.
+pub struct Migration {
+    from: u32,
+    changes: Vec<Change>,
+}
+
+impl Migration {
+    /// Creates a migration upgrading files of version `from` to `from + 1`.
+    pub fn from_version(from: u32) -> Self {
+        Migration {
+            from,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Renames a field, keeping its value.
+    pub fn rename(mut self, from: &str, to: &str) -> Self {
+        self.changes
+            .push(Change::Rename(from.to_owned(), to.to_owned()));
+        self
+    }
+
+    /// Adds a field with the given value, unless the file already contains it.
+    ///
+    /// ## Panics
+    /// This will panic if `value` cannot be serialized.
+    pub fn add<V: Serialize>(mut self, field: &str, value: V) -> Self {
+        let value = serde_value::to_value(value).expect("Failed to serialize migrated value");
+        self.changes.push(Change::Add(field.to_owned(), value));
+        self
+    }
+
+    /// Removes a field.
+    pub fn remove(mut self, field: &str) -> Self {
+        self.changes.push(Change::Remove(field.to_owned()));
+        self
+    }
+
+    /// Changes the file with a custom function, which works on the file's contents as a
+    /// `ConfigValue`. The description is logged when the change is applied.
+    pub fn custom<F>(mut self, description: &str, change: F) -> Self
+    where
+        F: Fn(&mut Value) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.changes
+            .push(Change::Custom(description.to_owned(), Box::new(change)));
+        self
+    }
+
+    fn apply(&self, value: &mut Value, log: &mut Vec<String>) -> Result<(), ConfigError> {
+        for change in &self.changes {
+            match *change {
+                Change::Rename(ref from, ref to) => {
+                    if let Some(moved) = take(value, from) {
+                        insert(value, to, moved)?;
+                        log.push(format!("renamed `{}` to `{}`", from, to));
+                    }
+                }
+                Change::Add(ref field, ref added) => {
+                    if get(value, field).is_none() {
+                        insert(value, field, added.clone())?;
+                        log.push(format!("added `{}` with {}", field, value::to_ron(added)));
+                    }
+                }
+                Change::Remove(ref field) => {
+                    if take(value, field).is_some() {
+                        log.push(format!("removed `{}`", field));
+                    }
+                }
+                Change::Custom(ref description, ref change) => {
+                    change(value).map_err(|err| {
+                        ConfigError::Migration(format!("{}: {}", description, err))
+                    })?;
+                    log.push(description.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A registry of migrations, upgrading configuration files step by step to the current
+/// version of a configuration struct.
+///
+/// Versioned configuration files contain a top-level `version` field, the configuration struct
+/// should have a matching `version: u32` field defaulting to the current version. Files without
+/// a `version` field are treated as version `0`.
+///
+/// # Example
+/// ```rust,ignore
+/// let migrations = ConfigMigrations::new(2)
+///     .with_migration(Migration::from_version(0).rename("size", "dimensions"))
+///     .with_migration(Migration::from_version(1).add("vsync", true).remove("brightness"));
+/// let config: DisplayConfig = migrations.load("resources/display.ron", true)?;
+/// ```
+pub struct ConfigMigrations {
+    current: u32,
+    migrations: BTreeMap<u32, Migration>,
+}
+
+impl ConfigMigrations {
+    /// Creates an empty registry for configurations of version `current`.
+    pub fn new(current: u32) -> Self {
+        ConfigMigrations {
+            current,
+            migrations: BTreeMap::new(),
+        }
+    }
+
+    /// Registers a migration, replacing any migration from the same version.
+    pub fn with_migration(mut self, migration: Migration) -> Self {
+        self.migrations.insert(migration.from, migration);
+        self
+    }
+
+    /// Gets the current version.
+    pub fn current(&self) -> u32 {
+        self.current
+    }
+
+    /// Loads a configuration file, upgrading it to the current version. Every applied change
+    /// is logged.
+    ///
+    /// If `rewrite` is `true` and the file was upgraded, the upgraded configuration is written
+    /// back to the file.
+    pub fn load<T, P>(&self, path: P, rewrite: bool) -> Result<T, ConfigError>
+    where
+        T: for<'a> Deserialize<'a> + Serialize,
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut value = load_file(path)?;
+        let version = self.migrate(&mut value, &path.display().to_string())?;
+        let config: T =
+            merge::from_value(value).map_err(|err| ConfigError::Migration(err.to_string()))?;
+
+        if rewrite && version != self.current {
+            if serde_value::to_value(&config)
+                .ok()
+                .and_then(|config| get(&config, VERSION_FIELD).and_then(value::as_u64))
+                != Some(u64::from(self.current))
+            {
+                return Err(ConfigError::Migration(format!(
+                    "Cannot rewrite '{}', the config struct has no `{}` field",
+                    path.display(),
+                    VERSION_FIELD
+                )));
+            }
+
+            let contents = ConfigFormat::from_path(path)?.serialize(&config)?;
+            File::create(path)?.write_all(contents.as_bytes())?;
+            info!(
+                "Rewrote config '{}' as version {}",
+                path.display(),
+                self.current
+            );
+        }
+
+        Ok(config)
+    }
+
+    /// Upgrades the contents of a configuration file in place, returning the version the file
+    /// had before. `name` is used to identify the file in the log.
+    pub fn migrate(&self, value: &mut Value, name: &str) -> Result<u32, ConfigError> {
+        let original = match get(value, VERSION_FIELD) {
+            None => 0,
+            Some(version) => value::as_u64(version).ok_or_else(|| {
+                ConfigError::Migration(format!(
+                    "Invalid config version: {}",
+                    value::to_ron(version)
+                ))
+            })? as u32,
+        };
+        if original > self.current {
+            return Err(ConfigError::Migration(format!(
+                "'{}' has version {}, which is newer than the supported version {}",
+                name, original, self.current
+            )));
+        }
+
+        let mut version = original;
+        while version < self.current {
+            let migration = self.migrations.get(&version).ok_or_else(|| {
+                ConfigError::Migration(format!(
+                    "No migration registered from config version {}",
+                    version
+                ))
+            })?;
+
+            let mut log = Vec::new();
+            migration.apply(value, &mut log)?;
+            version += 1;
+            insert(value, VERSION_FIELD, Value::U32(version))?;
+
+            info!(
+                "Migrated config '{}' from version {} to {}",
+                name,
+                version - 1,
+                version
+            );
+            for change in log {
+                info!("  {}", change);
+            }
+        }
+
+        Ok(original)
+    }
+}
+
+fn get<'a>(value: &'a Value, field: &str) -> Option<&'a Value> {
+    field.split('.').try_fold(value, |value, key| match *value {
+        Value::Map(ref map) => map.get(&value::key(key)),
+        _ => None,
+    })
+}
+
+fn take(value: &mut Value, field: &str) -> Option<Value> {
+    let (parent, key) = match field.rfind('.') {
+        Some(split) => (&field[..split], &field[split + 1..]),
+        None => ("", field),
+    };
+    let mut target = value;
+    if !parent.is_empty() {
+        for key in parent.split('.') {
+            target = match target {
+                Value::Map(map) => map.get_mut(&value::key(key))?,
+                _ => return None,
+            };
+        }
+    }
+    match target {
+        Value::Map(map) => map.remove(&value::key(key)),
+        _ => None,
+    }
+}
+
+fn insert(value: &mut Value, field: &str, inserted: Value) -> Result<(), ConfigError> {
+    let mut target = value;
+    let mut keys = field.split('.').peekable();
+    while let Some(key) = keys.next() {
+        if value::is_missing(target) {
+            *target = Value::Map(BTreeMap::new());
+        }
+        let map = match target {
+            Value::Map(map) => map,
+            _ => {
+                return Err(ConfigError::Migration(format!(
+                    "Cannot set `{}`, its parent is not a struct",
+                    field
+                )));
+            }
+        };
+        if keys.peek().is_none() {
+            map.insert(value::key(key), inserted);
+            return Ok(());
+        }
+        target = map.entry(value::key(key)).or_insert(Value::Unit);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use serde_derive::{Deserialize, Serialize};
+    use serde_value::Value;
+
+    use super::{ConfigMigrations, Migration};
+    use crate::{reader, Config, ConfigFormat, Validate};
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    #[serde(deny_unknown_fields)]
+    struct Display {
+        dimensions: (u32, u32),
+        vsync: bool,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    #[serde(deny_unknown_fields)]
+    struct Game {
+        version: u32,
+        display: Display,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    enum Mode {
+        Windowed,
+        Fullscreen,
+        Sized(u32),
+    }
+
+    impl Default for Mode {
+        fn default() -> Self {
+            Mode::Windowed
+        }
+    }
+
+    #[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+    #[serde(deny_unknown_fields)]
+    struct Window {
+        version: u32,
+        mode: Mode,
+        fallback: Mode,
+    }
+
+    impl Validate for Window {}
+
+    fn ron(source: &str) -> Value {
+        reader::read(source.as_bytes(), None).unwrap()
+    }
+
+    #[test]
+    fn migrate_step_by_step() {
+        let migrations = migrations();
+        let mut value = ron("(display: (size: (800, 600)), brightness: 1.0)");
+
+        assert_eq!(0, migrations.migrate(&mut value, "test").unwrap());
+        assert_eq!(
+            Game {
+                version: 2,
+                display: Display {
+                    dimensions: (800, 600),
+                    vsync: true,
+                },
+            },
+            value.deserialize_into().unwrap()
+        );
+    }
+
+    fn migrations() -> ConfigMigrations {
+        ConfigMigrations::new(2)
+            .with_migration(Migration::from_version(0).rename("display.size", "display.dimensions"))
+            .with_migration(
+                Migration::from_version(1)
+                    .add("display.vsync", true)
+                    .remove("brightness"),
+            )
+    }
+
+    #[test]
+    fn load_and_rewrite_ron_file() {
+        let path = env::temp_dir().join(format!("amethyst_config_migration_{}.ron", process::id()));
+        fs::write(
+            &path,
+            "(\n    display: (\n        size: (800, 600),\n    ),\n    brightness: 1.0,\n)",
+        )
+        .unwrap();
+        let expected = Game {
+            version: 2,
+            display: Display {
+                dimensions: (800, 600),
+                vsync: true,
+            },
+        };
+
+        assert_eq!(expected, migrations().load::<Game, _>(&path, true).unwrap());
+        let rewritten = ConfigFormat::Ron
+            .deserialize::<Game>(&fs::read(&path).unwrap())
+            .unwrap();
+        assert_eq!(expected, rewritten);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn migrate_enum_variants() {
+        let path = env::temp_dir().join(format!("amethyst_config_enums_{}.ron", process::id()));
+        fs::write(&path, "(mode: Sized(3), style: Fullscreen)").unwrap();
+        let migrations = ConfigMigrations::new(1).with_migration(
+            Migration::from_version(0)
+                .remove("style")
+                .add("fallback", Mode::Windowed),
+        );
+        let expected = Window {
+            version: 1,
+            mode: Mode::Sized(3),
+            fallback: Mode::Windowed,
+        };
+
+        assert_eq!(expected, migrations.load::<Window, _>(&path, true).unwrap());
+        assert_eq!(
+            expected,
+            migrations.load::<Window, _>(&path, false).unwrap()
+        );
+        assert_eq!(expected, Window::load_no_fallback(&path).unwrap());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn newer_version_fails() {
+        let migrations = ConfigMigrations::new(1);
+        let mut value = ron("(version: 2)");
+        assert!(migrations.migrate(&mut value, "test").is_err());
+    }
+}
@@ -0,0 +1,465 @@
+//! Reading RON documents into `ConfigValue` trees.
+//!
+//! `ron` drops the names of enum variants when reading a document without knowing its type, so
+//! RON files are read with this reader instead. Variants are stored like `serde_value` stores
+//! them: unit variants as strings and the other variants as a map from the variant name to the
+//! contents. Struct names, like in `Display(title: "Game")`, are stored the same way and
+//! removed again when the value is deserialized into the configuration struct.
+
+use std::{char, collections::BTreeMap, f64, path::Path, str};
+
+use ron::de::Error as DeError;
+use serde_value::Value;
+
+use crate::{ConfigError, ErrorLocation};
+
+/// Reads a RON document. Syntax errors are located in `file`.
+pub(crate) fn read(bytes: &[u8], file: Option<&Path>) -> Result<Value, ConfigError> {
+    let source = str::from_utf8(bytes).map_err(|err| ConfigError::Parser(err.into()))?;
+    let mut reader = Reader {
+        source,
+        position: 0,
+        file,
+    };
+    let value = reader.value()?;
+    reader.skip_whitespace();
+    if reader.position < source.len() {
+        return Err(reader.error("Unexpected trailing characters"));
+    }
+
+    Ok(value)
+}
+
+struct Reader<'a> {
+    source: &'a str,
+    position: usize,
+    file: Option<&'a Path>,
+}
+
+impl<'a> Reader<'a> {
+    fn value(&mut self) -> Result<Value, ConfigError> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        match self.peek() {
+            Some('(') => self.parenthesized(),
+            Some('[') => {
+                self.position += 1;
+                self.elements(']').map(Value::Seq)
+            }
+            Some('{') => {
+                self.position += 1;
+                self.map()
+            }
+            Some('"') => {
+                self.position += 1;
+                self.string().map(Value::String)
+            }
+            Some('r') if rest[1..].starts_with(|c| c == '"' || c == '#') => {
+                self.position += 1;
+                self.raw_string().map(Value::String)
+            }
+            Some('\'') => {
+                self.position += 1;
+                self.character().map(Value::Char)
+            }
+            Some(c) if c.is_ascii_digit() || c == '+' || c == '-' || c == '.' => self.number(),
+            Some(c) if c.is_alphabetic() || c == '_' => self.named(),
+            _ => Err(self.error("Expected a value")),
+        }
+    }
+
+    /// Reads the values starting with an identifier: booleans, options, floats like `inf`, enum
+    /// variants and structs prefixed with their name.
+    fn named(&mut self) -> Result<Value, ConfigError> {
+        let name = self.identifier();
+        match name {
+            "true" => return Ok(Value::Bool(true)),
+            "false" => return Ok(Value::Bool(false)),
+            "None" => return Ok(Value::Option(None)),
+            "inf" => return Ok(Value::F64(f64::INFINITY)),
+            "NaN" => return Ok(Value::F64(f64::NAN)),
+            _ => {}
+        }
+
+        self.skip_whitespace();
+        if !self.rest().starts_with('(') {
+            return Ok(Value::String(name.to_owned()));
+        }
+        let contents = match self.parenthesized()? {
+            // Newtype variants and `Some` contain a single value.
+            Value::Seq(ref mut values) if values.len() == 1 => values.pop().unwrap(),
+            contents => contents,
+        };
+        if name == "Some" {
+            return Ok(Value::Option(Some(Box::new(contents))));
+        }
+
+        let mut variant = BTreeMap::new();
+        variant.insert(Value::String(name.to_owned()), contents);
+        Ok(Value::Map(variant))
+    }
+
+    /// Reads a unit `()`, a tuple `(1, 2)` or a struct `(width: 1, height: 2)`.
+    fn parenthesized(&mut self) -> Result<Value, ConfigError> {
+        self.expect("(")?;
+        self.skip_whitespace();
+        if self.consume(")") {
+            return Ok(Value::Unit);
+        }
+
+        if self.at_field() {
+            let mut fields = BTreeMap::new();
+            loop {
+                self.skip_whitespace();
+                if self.consume(")") {
+                    break;
+                }
+                let name = self.identifier().to_owned();
+                self.expect(":")?;
+                fields.insert(Value::String(name), self.value()?);
+                if !self.comma() {
+                    self.expect(")")?;
+                    break;
+                }
+            }
+            Ok(Value::Map(fields))
+        } else {
+            self.elements(')').map(Value::Seq)
+        }
+    }
+
+    /// Checks whether the reader is at a struct field, an identifier followed by `:`.
+    fn at_field(&self) -> bool {
+        let rest = self.rest();
+        if !rest.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            return false;
+        }
+        let length = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or_else(|| rest.len());
+        let mut after = Reader {
+            position: self.position + length,
+            ..*self
+        };
+        after.skip_whitespace();
+        after.rest().starts_with(':')
+    }
+
+    /// Reads comma separated values up to `end`.
+    fn elements(&mut self, end: char) -> Result<Vec<Value>, ConfigError> {
+        let end = end.to_string();
+        let mut values = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.consume(&end) {
+                break;
+            }
+            values.push(self.value()?);
+            if !self.comma() {
+                self.expect(&end)?;
+                break;
+            }
+        }
+        Ok(values)
+    }
+
+    fn map(&mut self) -> Result<Value, ConfigError> {
+        let mut map = BTreeMap::new();
+        loop {
+            self.skip_whitespace();
+            if self.consume("}") {
+                break;
+            }
+            let key = self.value()?;
+            self.expect(":")?;
+            map.insert(key, self.value()?);
+            if !self.comma() {
+                self.expect("}")?;
+                break;
+            }
+        }
+        Ok(Value::Map(map))
+    }
+
+    fn string(&mut self) -> Result<String, ConfigError> {
+        let mut string = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => string.push(self.escape()?),
+                Some(c) => string.push(c),
+                None => return Err(self.error("Expected the end of the string")),
+            }
+        }
+    }
+
+    fn raw_string(&mut self) -> Result<String, ConfigError> {
+        let hashes = self.rest().chars().take_while(|&c| c == '#').count();
+        self.position += hashes;
+        self.expect("\"")?;
+        let end = format!("\"{}", "#".repeat(hashes));
+        match self.rest().find(&end) {
+            Some(length) => {
+                let string = self.rest()[..length].to_owned();
+                self.position += length + end.len();
+                Ok(string)
+            }
+            None => Err(self.error("Expected the end of the raw string")),
+        }
+    }
+
+    fn character(&mut self) -> Result<char, ConfigError> {
+        let c = match self.next() {
+            Some('\\') => self.escape()?,
+            Some(c) => c,
+            None => return Err(self.error("Expected a character")),
+        };
+        self.expect("'")?;
+        Ok(c)
+    }
+
+    fn escape(&mut self) -> Result<char, ConfigError> {
+        let c = match self.next() {
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('0') => '\0',
+            Some('x') => {
+                let code = self.rest().get(..2).unwrap_or("");
+                self.position += code.len();
+                u32::from_str_radix(code, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| self.error("Invalid escape"))?
+            }
+            Some('u') => {
+                self.expect("{")?;
+                let length = self.rest().find('}').unwrap_or(0);
+                let code = &self.rest()[..length];
+                self.position += length;
+                self.expect("}")?;
+                u32::from_str_radix(code, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| self.error("Invalid escape"))?
+            }
+            Some(c @ '\'') | Some(c @ '"') | Some(c @ '\\') => c,
+            _ => return Err(self.error("Invalid escape")),
+        };
+        Ok(c)
+    }
+
+    fn number(&mut self) -> Result<Value, ConfigError> {
+        let rest = self.rest();
+        let length = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || "+-._".contains(c)))
+            .unwrap_or_else(|| rest.len());
+        let text = rest[..length].replace('_', "");
+        let (negative, unsigned) = match text.chars().next() {
+            Some('-') => (true, &text[1..]),
+            Some('+') => (false, &text[1..]),
+            _ => (false, &text[..]),
+        };
+
+        let radix = match unsigned.get(..2) {
+            Some("0x") => 16,
+            Some("0o") => 8,
+            Some("0b") => 2,
+            _ => 10,
+        };
+        let value = if radix != 10 {
+            u64::from_str_radix(&unsigned[2..], radix)
+                .ok()
+                .and_then(|value| integer(negative, value))
+        } else if unsigned.contains(|c| c == '.' || c == 'e' || c == 'E')
+            || unsigned == "inf"
+            || unsigned == "NaN"
+        {
+            unsigned
+                .parse::<f64>()
+                .ok()
+                .map(|value| Value::F64(if negative { -value } else { value }))
+        } else {
+            unsigned
+                .parse::<u64>()
+                .ok()
+                .and_then(|value| integer(negative, value))
+        };
+
+        let value = value.ok_or_else(|| self.error("Invalid number"))?;
+        self.position += length;
+        Ok(value)
+    }
+
+    fn identifier(&mut self) -> &'a str {
+        let rest = self.rest();
+        let length = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or_else(|| rest.len());
+        self.position += length;
+        &rest[..length]
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.position += rest.len() - trimmed.len();
+            let skipped = if trimmed.starts_with("//") {
+                trimmed.find('\n').unwrap_or_else(|| trimmed.len())
+            } else if trimmed.starts_with("/*") {
+                trimmed.find("*/").map_or(trimmed.len(), |end| end + 2)
+            } else if trimmed.starts_with("#![") {
+                // Extensions like `#![enable(implicit_some)]`.
+                trimmed.find(']').map_or(trimmed.len(), |end| end + 1)
+            } else {
+                return;
+            };
+            self.position += skipped;
+        }
+    }
+
+    fn comma(&mut self) -> bool {
+        self.skip_whitespace();
+        self.consume(",")
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), ConfigError> {
+        self.skip_whitespace();
+        if self.consume(token) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected `{}`", token)))
+        }
+    }
+
+    fn consume(&mut self, token: &str) -> bool {
+        let found = self.rest().starts_with(token);
+        if found {
+            self.position += token.len();
+        }
+        found
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.position..]
+    }
+
+    fn error(&self, message: &str) -> ConfigError {
+        let before = &self.source[..self.position];
+        let line_start = before.rfind('\n').map_or(0, |start| start + 1);
+        ConfigError::Located {
+            location: ErrorLocation {
+                file: self.file.map(Path::to_path_buf),
+                line: Some(before.matches('\n').count() + 1),
+                column: Some(before[line_start..].chars().count() + 1),
+                field: None,
+            },
+            error: Box::new(ConfigError::Parser(DeError::Message(message.to_owned()))),
+        }
+    }
+}
+
+fn integer(negative: bool, value: u64) -> Option<Value> {
+    const MAX: u64 = ::std::i64::MAX as u64;
+    match (negative, value) {
+        (false, value) if value <= MAX => Some(Value::I64(value as i64)),
+        (false, value) => Some(Value::U64(value)),
+        (true, value) if value <= MAX + 1 => Some(Value::I64((value as i64).wrapping_neg())),
+        (true, _) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_value::Value;
+
+    use super::read;
+    use crate::ConfigError;
+
+    fn map(entries: Vec<(&str, Value)>) -> Value {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(key, value)| (Value::String(key.to_owned()), value))
+                .collect::<BTreeMap<_, _>>(),
+        )
+    }
+
+    #[test]
+    fn keeps_variant_names() {
+        let source = "#![enable(implicit_some)]
+            (
+                mode: Fullscreen, // A unit variant.
+                size: Sized(3),
+                color: Rgb(1, 2, 0x3),
+                filter: Linear(mip: None, bias: -0.5),
+                title: Some(\"Game \\u{1F600}\"),
+                raw: r#\"C:\\Games\"#,
+                key: 'w',
+                bindings: { \"jump\": [Key(Space)] },
+            )";
+
+        assert_eq!(
+            map(vec![
+                ("mode", Value::String("Fullscreen".to_owned())),
+                ("size", map(vec![("Sized", Value::I64(3))])),
+                (
+                    "color",
+                    map(vec![(
+                        "Rgb",
+                        Value::Seq(vec![Value::I64(1), Value::I64(2), Value::I64(3)])
+                    )])
+                ),
+                (
+                    "filter",
+                    map(vec![(
+                        "Linear",
+                        map(vec![
+                            ("mip", Value::Option(None)),
+                            ("bias", Value::F64(-0.5)),
+                        ])
+                    )])
+                ),
+                (
+                    "title",
+                    Value::Option(Some(Box::new(Value::String("Game 😀".to_owned()))))
+                ),
+                ("raw", Value::String("C:\\Games".to_owned())),
+                ("key", Value::Char('w')),
+                (
+                    "bindings",
+                    map(vec![(
+                        "jump",
+                        Value::Seq(vec![map(vec![("Key", Value::String("Space".to_owned()))])])
+                    )])
+                ),
+            ]),
+            read(source.as_bytes(), None).unwrap()
+        );
+    }
+
+    #[test]
+    fn syntax_errors_are_located() {
+        match read(b"(\n    mode: Fullscreen,\n    volume: [1.0,\n)", None) {
+            Err(ConfigError::Located { location, .. }) => {
+                assert_eq!(Some(4), location.line);
+                assert_eq!(Some(1), location.column);
+            }
+            other => panic!("Expected a located error, got {:?}", other),
+        }
+        assert!(read(b"(mode: Fullscreen))", None).is_err());
+    }
+}
@@ -0,0 +1,397 @@
+//! Validating loaded configurations and locating errors in configuration files.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{ConfigError, ConfigFormat};
+
+/// Checks a loaded configuration for semantically invalid values, like a window size of `0`.
+///
+/// `Config::load_no_fallback` runs the validation after parsing a file and
+/// `ConfigLayers::load_validated` after merging the layers. The default implementation accepts
+/// every value, so configurations without invalid values only need an empty `impl Validate`.
+///
+/// The trait can be derived, the derived implementation requires `Validate` and
+/// `ValidationErrors` to be in scope. Fields can be checked with these attributes:
+///
+/// * `#[validate(range(min = 1, max = 240))]`, both bounds are inclusive and optional.
+/// * `#[validate(non_empty)]`, for strings and collections.
+/// * `#[validate(one_of("fifo", "mailbox"))]`.
+/// * `#[validate(nested)]`, for fields which implement `Validate` themselves.
+///
+/// # Example
+/// ```rust,ignore
+/// #[derive(Default, Deserialize, Serialize, Validate)]
+/// struct GraphicsConfig {
+///     #[validate(range(min = 1, max = 240))]
+///     fps: u32,
+///     #[validate(one_of("low", "medium", "high"))]
+///     quality: String,
+/// }
+/// ```
+pub trait Validate {
+    /// Adds an error for every invalid value.
+    fn validate(&self, errors: &mut ValidationErrors) {
+        let _ = errors;
+    }
+}
+
+/// Where in a configuration an error occurred. Parts which are not known are `None`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ErrorLocation {
+    /// The configuration file.
+    pub file: Option<PathBuf>,
+    /// The line, starting at `1`.
+    pub line: Option<usize>,
+    /// The column, starting at `1`.
+    pub column: Option<usize>,
+    /// The path of field names leading to the value, separated by dots like `display.vsync`.
+    pub field: Option<String>,
+}
+
+impl fmt::Display for ErrorLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.file {
+            Some(ref file) => write!(f, "{}", file.display())?,
+            None => write!(f, "<config>")?,
+        }
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+        }
+        if let Some(ref field) = self.field {
+            write!(f, " at `{}`", field)?;
+        }
+        Ok(())
+    }
+}
+
+/// An invalid value found by `Validate`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    /// Where the invalid value is.
+    pub location: ErrorLocation,
+    /// Why the value is invalid.
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// Collects the errors found while validating a configuration.
+#[derive(Clone, Debug, Default)]
+pub struct ValidationErrors {
+    prefix: String,
+    errors: Vec<ValidationError>,
+}
+
+impl ValidationErrors {
+    /// Creates an empty set of errors.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds an error for the given field.
+    pub fn add<M: Into<String>>(&mut self, field: &str, message: M) {
+        let field = if self.prefix.is_empty() {
+            field.to_owned()
+        } else {
+            format!("{}.{}", self.prefix, field)
+        };
+        self.errors.push(ValidationError {
+            location: ErrorLocation {
+                field: Some(field),
+                ..Default::default()
+            },
+            message: message.into(),
+        });
+    }
+
+    /// Runs `validate` with the field names prefixed by `field`, used for nested structs.
+    pub fn nested<F>(&mut self, field: &str, validate: F)
+    where
+        F: FnOnce(&mut ValidationErrors),
+    {
+        let length = self.prefix.len();
+        if !self.prefix.is_empty() {
+            self.prefix.push('.');
+        }
+        self.prefix.push_str(field);
+        validate(self);
+        self.prefix.truncate(length);
+    }
+
+    /// Adds an error if `value` is outside of the inclusive bounds.
+    pub fn range(&mut self, field: &str, value: f64, min: Option<f64>, max: Option<f64>) {
+        let below = min.map_or(false, |min| value < min);
+        let above = max.map_or(false, |max| value > max);
+        if below || above {
+            let message = match (min, max) {
+                (Some(min), Some(max)) => format!("{} is not between {} and {}", value, min, max),
+                (Some(min), None) => format!("{} is less than {}", value, min),
+                (None, _) => format!("{} is greater than {}", value, max.unwrap_or(value)),
+            };
+            self.add(field, message);
+        }
+    }
+
+    /// Checks whether no errors were found.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Gets the errors found.
+    pub fn errors(&self) -> &[ValidationError] {
+        &self.errors
+    }
+
+    /// Unwraps the errors found.
+    pub fn into_errors(self) -> Vec<ValidationError> {
+        self.errors
+    }
+}
+
+/// Parses a configuration, locating parse errors in `bytes`.
+fn deserialize_located<T>(
+    bytes: &[u8],
+    format: ConfigFormat,
+    file: Option<&Path>,
+) -> Result<T, ConfigError>
+where
+    T: for<'a> Deserialize<'a>,
+{
+    format.deserialize::<T>(bytes).map_err(|error| {
+        let mut location = parse_error_location(&error, &String::from_utf8_lossy(bytes));
+        location.file = file.map(Path::to_path_buf);
+        ConfigError::Located {
+            location,
+            error: Box::new(error),
+        }
+    })
+}
+
+/// Parses and validates a configuration, locating errors in `bytes`.
+pub(crate) fn load_validated<T>(
+    bytes: &[u8],
+    format: ConfigFormat,
+    file: Option<&Path>,
+) -> Result<T, ConfigError>
+where
+    T: for<'a> Deserialize<'a> + Validate,
+{
+    let value = deserialize_located::<T>(bytes, format, file)?;
+    let source = String::from_utf8_lossy(bytes);
+
+    let mut errors = ValidationErrors::new();
+    value.validate(&mut errors);
+    if errors.is_empty() {
+        return Ok(value);
+    }
+
+    let errors = errors
+        .into_errors()
+        .into_iter()
+        .map(|mut error| {
+            error.location.file = file.map(Path::to_path_buf);
+            if let Some((line, column)) = error
+                .location
+                .field
+                .as_ref()
+                .and_then(|field| locate(&source, field))
+            {
+                error.location.line = Some(line);
+                error.location.column = Some(column);
+            }
+            error
+        })
+        .collect();
+    Err(ConfigError::Validation(errors))
+}
+
+/// Gets the position and, if the message names one, the field of a parse error.
+fn parse_error_location(error: &ConfigError, source: &str) -> ErrorLocation {
+    let mut location = ErrorLocation::default();
+    let position = match *error {
+        ConfigError::Parser(ron::de::Error::Parser(_, ref position)) => {
+            Some((position.line, position.col))
+        }
+        #[cfg(feature = "json")]
+        ConfigError::Json(ref err) => Some((err.line(), err.column())),
+        #[cfg(feature = "toml")]
+        ConfigError::TomlParser(ref err) => {
+            err.line_col().map(|(line, column)| (line + 1, column + 1))
+        }
+        #[cfg(feature = "yaml")]
+        ConfigError::Yaml(ref err) => err
+            .location()
+            .map(|location| (location.line(), location.column())),
+        _ => None,
+    };
+
+    // `serde` quotes field names in its messages, like "unknown field `fsp`".
+    let message = error.to_string();
+    location.field = message
+        .find('`')
+        .and_then(|start| {
+            let rest = &message[start + 1..];
+            rest.find('`').map(|end| rest[..end].to_owned())
+        })
+        .filter(|field| !field.is_empty());
+
+    let position = position.or_else(|| {
+        location
+            .field
+            .as_ref()
+            .and_then(|field| locate(source, field))
+    });
+    if let Some((line, column)) = position {
+        location.line = Some(line);
+        location.column = Some(column);
+    }
+    location
+}
+
+/// Finds the position of a field in the source of a configuration file, by searching for the
+/// names in the field path in order, each followed by `:` or `=`.
+fn locate(source: &str, field: &str) -> Option<(usize, usize)> {
+    let mut offset = 0;
+    let mut found = None;
+    for key in field.split('.') {
+        let index = offset + find_key(&source[offset..], key)?;
+        found = Some(index);
+        offset = index + key.len();
+    }
+    found.map(|index| {
+        let before = &source[..index];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |start| start + 1);
+        (line, source[line_start..index].chars().count() + 1)
+    })
+}
+
+fn find_key(source: &str, key: &str) -> Option<usize> {
+    let mut start = 0;
+    while let Some(index) = source[start..].find(key) {
+        let index = start + index;
+        let word_start = source[..index]
+            .chars()
+            .next_back()
+            .map_or(true, |c| !(c.is_alphanumeric() || c == '_'));
+        let after = source[index + key.len()..]
+            .trim_start_matches(|c| c == '"' || c == '\'')
+            .trim_start();
+        // TOML tables are written as `[display]`.
+        if word_start && (after.starts_with(':') || after.starts_with('=') || after.starts_with(']'))
+        {
+            return Some(index);
+        }
+        start = index + key.len();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_validated, locate, Validate, ValidationErrors};
+    use crate::{Config, ConfigError, ConfigFormat};
+    use serde_derive::{Deserialize, Serialize};
+    use std::path::Path;
+
+    #[derive(Debug, Default, Deserialize, Serialize)]
+    struct Display {
+        fps: u32,
+        title: String,
+    }
+
+    impl Validate for Display {
+        fn validate(&self, errors: &mut ValidationErrors) {
+            errors.range("fps", f64::from(self.fps), Some(1.0), Some(240.0));
+            if self.title.is_empty() {
+                errors.add("title", "must not be empty");
+            }
+        }
+    }
+
+    #[derive(Debug, Default, Deserialize, Serialize)]
+    struct Unchecked {
+        fps: u32,
+    }
+
+    impl Validate for Unchecked {}
+
+    #[derive(Debug, Default, Deserialize, Serialize)]
+    struct Game {
+        display: Display,
+    }
+
+    impl Validate for Game {
+        fn validate(&self, errors: &mut ValidationErrors) {
+            errors.nested("display", |errors| self.display.validate(errors));
+        }
+    }
+
+    #[test]
+    fn locate_nested_field() {
+        let source = "(\n    display: (\n        fps: 0,\n    ),\n)";
+        assert_eq!(Some((3, 9)), locate(source, "display.fps"));
+        assert_eq!(None, locate(source, "display.vsync"));
+    }
+
+    #[test]
+    fn validation_errors_are_located() {
+        let source = "(\n    display: (\n        fps: 0,\n        title: \"\",\n    ),\n)";
+        let result = load_validated::<Game>(
+            source.as_bytes(),
+            ConfigFormat::Ron,
+            Some(Path::new("game.ron")),
+        );
+
+        match result {
+            Err(ConfigError::Validation(errors)) => {
+                assert_eq!(2, errors.len());
+                assert_eq!(Some("display.fps".to_owned()), errors[0].location.field);
+                assert_eq!(Some(3), errors[0].location.line);
+                assert_eq!(Some(4), errors[1].location.line);
+                assert_eq!(
+                    Some(Path::new("game.ron").to_path_buf()),
+                    errors[1].location.file
+                );
+            }
+            other => panic!("Expected validation errors, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_errors_are_located() {
+        let source = "(\n    display: (\n        fps: 60,\n    ),\n)";
+        match load_validated::<Game>(source.as_bytes(), ConfigFormat::Ron, None) {
+            Err(ConfigError::Located { location, .. }) => {
+                assert_eq!(Some("title".to_owned()), location.field);
+            }
+            other => panic!("Expected a located error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn loading_validates() {
+        assert_eq!(0, Unchecked::load_bytes(b"(fps: 0)").unwrap().fps);
+
+        let source = b"(display: (fps: 0, title: \"Game\"))";
+        match Game::load_bytes(source) {
+            Err(ConfigError::Validation(errors)) => {
+                assert_eq!(Some("display.fps".to_owned()), errors[0].location.field);
+            }
+            other => panic!("Expected validation errors, got {:?}", other),
+        }
+        assert!(Game::load_bytes(b"(display: (fps: 60, title: \"Game\"))").is_ok());
+    }
+}
@@ -0,0 +1,61 @@
+//! Helpers for configurations loaded as `ConfigValue` trees, used for merging layers and
+//! migrating old files before the configuration type is deserialized.
+
+use std::{fs::File, io::Read, path::Path};
+
+use serde_value::Value;
+
+use crate::{reader, ConfigError, ConfigFormat};
+
+/// Reads a configuration file in the format matching its extension.
+pub(crate) fn load_file(path: &Path) -> Result<Value, ConfigError> {
+    let format = ConfigFormat::from_path(path)?;
+    let mut content = Vec::new();
+    File::open(path)?.read_to_end(&mut content)?;
+    if format == ConfigFormat::Ron {
+        reader::read(&content, Some(path))
+    } else {
+        format.deserialize(&content)
+    }
+}
+
+/// Creates the key of a struct field.
+pub(crate) fn key(name: &str) -> Value {
+    Value::String(name.to_owned())
+}
+
+/// Gets the name of a key, as used in dotted field paths.
+pub(crate) fn key_name(key: &Value) -> String {
+    match *key {
+        Value::String(ref name) => name.clone(),
+        ref other => to_ron(other),
+    }
+}
+
+/// Checks whether a value is a placeholder for a missing value.
+pub(crate) fn is_missing(value: &Value) -> bool {
+    match *value {
+        Value::Unit | Value::Option(None) => true,
+        _ => false,
+    }
+}
+
+/// Gets the value of a non-negative integer.
+pub(crate) fn as_u64(value: &Value) -> Option<u64> {
+    match *value {
+        Value::U8(v) => Some(u64::from(v)),
+        Value::U16(v) => Some(u64::from(v)),
+        Value::U32(v) => Some(u64::from(v)),
+        Value::U64(v) => Some(v),
+        Value::I8(v) if v >= 0 => Some(v as u64),
+        Value::I16(v) if v >= 0 => Some(v as u64),
+        Value::I32(v) if v >= 0 => Some(v as u64),
+        Value::I64(v) if v >= 0 => Some(v as u64),
+        _ => None,
+    }
+}
+
+/// Formats a value as RON, for log and error messages.
+pub(crate) fn to_ron(value: &Value) -> String {
+    ron::ser::to_string(value).unwrap_or_else(|_| format!("{:?}", value))
+}
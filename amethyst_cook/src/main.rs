@@ -0,0 +1,134 @@
+//! Cooks the assets listed in a manifest into a `CookedCache` ahead of time.
+//!
+//! ```text
+//! amethyst_cook <assets directory> <manifest> <cache directory>
+//! ```
+//!
+//! The manifest is a RON `AssetManifest` with the name, format and options of every asset,
+//! which have to match the ones used by the game for the cooked entries to be used:
+//!
+//! ```ron
+//! (
+//!     assets: [
+//!         Png((name: "texture/logo.png", format: (), options: (...))),
+//!         Obj((name: "mesh/cube.obj", format: (), options: ())),
+//!     ],
+//! )
+//! ```
+//!
+//! In the game, set the cache with `Loader::set_cooked_cache` and enable the formats with
+//! `Loader::register_cooked`.
+//!
+//! Only formats importing a single file (`SimpleFormat`s) can be cooked, so glTF scenes,
+//! which reference other files and are loaded as prefabs, are not supported.
+
+#![warn(missing_docs, rust_2018_idioms, rust_2018_compatibility)]
+
+use amethyst_assets as assets;
+use amethyst_renderer as renderer;
+
+#[macro_use]
+extern crate serde;
+
+use std::{env, fs, process};
+
+use crate::{
+    assets::{Asset, AssetManifest, CookedCache, Directory, ManifestEntry, Result, SimpleFormat},
+    renderer::{
+        BmpFormat, JpgFormat, Mesh, ObjFormat, PngFormat, Texture, TextureFormat, TgaFormat,
+    },
+};
+
+/// The assets which can be cooked.
+#[derive(Deserialize)]
+enum CookAsset {
+    Bmp(ManifestEntry<Texture, BmpFormat>),
+    Jpg(ManifestEntry<Texture, JpgFormat>),
+    Png(ManifestEntry<Texture, PngFormat>),
+    Tga(ManifestEntry<Texture, TgaFormat>),
+    Texture(ManifestEntry<Texture, TextureFormat>),
+    Obj(ManifestEntry<Mesh, ObjFormat>),
+}
+
+impl CookAsset {
+    fn cook(&self, cache: &CookedCache, source: &Directory) -> Result<bool> {
+        match *self {
+            CookAsset::Bmp(ref entry) => cook(entry, cache, source),
+            CookAsset::Jpg(ref entry) => cook(entry, cache, source),
+            CookAsset::Png(ref entry) => cook(entry, cache, source),
+            CookAsset::Tga(ref entry) => cook(entry, cache, source),
+            CookAsset::Texture(ref entry) => cook(entry, cache, source),
+            CookAsset::Obj(ref entry) => cook(entry, cache, source),
+        }
+    }
+
+    fn name(&self) -> &str {
+        match *self {
+            CookAsset::Bmp(ref entry) => &entry.name,
+            CookAsset::Jpg(ref entry) => &entry.name,
+            CookAsset::Png(ref entry) => &entry.name,
+            CookAsset::Tga(ref entry) => &entry.name,
+            CookAsset::Texture(ref entry) => &entry.name,
+            CookAsset::Obj(ref entry) => &entry.name,
+        }
+    }
+}
+
+fn cook<A, F>(entry: &ManifestEntry<A, F>, cache: &CookedCache, source: &Directory) -> Result<bool>
+where
+    A: Asset,
+    A::Data: serde::Serialize,
+    F: SimpleFormat<A> + Clone + Send + Sync + 'static,
+    F::Options: serde::Serialize,
+{
+    cache.cook::<A, F>(&entry.name, &entry.format, entry.options.clone(), source)
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.len() != 3 {
+        eprintln!("Usage: amethyst_cook <assets directory> <manifest> <cache directory>");
+        process::exit(2);
+    }
+
+    let manifest = fs::read(&args[1])
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| {
+            ron::de::from_bytes::<AssetManifest<CookAsset>>(&bytes).map_err(|e| e.to_string())
+        });
+    let manifest = match manifest {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            eprintln!("Failed to read manifest {:?}: {}", args[1], e);
+            process::exit(1);
+        }
+    };
+
+    let source = Directory::new(&args[0]);
+    let cache = CookedCache::new(&args[2]);
+    let (mut cooked, mut up_to_date, mut failed) = (0, 0, 0);
+    for asset in &manifest.assets {
+        match asset.cook(&cache, &source) {
+            Ok(true) => {
+                println!("Cooked {}", asset.name());
+                cooked += 1;
+            }
+            Ok(false) => up_to_date += 1,
+            Err(e) => {
+                eprintln!("Failed to cook {}: {}", asset.name(), e);
+                for cause in e.iter().skip(1) {
+                    eprintln!("  caused by: {}", cause);
+                }
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "{} cooked, {} up to date, {} failed",
+        cooked, up_to_date, failed
+    );
+    if failed > 0 {
+        process::exit(1);
+    }
+}
@@ -73,6 +73,8 @@ use std::{
     time::{Duration, Instant},
 };
 
+use amethyst_config::Validate;
+
 const ZERO: Duration = Duration::from_millis(0);
 
 /// Frame rate limiting strategy.
@@ -128,6 +130,9 @@ pub struct FrameRateLimitConfig {
     fps: u32,
 }
 
+// An fps of `0` disables the limit, so every value is valid.
+impl Validate for FrameRateLimitConfig {}
+
 impl Default for FrameRateLimitConfig {
     fn default() -> Self {
         FrameRateLimitConfig {
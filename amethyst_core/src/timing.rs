@@ -2,6 +2,8 @@
 
 use std::time::{Duration, Instant};
 
+use log::debug;
+
 /// Frame timing values.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Time {
@@ -19,6 +21,14 @@ pub struct Time {
     fixed_time: Duration,
     /// Time at which `State::fixed_update` was last called.
     pub last_fixed_update: Instant,
+    /// Real time accumulated since the last fixed update that has not yet been consumed.
+    fixed_time_accumulator: Duration,
+    /// Maximum number of fixed updates that may run during a single frame.
+    max_fixed_updates: u32,
+    /// Number of fixed updates that ran during the current frame.
+    fixed_updates_this_frame: u32,
+    /// Fraction of a fixed step left in the accumulator after the last fixed update.
+    interpolation_alpha: f32,
     /// The total number of frames that have been played in this session.
     frame_number: u64,
     ///Time elapsed since game start, ignoring the speed multipler.
@@ -60,6 +70,20 @@ impl Time {
         self.fixed_time
     }
 
+    /// Gets the maximum number of fixed updates that will run during a single frame.
+    pub fn max_fixed_updates(&self) -> u32 {
+        self.max_fixed_updates
+    }
+
+    /// Gets the fraction of a fixed step that has elapsed since the last fixed update, in the
+    /// range `[0.0, 1.0)`.
+    ///
+    /// This can be used to interpolate between the two most recent simulation states when
+    /// rendering.
+    pub fn interpolation_alpha(&self) -> f32 {
+        self.interpolation_alpha
+    }
+
     /// Gets the current frame number.  This increments by 1 every frame.  There is no frame 0.
     pub fn frame_number(&self) -> u64 {
         self.frame_number
@@ -136,6 +160,17 @@ impl Time {
         self.fixed_time = time;
     }
 
+    /// Sets the maximum number of fixed updates that will run during a single frame to catch up
+    /// with elapsed time. Any time beyond that is dropped, which keeps a slow frame from causing
+    /// ever slower frames.
+    ///
+    /// ## Panics
+    /// This will panic if `max` is 0.
+    pub fn set_max_fixed_updates(&mut self, max: u32) {
+        assert!(max > 0);
+        self.max_fixed_updates = max;
+    }
+
     /// Increments the current frame number by 1.
     ///
     /// This should only be called by the engine.  Bad things might happen if you call this in
@@ -156,12 +191,59 @@ impl Time {
         self.time_scale = multiplier;
     }
 
-    /// Indicates a fixed update just finished.
+    /// Adds the real time elapsed during the last frame to the fixed update accumulator.
+    ///
+    /// This should only be called by the engine.  Bad things might happen if you call this in
+    /// your game.
+    pub fn start_fixed_update(&mut self) {
+        self.fixed_time_accumulator += self.delta_real_time;
+        self.fixed_updates_this_frame = 0;
+    }
+
+    /// Consumes one fixed step from the accumulator, returning `true` if a fixed update should
+    /// run.
+    ///
+    /// This should only be called by the engine.  Bad things might happen if you call this in
+    /// your game.
+    pub fn step_fixed_update(&mut self) -> bool {
+        if self.fixed_updates_this_frame < self.max_fixed_updates
+            && self.fixed_time_accumulator >= self.fixed_time
+        {
+            self.fixed_time_accumulator -= self.fixed_time;
+            self.last_fixed_update += self.fixed_time;
+            self.fixed_updates_this_frame += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Indicates the fixed updates for this frame just finished.
     ///
     /// This should only be called by the engine.  Bad things might happen if you call this in
     /// your game.
     pub fn finish_fixed_update(&mut self) {
-        self.last_fixed_update += self.fixed_time
+        let fixed_nanos = duration_to_nanos(self.fixed_time);
+        if fixed_nanos == 0 {
+            self.fixed_time_accumulator = Duration::from_secs(0);
+            self.interpolation_alpha = 0.0;
+            return;
+        }
+
+        if self.fixed_time_accumulator >= self.fixed_time {
+            debug!(
+                "Fixed update fell behind by {:?}, dropping the excess time",
+                self.fixed_time_accumulator
+            );
+            let remainder =
+                nanos_to_duration(duration_to_nanos(self.fixed_time_accumulator) % fixed_nanos);
+            // Skip the dropped steps, so `last_fixed_update` only depends on the frame times.
+            self.last_fixed_update += self.fixed_time_accumulator - remainder;
+            self.fixed_time_accumulator = remainder;
+        }
+
+        self.interpolation_alpha =
+            duration_to_nanos(self.fixed_time_accumulator) as f32 / fixed_nanos as f32;
     }
 }
 
@@ -175,6 +257,10 @@ impl Default for Time {
             fixed_seconds: duration_to_secs(Duration::new(0, 16_666_666)),
             fixed_time: Duration::new(0, 16_666_666),
             last_fixed_update: Instant::now(),
+            fixed_time_accumulator: Duration::from_secs(0),
+            max_fixed_updates: 5,
+            fixed_updates_this_frame: 0,
+            interpolation_alpha: 0.0,
             frame_number: 0,
             absolute_real_time: Duration::default(),
             absolute_time: Duration::default(),
@@ -183,6 +269,24 @@ impl Default for Time {
     }
 }
 
+/// Determines how the time elapsed during a frame is measured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeSource {
+    /// Measures the real time that has passed, using the `Stopwatch`.
+    WallClock,
+    /// Advances time by the given fixed delta every frame, no matter how long the frame took.
+    ///
+    /// Useful for deterministic tests and simulations. Frames are not limited by the
+    /// `FrameLimiter` with this time source.
+    Manual(Duration),
+}
+
+impl Default for TimeSource {
+    fn default() -> Self {
+        TimeSource::WallClock
+    }
+}
+
 /// A stopwatch which accurately measures elapsed time.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Stopwatch {
@@ -249,6 +353,16 @@ impl Stopwatch {
     pub fn reset(&mut self) {
         *self = Stopwatch::Waiting;
     }
+
+    /// Adds the given duration to the elapsed time, without affecting whether the stopwatch is
+    /// running.
+    pub fn advance(&mut self, duration: Duration) {
+        *self = match *self {
+            Stopwatch::Waiting => Stopwatch::Ended(duration),
+            Stopwatch::Started(dur, start) => Stopwatch::Started(dur + duration, start),
+            Stopwatch::Ended(dur) => Stopwatch::Ended(dur + duration),
+        };
+    }
 }
 
 // Unit tests
@@ -256,7 +370,7 @@ impl Stopwatch {
 mod tests {
     use std::{thread, time::Duration};
 
-    use super::Stopwatch;
+    use super::{Stopwatch, Time};
 
     #[test]
     fn elapsed() {
@@ -324,6 +438,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn advance() {
+        let mut watch = Stopwatch::new();
+
+        watch.advance(Duration::from_millis(16));
+        watch.advance(Duration::from_millis(16));
+
+        assert_eq!(Duration::from_millis(32), watch.elapsed());
+    }
+
+    #[test]
+    fn fixed_update_catch_up() {
+        let mut time = Time::default();
+        time.set_fixed_time(Duration::from_millis(10));
+        time.set_delta_time(Duration::from_millis(35));
+
+        time.start_fixed_update();
+        let mut steps = 0;
+        while time.step_fixed_update() {
+            steps += 1;
+        }
+        time.finish_fixed_update();
+
+        assert_eq!(3, steps);
+        assert!((time.interpolation_alpha() - 0.5).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn fixed_update_max_steps() {
+        let mut time = Time::default();
+        time.set_fixed_time(Duration::from_millis(10));
+        time.set_max_fixed_updates(2);
+        time.set_delta_time(Duration::from_millis(55));
+        let start = time.last_fixed_update();
+
+        time.start_fixed_update();
+        let mut steps = 0;
+        while time.step_fixed_update() {
+            steps += 1;
+        }
+        time.finish_fixed_update();
+
+        assert_eq!(2, steps);
+        assert!((time.interpolation_alpha() - 0.5).abs() < 1.0e-4);
+        assert_eq!(start + Duration::from_millis(50), time.last_fixed_update());
+
+        // The dropped time must not be carried over into the next frame.
+        time.set_delta_time(Duration::from_millis(0));
+        time.start_fixed_update();
+        assert!(!time.step_fixed_update());
+        time.finish_fixed_update();
+    }
+
     // test that multiple start-stop cycles are cumulative
     #[test]
     fn stop_start() {
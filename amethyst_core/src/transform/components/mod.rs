@@ -6,6 +6,9 @@ pub use self::{
     transform::GlobalTransform,
 };
 
+#[cfg(feature = "saveload")]
+pub use self::parent::ParentSaveloadError;
+
 mod local_transform;
 mod parent;
 mod transform;
@@ -1,3 +1,6 @@
+#[cfg(feature = "saveload")]
+use std::{error::Error, fmt};
+
 use specs::prelude::{Component, DenseVecStorage, Entity, FlaggedStorage};
 pub use specs_hierarchy::HierarchyEvent;
 use specs_hierarchy::{Hierarchy, Parent as HParent};
@@ -18,6 +21,70 @@ impl Component for Parent {
     type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
 }
 
+#[cfg(feature = "saveload")]
+impl<M> specs::saveload::ConvertSaveload<M> for Parent
+where
+    M: specs::saveload::Marker,
+{
+    type Data = M;
+    type Error = ParentSaveloadError;
+
+    fn convert_into<F>(&self, mut ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        ids(self.entity).ok_or(ParentSaveloadError::Unmarked(self.entity))
+    }
+
+    fn convert_from<F>(data: Self::Data, mut ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        ids(data)
+            .map(|entity| Parent { entity })
+            .ok_or(ParentSaveloadError::Unallocated)
+    }
+}
+
+/// Error raised when a `Parent` can't be saved or loaded.
+#[cfg(feature = "saveload")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParentSaveloadError {
+    /// The parent entity of a saved entity is not marked, so it is not saved and can't be
+    /// referred to.
+    Unmarked(Entity),
+    /// No entity could be allocated for the parent of a loaded entity.
+    Unallocated,
+}
+
+#[cfg(feature = "saveload")]
+impl fmt::Display for ParentSaveloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ParentSaveloadError::Unmarked(entity) => write!(
+                f,
+                "The parent {:?} of a saved entity is not marked for saving",
+                entity
+            ),
+            ParentSaveloadError::Unallocated => {
+                write!(f, "Failed to allocate the parent of a loaded entity")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "saveload")]
+impl Error for ParentSaveloadError {
+    fn description(&self) -> &str {
+        match *self {
+            ParentSaveloadError::Unmarked(_) => {
+                "The parent of a saved entity is not marked for saving"
+            }
+            ParentSaveloadError::Unallocated => "Failed to allocate the parent of a loaded entity",
+        }
+    }
+}
+
 impl HParent for Parent {
     fn parent_entity(&self) -> Entity {
         self.entity
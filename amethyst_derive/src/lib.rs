@@ -13,6 +13,7 @@ use syn::DeriveInput;
 
 mod event_reader;
 mod prefab_data;
+mod validate;
 
 #[proc_macro_derive(EventReader, attributes(reader))]
 pub fn event_reader_derive(input: TokenStream) -> TokenStream {
@@ -30,3 +31,13 @@ pub fn prefab_data_derive(input: TokenStream) -> TokenStream {
     let gen = prefab_data::impl_prefab_data(&ast);
     gen.into()
 }
+
+/// Deriving `Validate` requires that `amethyst::config::{Validate, ValidationErrors}` are
+/// imported and visible in the current scope. Fields are checked according to their
+/// `#[validate(..)]` attributes, see the `Validate` trait for the supported checks.
+#[proc_macro_derive(Validate, attributes(validate))]
+pub fn validate_derive(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    let gen = validate::impl_validate(&ast);
+    gen.into()
+}
@@ -0,0 +1,134 @@
+use proc_macro2::TokenStream;
+use syn::{Attribute, Data, DeriveInput, Ident, Lit, Meta, NestedMeta};
+
+pub fn impl_validate(ast: &DeriveInput) -> TokenStream {
+    let base = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let fields = match ast.data {
+        Data::Struct(ref s) => &s.fields,
+        _ => panic!("Validate derive only supports structs"),
+    };
+    let checks = fields.iter().flat_map(|f| {
+        let ident = f
+            .ident
+            .as_ref()
+            .expect("Validate derive only supports named fields");
+        validations(&f.attrs[..])
+            .into_iter()
+            .map(move |validation| gen_check(ident, validation))
+    });
+
+    quote! {
+        impl #impl_generics Validate for #base #ty_generics #where_clause {
+            fn validate(&self, errors: &mut ValidationErrors) {
+                #(#checks)*
+            }
+        }
+    }
+}
+
+enum Validation {
+    Range(Option<Lit>, Option<Lit>),
+    NonEmpty,
+    OneOf(Vec<Lit>),
+    Nested,
+}
+
+fn gen_check(ident: &Ident, validation: Validation) -> TokenStream {
+    let name = ident.to_string();
+    match validation {
+        Validation::Range(min, max) => {
+            let min = bound(min);
+            let max = bound(max);
+            quote! {
+                errors.range(#name, self.#ident as f64, #min, #max);
+            }
+        }
+        Validation::NonEmpty => quote! {
+            if self.#ident.is_empty() {
+                errors.add(#name, "must not be empty");
+            }
+        },
+        Validation::OneOf(values) => {
+            let expected = values
+                .iter()
+                .map(|lit| match *lit {
+                    Lit::Str(ref s) => format!("{:?}", s.value()),
+                    ref other => quote!(#other).to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            quote! {
+                if ![#(#values),*].iter().any(|value| *value == self.#ident) {
+                    errors.add(#name, format!("must be one of {}", #expected));
+                }
+            }
+        }
+        Validation::Nested => quote! {
+            errors.nested(#name, |errors| Validate::validate(&self.#ident, errors));
+        },
+    }
+}
+
+fn bound(lit: Option<Lit>) -> TokenStream {
+    match lit {
+        Some(lit) => quote! { Some((#lit) as f64) },
+        None => quote! { None },
+    }
+}
+
+fn validations(attrs: &[Attribute]) -> Vec<Validation> {
+    let mut validations = Vec::new();
+    for meta in attrs
+        .iter()
+        .filter(|attr| attr.path.segments[0].ident == "validate")
+        .map(|attr| {
+            attr.interpret_meta()
+                .expect("validate attribute incorrectly defined")
+        })
+    {
+        let list = match meta {
+            Meta::List(l) => l,
+            _ => panic!("validate attribute must contain a list of validations"),
+        };
+        for nested_meta in list.nested.iter() {
+            let validation = match *nested_meta {
+                NestedMeta::Meta(Meta::Word(ref word)) if word == "non_empty" => {
+                    Validation::NonEmpty
+                }
+                NestedMeta::Meta(Meta::Word(ref word)) if word == "nested" => Validation::Nested,
+                NestedMeta::Meta(Meta::List(ref l)) if l.ident == "range" => {
+                    let mut min = None;
+                    let mut max = None;
+                    for bound in l.nested.iter() {
+                        match *bound {
+                            NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.ident == "min" => {
+                                min = Some(nv.lit.clone());
+                            }
+                            NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.ident == "max" => {
+                                max = Some(nv.lit.clone());
+                            }
+                            _ => panic!("range only supports `min = ..` and `max = ..`"),
+                        }
+                    }
+                    Validation::Range(min, max)
+                }
+                NestedMeta::Meta(Meta::List(ref l)) if l.ident == "one_of" => Validation::OneOf(
+                    l.nested
+                        .iter()
+                        .map(|value| match *value {
+                            NestedMeta::Literal(ref lit) => lit.clone(),
+                            _ => panic!("one_of only supports literals"),
+                        })
+                        .collect(),
+                ),
+                _ => panic!(
+                    "Unknown validation, expected `range`, `non_empty`, `one_of` or `nested`"
+                ),
+            };
+            validations.push(validation);
+        }
+    }
+    validations
+}
@@ -2,6 +2,7 @@
 extern crate amethyst_derive;
 
 use amethyst_assets::{PrefabData, PrefabError, ProgressCounter};
+use amethyst_config::{Validate, ValidationErrors};
 use amethyst_core::{
     shrev::{EventChannel, ReaderId},
     specs::{Component, DenseVecStorage, Entity, Read, Resources, SystemData, WriteStorage},
@@ -59,3 +60,47 @@ pub struct Outer {
     #[prefab(Component)]
     external: External,
 }
+
+#[derive(Validate)]
+pub struct WindowConfig {
+    #[validate(non_empty)]
+    title: String,
+    #[validate(range(min = 1, max = 240))]
+    fps: u32,
+    #[validate(range(min = 0.0))]
+    scale: f32,
+    #[validate(one_of("fifo", "mailbox"))]
+    present_mode: String,
+}
+
+#[derive(Validate)]
+pub struct GameConfig {
+    #[validate(nested)]
+    window: WindowConfig,
+    unchecked: u32,
+}
+
+#[test]
+fn derive_validate() {
+    let config = GameConfig {
+        window: WindowConfig {
+            title: String::new(),
+            fps: 0,
+            scale: 1.0,
+            present_mode: "immediate".to_owned(),
+        },
+        unchecked: 0,
+    };
+    let mut errors = ValidationErrors::new();
+    config.validate(&mut errors);
+
+    let fields = errors
+        .errors()
+        .iter()
+        .map(|error| error.location.field.clone().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec!["window.title", "window.fps", "window.present_mode"],
+        fields
+    );
+}
@@ -5,6 +5,8 @@ use std::{borrow::Borrow, hash::Hash};
 use fnv::FnvHashMap as HashMap;
 use smallvec::SmallVec;
 
+use amethyst_config::Validate;
+
 use super::{Axis, Button};
 
 /// Used for saving and loading input settings.
@@ -21,6 +23,13 @@ where
     pub(super) actions: HashMap<AC, SmallVec<[SmallVec<[Button; 2]>; 4]>>,
 }
 
+impl<AX, AC> Validate for Bindings<AX, AC>
+where
+    AX: Hash + Eq,
+    AC: Hash + Eq,
+{
+}
+
 impl<AX, AC> Bindings<AX, AC>
 where
     AX: Hash + Eq,
@@ -1,5 +1,6 @@
 //! Renderer configuration.
 
+use amethyst_config::{Validate, ValidationErrors};
 use winit::{self, dpi::LogicalSize, WindowBuilder};
 
 /// Structure for holding the renderer configuration.
@@ -31,6 +32,31 @@ pub struct DisplayConfig {
     pub visibility: bool,
 }
 
+impl Validate for DisplayConfig {
+    fn validate(&self, errors: &mut ValidationErrors) {
+        let dimensions = [
+            ("dimensions", self.dimensions),
+            ("min_dimensions", self.min_dimensions),
+            ("max_dimensions", self.max_dimensions),
+        ];
+        for &(field, dimensions) in &dimensions {
+            if let Some((width, height)) = dimensions {
+                if width == 0 || height == 0 {
+                    errors.add(field, "width and height must be greater than 0");
+                }
+            }
+        }
+
+        if let (Some((min_width, min_height)), Some((max_width, max_height))) =
+            (self.min_dimensions, self.max_dimensions)
+        {
+            if min_width > max_width || min_height > max_height {
+                errors.add("min_dimensions", "must not be larger than max_dimensions");
+            }
+        }
+    }
+}
+
 impl Default for DisplayConfig {
     fn default() -> Self {
         DisplayConfig {
@@ -5,7 +5,9 @@ pub use self::{mesh::*, mtl::*, texture::*};
 
 use serde::{de::DeserializeOwned, Serialize};
 
-use amethyst_assets::{AssetPrefab, Format, PrefabData, PrefabError, ProgressCounter};
+use amethyst_assets::{
+    AssetFormatRegistry, AssetPrefab, Format, PrefabData, PrefabError, ProgressCounter,
+};
 use amethyst_core::specs::prelude::Entity;
 
 use crate::{shape::InternalShape, Mesh, ShapePrefab, Texture};
@@ -14,6 +16,18 @@ mod mesh;
 mod mtl;
 mod texture;
 
+/// Registers the texture and mesh formats for their file extensions.
+///
+/// Textures are loaded with `TextureMetadata::srgb`; register a format again to use other
+/// options.
+pub fn register_formats(registry: &mut AssetFormatRegistry) {
+    registry.register::<Texture, _>(&["png"], PngFormat, TextureMetadata::srgb());
+    registry.register::<Texture, _>(&["jpg", "jpeg"], JpgFormat, TextureMetadata::srgb());
+    registry.register::<Texture, _>(&["bmp"], BmpFormat, TextureMetadata::srgb());
+    registry.register::<Texture, _>(&["tga"], TgaFormat, TextureMetadata::srgb());
+    registry.register::<Mesh, _>(&["obj"], ObjFormat, ());
+}
+
 /// Internal mesh loading
 ///
 /// ### Type parameters:
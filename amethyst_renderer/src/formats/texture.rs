@@ -6,6 +6,7 @@ use gfx::{
     traits::Pod,
 };
 use image::{DynamicImage, ImageFormat, RgbaImage};
+use serde::{de::Error as DeError, Deserialize, Deserializer, Serialize, Serializer};
 
 use amethyst_assets::{
     AssetStorage, Format, Handle, Loader, PrefabData, PrefabError, ProcessingState,
@@ -135,7 +136,6 @@ impl TextureMetadata {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum TextureData {
     /// Image data
-    #[serde(skip)]
     Image(ImageData, TextureMetadata),
 
     /// Color
@@ -278,12 +278,54 @@ where
 }
 
 /// ImageData provided by formats, can be interpreted as a texture.
+///
+/// Serialized as its dimensions and raw pixels, so decoded images can be cooked.
 #[derive(Clone, Debug)]
 pub struct ImageData {
     /// The raw image data.
     pub rgba: RgbaImage,
 }
 
+#[derive(Serialize)]
+struct RawImageRef<'a> {
+    width: u32,
+    height: u32,
+    rgba: &'a [u8],
+}
+
+#[derive(Deserialize)]
+struct RawImage {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+impl Serialize for ImageData {
+    fn serialize<S>(&self, serializer: S) -> StdResult<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        RawImageRef {
+            width: self.rgba.width(),
+            height: self.rgba.height(),
+            rgba: &self.rgba,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ImageData {
+    fn deserialize<D>(deserializer: D) -> StdResult<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = RawImage::deserialize(deserializer)?;
+        RgbaImage::from_raw(raw.width, raw.height, raw.rgba)
+            .map(|rgba| ImageData { rgba })
+            .ok_or_else(|| D::Error::custom("image data does not match its dimensions"))
+    }
+}
+
 fn load_into_rgba8_from_memory(
     data: &[u8],
     options: TextureMetadata,
@@ -65,10 +65,10 @@ pub use crate::{
     config::DisplayConfig,
     debug_drawing::{DebugLines, DebugLinesComponent},
     formats::{
-        build_mesh_with_combo, create_mesh_asset, create_texture_asset, BmpFormat,
-        ComboMeshCreator, GraphicsPrefab, ImageData, JpgFormat, MaterialPrefab, MeshCreator,
-        MeshData, ObjFormat, PngFormat, TextureData, TextureFormat, TextureMetadata, TexturePrefab,
-        TgaFormat,
+        build_mesh_with_combo, create_mesh_asset, create_texture_asset, register_formats,
+        BmpFormat, ComboMeshCreator, GraphicsPrefab, ImageData, JpgFormat, MaterialPrefab,
+        MeshCreator, MeshData, ObjFormat, PngFormat, TextureData, TextureFormat, TextureMetadata,
+        TexturePrefab, TgaFormat,
     },
     hidden::{Hidden, HiddenPropagate},
     hide_system::HideHierarchySystem,
@@ -1,8 +1,8 @@
 use ron::de::from_bytes as from_ron_bytes;
 
 use amethyst_assets::{
-    Asset, Error as AssetsError, ErrorKind as AssetsErrorKind, Handle, ProcessingState,
-    Result as AssetsResult, SimpleFormat,
+    record_dependency, Asset, Error as AssetsError, ErrorKind as AssetsErrorKind, Handle,
+    ProcessingState, Result as AssetsResult, SimpleFormat,
 };
 use amethyst_core::specs::prelude::{Component, DenseVecStorage, VecStorage};
 
@@ -317,6 +317,7 @@ impl SimpleFormat<SpriteSheet> for SpriteSheetFormat {
             );
             sprites.push(sprite);
         }
+        record_dependency(&texture);
         Ok(SpriteSheet { texture, sprites })
     }
 }
@@ -64,6 +64,19 @@ impl Asset for Texture {
     const NAME: &'static str = "renderer::Texture";
     type Data = TextureData;
     type HandleStorage = DenseVecStorage<TextureHandle>;
+
+    fn size_bytes(&self) -> usize {
+        let info = self.texture.get_info();
+        let (w, h, d, _) = info.kind.get_dimensions();
+        let bits = info.format.get_total_bits() as usize;
+        // Mipmaps add at most a third to the size of the base level.
+        let base = w as usize * h as usize * d.max(1) as usize * bits / 8;
+        if info.levels > 1 {
+            base + base / 3
+        } else {
+            base
+        }
+    }
 }
 
 /// Builds new textures.
@@ -1,6 +1,8 @@
 use gfx_glyph::Font;
 
-use amethyst_assets::{Asset, Error, Handle, ProcessingState, ResultExt, SimpleFormat};
+use amethyst_assets::{
+    Asset, AssetFormatRegistry, Error, Handle, ProcessingState, ResultExt, SimpleFormat,
+};
 use amethyst_core::specs::prelude::VecStorage;
 
 /// A loaded set of fonts from a file.
@@ -72,3 +74,8 @@ impl SimpleFormat<FontAsset> for FontFormat {
         }
     }
 }
+
+/// Registers the font formats for their file extensions.
+pub fn register_formats(registry: &mut AssetFormatRegistry) {
+    registry.register::<FontAsset, _>(&["ttf", "otf"], TtfFormat, ());
+}
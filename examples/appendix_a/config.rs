@@ -1,10 +1,16 @@
-use amethyst::core::nalgebra::Vector2;
+use amethyst::{
+    config::{Validate, ValidationErrors},
+    core::nalgebra::Vector2,
+    Validate,
+};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Validate)]
 pub struct ArenaConfig {
     /// Height of the arena. Used to define Y axis coordinate scheme
+    #[validate(range(min = 1.0))]
     pub height: f32,
     /// Width of the arena. Used to define X axis coordinate scheme
+    #[validate(range(min = 1.0))]
     pub width: f32,
 }
 
@@ -17,11 +23,12 @@ impl Default for ArenaConfig {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Validate)]
 pub struct BallConfig {
     /// X axis and Y axis velocity of the ball
     pub velocity: Vector2<f32>,
     /// Ball radius in pixels
+    #[validate(range(min = 0.0))]
     pub radius: f32,
     /// RGBA Color of the ball
     pub colour: (f32, f32, f32, f32),
@@ -37,17 +44,21 @@ impl Default for BallConfig {
     }
 }
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize, Validate)]
 pub struct PaddlesConfig {
+    #[validate(nested)]
     pub left: PaddleConfig,
+    #[validate(nested)]
     pub right: PaddleConfig,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Validate)]
 pub struct PaddleConfig {
     /// Height, in pixels, of the paddle
+    #[validate(range(min = 0.0))]
     pub height: f32,
     /// Width, in pixels, of the paddle
+    #[validate(range(min = 0.0))]
     pub width: f32,
     /// Velocity of the paddle's vertical movement
     pub velocity: f32,
@@ -66,12 +77,15 @@ impl Default for PaddleConfig {
     }
 }
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize, Validate)]
 pub struct PongConfig {
     /// Configuration of the paddles
+    #[validate(nested)]
     pub paddles: PaddlesConfig,
     /// Configuration of the ball
+    #[validate(nested)]
     pub ball: BallConfig,
     /// Configuration of the arena
+    #[validate(nested)]
     pub arena: ArenaConfig,
 }
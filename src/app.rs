@@ -1,6 +1,19 @@
 //! The core engine framework.
 
-use std::{error::Error as StdError, marker::PhantomData, path::Path, sync::Arc, time::Duration};
+use std::{
+    error::Error as StdError,
+    marker::PhantomData,
+    path::Path,
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::{RecvTimeoutError, Sender};
 
 use crate::shred::Resource;
 use log::Level;
@@ -11,25 +24,77 @@ use thread_profiler::{register_thread_with_profiler, write_profile};
 use winit::Event;
 
 use crate::{
-    assets::{Loader, Source},
+    assets::{AssetFormatRegistry, Loader, Source},
+    audio,
     callback_queue::CallbackQueue,
     core::{
         frame_limiter::{FrameLimiter, FrameRateLimitConfig, FrameRateLimitStrategy},
         shrev::{EventChannel, ReaderId},
-        timing::{Stopwatch, Time},
+        timing::{Stopwatch, Time, TimeSource},
         EventReader, Named,
     },
     ecs::{
         common::Errors,
+        error::BoxedErr,
         prelude::{Component, Read, World, Write},
     },
     error::{Error, Result},
     game_data::DataInit,
-    state::{State, StateData, StateMachine, TransEvent},
+    logger,
+    metrics::{FrameMetrics, FramePhase},
+    renderer,
+    state::{State, StateData, StateMachine, StateTransitionEvent, TransEvent},
     state_event::{StateEvent, StateEventReader},
-    ui::UiEvent,
+    task::TaskSpawner,
+    ui::{self, UiEvent},
 };
 
+/// Determines what the application does with the errors collected in the `Errors` resource at the
+/// end of every frame.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub enum ErrorPolicy {
+    /// Print all errors and exit the process. This is the default.
+    Exit,
+    /// Log every error and keep running.
+    LogAndContinue,
+    /// Pass every error to `State::handle_error` of the active state.
+    State,
+    /// Pass every error to the given closure.
+    Custom(#[derivative(Debug = "ignore")] Box<dyn FnMut(&mut World, BoxedErr)>),
+}
+
+impl Default for ErrorPolicy {
+    fn default() -> Self {
+        ErrorPolicy::Exit
+    }
+}
+
+/// A function that is run with the `World` when the application shuts down.
+pub type ShutdownHook = Box<dyn FnMut(&mut World)>;
+
+/// Determines what happens when the shutdown does not complete within the shutdown timeout.
+///
+/// The policy is applied from a background thread while the states are being stopped or the
+/// shutdown hooks are still running.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub enum ShutdownTimeoutPolicy {
+    /// Log an error and exit the process with exit code 1, without waiting for the remaining
+    /// shutdown hooks. This is the default.
+    Exit,
+    /// Log an error and keep waiting for the shutdown to complete.
+    Log,
+    /// Call the given closure with the timeout and keep waiting for the shutdown to complete.
+    Custom(#[derivative(Debug = "ignore")] Box<dyn Fn(Duration) + Send + Sync>),
+}
+
+impl Default for ShutdownTimeoutPolicy {
+    fn default() -> Self {
+        ShutdownTimeoutPolicy::Exit
+    }
+}
+
 /// `CoreApplication` is the application implementation for the game engine. This is fully generic
 /// over the state type and event type.
 ///
@@ -60,6 +125,12 @@ where
     trans_reader_id: ReaderId<TransEvent<T, E>>,
     states: StateMachine<'a, T, E>,
     ignore_window_close: bool,
+    headless: bool,
+    time_source: TimeSource,
+    error_policy: ErrorPolicy,
+    #[derivative(Debug = "ignore")]
+    shutdown_hooks: Vec<ShutdownHook>,
+    terminate: Arc<AtomicBool>,
     data: T,
 }
 
@@ -229,14 +300,30 @@ where
         self.initialize();
         self.world.write_resource::<Stopwatch>().start();
         while self.states.is_running() {
+            let frame_start = Instant::now();
             self.advance_frame();
 
-            self.world.write_resource::<FrameLimiter>().wait();
+            // Headless and manually timed applications run as fast as possible.
+            let manual_time = if let TimeSource::Manual(_) = self.time_source {
+                true
+            } else {
+                false
+            };
+            if !self.headless && !manual_time {
+                self.world.write_resource::<FrameLimiter>().wait();
+            }
+            self.record_phase(FramePhase::Frame, frame_start);
+            if let TimeSource::Manual(delta) = self.time_source {
+                let mut stopwatch = self.world.write_resource::<Stopwatch>();
+                stopwatch.reset();
+                stopwatch.advance(delta);
+            }
             {
                 let elapsed = self.world.read_resource::<Stopwatch>().elapsed();
                 let mut time = self.world.write_resource::<Time>();
                 time.increment_frame_number();
                 time.set_delta_time(elapsed);
+                logger::set_frame_number(time.frame_number());
             }
             let mut stopwatch = self.world.write_resource::<Stopwatch>();
             stopwatch.stop();
@@ -257,7 +344,7 @@ where
 
     // React to window close events
     fn should_close(&mut self) -> bool {
-        if self.ignore_window_close {
+        if self.headless || self.ignore_window_close {
             false
         } else {
             use crate::renderer::WindowEvent;
@@ -297,7 +384,12 @@ where
         for<'b> R: EventReader<'b, Event = E>,
     {
         trace!("Advancing frame (`Application::advance_frame`)");
-        if self.should_close() {
+        if self.terminate.load(Ordering::SeqCst) {
+            info!("Termination signal received, stopping the state machine");
+            let world = &mut self.world;
+            let states = &mut self.states;
+            states.stop(StateData::new(world, &mut self.data));
+        } else if self.should_close() {
             let world = &mut self.world;
             let states = &mut self.states;
             states.stop(StateData::new(world, &mut self.data));
@@ -332,6 +424,7 @@ where
         {
             #[cfg(feature = "profiler")]
             profile_scope!("handle_event");
+            let phase_start = Instant::now();
 
             {
                 let events = &mut self.events;
@@ -345,41 +438,131 @@ where
                     states.handle_event(StateData::new(world, &mut self.data), e);
                 }
             }
+            self.record_phase(FramePhase::HandleEvent, phase_start);
         }
         {
-            let do_fixed = {
-                let time = self.world.write_resource::<Time>();
-                time.last_fixed_update().elapsed() >= time.fixed_time()
-            };
             #[cfg(feature = "profiler")]
             profile_scope!("fixed_update");
-            if do_fixed {
+            let phase_start = Instant::now();
+            self.world.write_resource::<Time>().start_fixed_update();
+            while self.world.write_resource::<Time>().step_fixed_update() {
                 self.states
                     .fixed_update(StateData::new(&mut self.world, &mut self.data));
-                self.world.write_resource::<Time>().finish_fixed_update();
             }
+            self.world.write_resource::<Time>().finish_fixed_update();
+            self.record_phase(FramePhase::FixedUpdate, phase_start);
 
             #[cfg(feature = "profiler")]
             profile_scope!("update");
+            let phase_start = Instant::now();
             self.states
                 .update(StateData::new(&mut self.world, &mut self.data));
+            self.record_phase(FramePhase::Update, phase_start);
         }
 
         #[cfg(feature = "profiler")]
         profile_scope!("maintain");
+        let phase_start = Instant::now();
         self.world.maintain();
+        self.record_phase(FramePhase::Maintain, phase_start);
+        self.world
+            .write_resource::<FrameMetrics>()
+            .collect_system_timings();
+
+        self.handle_errors();
+    }
 
-        // TODO: replace this with a more customizable method.
-        // TODO: effectively, the user should have more control over error handling here
-        // TODO: because right now the app will just exit in case of an error.
-        self.world.write_resource::<Errors>().print_and_exit();
+    /// Records the time elapsed since `start` for the given frame phase.
+    fn record_phase(&mut self, phase: FramePhase, start: Instant) {
+        self.world
+            .write_resource::<FrameMetrics>()
+            .record_phase(phase, start.elapsed());
+    }
+
+    /// Applies the `ErrorPolicy` to the errors collected during this frame.
+    fn handle_errors(&mut self) {
+        if !self.world.read_resource::<Errors>().has_error() {
+            return;
+        }
+
+        #[cfg(feature = "profiler")]
+        profile_scope!("handle_errors");
+        if let ErrorPolicy::Exit = self.error_policy {
+            self.world.write_resource::<Errors>().print_and_exit();
+            return;
+        }
+
+        let errors = self.world.write_resource::<Errors>().collect();
+        for error in errors {
+            match self.error_policy {
+                ErrorPolicy::Exit => unreachable!(),
+                ErrorPolicy::LogAndContinue => error!("{}", error),
+                ErrorPolicy::State => self
+                    .states
+                    .handle_error(StateData::new(&mut self.world, &mut self.data), error),
+                ErrorPolicy::Custom(ref mut handler) => handler(&mut self.world, error),
+            }
+        }
     }
 
     /// Cleans up after the quit signal is received.
     fn shutdown(&mut self) {
         info!("Engine is shutting down");
 
-        // Placeholder.
+        self.world.write_resource::<ShutdownWatchdog>().start();
+        for hook in &mut self.shutdown_hooks {
+            hook(&mut self.world);
+        }
+        self.world.write_resource::<ShutdownWatchdog>().finish();
+    }
+}
+
+/// Applies the `ShutdownTimeoutPolicy` if the shutdown does not complete within the shutdown
+/// timeout.
+///
+/// This is a resource so the `StateMachine` can start it before it stops the states.
+pub(crate) struct ShutdownWatchdog {
+    timeout: Duration,
+    policy: Arc<ShutdownTimeoutPolicy>,
+    sender: Option<Sender<()>>,
+}
+
+impl ShutdownWatchdog {
+    /// Spawns a thread that applies the policy once the timeout elapses. Does nothing if the
+    /// watchdog is already running.
+    pub(crate) fn start(&mut self) {
+        if self.sender.is_some() {
+            return;
+        }
+
+        let timeout = self.timeout;
+        let policy = self.policy.clone();
+        let (sender, receiver) = crossbeam_channel::bounded(1);
+        thread::spawn(move || {
+            if let Err(RecvTimeoutError::Timeout) = receiver.recv_timeout(timeout) {
+                match *policy {
+                    ShutdownTimeoutPolicy::Exit => {
+                        error!(
+                            "Shutdown did not complete within {:?}, forcing the process to exit",
+                            timeout
+                        );
+                        process::exit(1);
+                    }
+                    ShutdownTimeoutPolicy::Log => {
+                        error!("Shutdown did not complete within {:?}", timeout)
+                    }
+                    ShutdownTimeoutPolicy::Custom(ref handler) => handler(timeout),
+                }
+            }
+        });
+        self.sender = Some(sender);
+    }
+
+    /// Stops the watchdog thread without applying the policy.
+    fn finish(&mut self) {
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.send(());
+        }
     }
 }
 
@@ -404,6 +587,13 @@ pub struct ApplicationBuilder<S, T, E, R> {
     /// Used by bundles to access the world directly
     pub world: World,
     ignore_window_close: bool,
+    headless: bool,
+    time_source: TimeSource,
+    error_policy: ErrorPolicy,
+    shutdown_hooks: Vec<ShutdownHook>,
+    shutdown_timeout: Duration,
+    shutdown_timeout_policy: ShutdownTimeoutPolicy,
+    catch_termination_signals: bool,
     phantom: PhantomData<(T, E, R)>,
 }
 
@@ -504,16 +694,25 @@ where
             .build()
             .map(Arc::new)
             .map_err(|err| Error::Core(err.description().to_string().into()))?;
+        let callback_queue = CallbackQueue::default();
         world.add_resource(Loader::new(path.as_ref().to_owned(), pool.clone()));
+        let mut formats = AssetFormatRegistry::new();
+        audio::register_formats(&mut formats);
+        renderer::register_formats(&mut formats);
+        ui::register_formats(&mut formats);
+        world.add_resource(formats);
+        world.add_resource(TaskSpawner::new(pool.clone(), &callback_queue));
         world.add_resource(pool);
         world.add_resource(EventChannel::<Event>::with_capacity(2000));
         world.add_resource(EventChannel::<UiEvent>::with_capacity(40));
         world.add_resource(EventChannel::<TransEvent<T, StateEvent>>::with_capacity(2));
+        world.add_resource(EventChannel::<StateTransitionEvent>::with_capacity(8));
         world.add_resource(Errors::default());
         world.add_resource(FrameLimiter::default());
         world.add_resource(Stopwatch::default());
         world.add_resource(Time::default());
-        world.add_resource(CallbackQueue::default());
+        world.add_resource(callback_queue);
+        world.add_resource(FrameMetrics::default());
 
         world.register::<Named>();
 
@@ -521,6 +720,13 @@ where
             initial_state,
             world,
             ignore_window_close: false,
+            headless: false,
+            time_source: TimeSource::default(),
+            error_policy: ErrorPolicy::default(),
+            shutdown_hooks: Vec::new(),
+            shutdown_timeout: Duration::from_secs(10),
+            shutdown_timeout_policy: ShutdownTimeoutPolicy::default(),
+            catch_termination_signals: true,
             phantom: PhantomData,
         })
     }
@@ -741,6 +947,21 @@ where
         self
     }
 
+    /// Sets the maximum number of fixed updates that may run in a single frame to catch up with
+    /// elapsed time, defaults to 5.
+    ///
+    /// # Parameters
+    ///
+    /// `max`: The maximum number of fixed updates per frame. Must be greater than 0.
+    ///
+    /// # Returns
+    ///
+    /// This function returns the ApplicationBuilder after modifying it.
+    pub fn with_max_fixed_updates(self, max: u32) -> Self {
+        self.world.write_resource::<Time>().set_max_fixed_updates(max);
+        self
+    }
+
     /// Tells the resulting application window to ignore close events if ignore is true.
     /// This will make your game window unresponsive to operating system close commands.
     /// Use with caution.
@@ -757,6 +978,173 @@ where
         self
     }
 
+    /// Runs the application without a window.
+    ///
+    /// The state machine and dispatcher run as usual, but no window events are expected and
+    /// window close requests are never checked. Do not add a `RenderBundle` or any other
+    /// bundle that opens a window to a headless application.
+    ///
+    /// Headless applications are not frame limited, they run as fast as possible.
+    ///
+    /// # Parameters
+    ///
+    /// `headless`: Whether or not the application runs without a window.  False by default.
+    ///
+    /// # Returns
+    ///
+    /// This function returns the ApplicationBuilder after modifying it.
+    ///
+    /// # Examples
+    ///
+    /// ~~~no_run
+    /// use std::time::Duration;
+    ///
+    /// use amethyst::prelude::*;
+    /// use amethyst::core::timing::TimeSource;
+    ///
+    /// struct ServerState;
+    /// impl SimpleState for ServerState {}
+    ///
+    /// let mut game = Application::build("assets/", ServerState)
+    ///     .expect("Failed to initialize")
+    ///     .headless(true)
+    ///     .with_time_source(TimeSource::Manual(Duration::from_millis(16)))
+    ///     .build(GameDataBuilder::default())
+    ///     .expect("Failed to create Application");
+    ///
+    /// game.run();
+    /// ~~~
+    pub fn headless(mut self, headless: bool) -> Self {
+        if headless {
+            info!("Running in headless mode");
+        }
+        self.headless = headless;
+        self
+    }
+
+    /// Sets how the time elapsed during each frame is measured.
+    ///
+    /// # Parameters
+    ///
+    /// `time_source`: The time source to use. `TimeSource::WallClock` by default.
+    ///
+    /// # Returns
+    ///
+    /// This function returns the ApplicationBuilder after modifying it.
+    pub fn with_time_source(mut self, time_source: TimeSource) -> Self {
+        self.time_source = time_source;
+        self
+    }
+
+    /// Sets how errors reported by systems through the `Errors` resource are handled.
+    ///
+    /// # Parameters
+    ///
+    /// `policy`: The error policy to use. `ErrorPolicy::Exit` by default.
+    ///
+    /// # Returns
+    ///
+    /// This function returns the ApplicationBuilder after modifying it.
+    ///
+    /// # Examples
+    ///
+    /// ~~~no_run
+    /// use amethyst::prelude::*;
+    /// use amethyst::ErrorPolicy;
+    ///
+    /// struct NullState;
+    /// impl EmptyState for NullState {}
+    ///
+    /// let mut game = Application::build("assets/", NullState)
+    ///     .expect("Failed to initialize")
+    ///     .with_error_policy(ErrorPolicy::Custom(Box::new(|_world, error| {
+    ///         eprintln!("Recovering from: {}", error);
+    ///     })))
+    ///     .build(())
+    ///     .expect("Failed to create Application");
+    /// ~~~
+    pub fn with_error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
+    }
+
+    /// Registers a function that is run with the `World` when the application shuts down, after
+    /// all states have been stopped. Hooks run in the order they were added.
+    ///
+    /// # Parameters
+    ///
+    /// `hook`: The function to run on shutdown, e.g. to flush save files or close connections.
+    ///
+    /// # Returns
+    ///
+    /// This function returns the ApplicationBuilder after modifying it.
+    ///
+    /// # Examples
+    ///
+    /// ~~~no_run
+    /// use amethyst::prelude::*;
+    ///
+    /// struct NullState;
+    /// impl EmptyState for NullState {}
+    ///
+    /// let mut game = Application::build("assets/", NullState)
+    ///     .expect("Failed to initialize")
+    ///     .with_shutdown_hook(|_world| println!("Saving the game..."))
+    ///     .build(())
+    ///     .expect("Failed to create Application");
+    /// ~~~
+    pub fn with_shutdown_hook<F>(mut self, hook: F) -> Self
+    where
+        F: FnMut(&mut World) + 'static,
+    {
+        self.shutdown_hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Sets how long stopping the states and running the shutdown hooks may take before the
+    /// `ShutdownTimeoutPolicy` is applied, defaults to ten seconds.
+    ///
+    /// # Parameters
+    ///
+    /// `timeout`: The maximum duration of the shutdown.
+    ///
+    /// # Returns
+    ///
+    /// This function returns the ApplicationBuilder after modifying it.
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Sets what happens when the shutdown takes longer than the shutdown timeout.
+    ///
+    /// # Parameters
+    ///
+    /// `policy`: The shutdown timeout policy to use. `ShutdownTimeoutPolicy::Exit` by default.
+    ///
+    /// # Returns
+    ///
+    /// This function returns the ApplicationBuilder after modifying it.
+    pub fn with_shutdown_timeout_policy(mut self, policy: ShutdownTimeoutPolicy) -> Self {
+        self.shutdown_timeout_policy = policy;
+        self
+    }
+
+    /// Tells the application to catch `SIGINT` and `SIGTERM` and shut down cleanly when they are
+    /// received, instead of being killed. Only supported on Unix platforms.
+    ///
+    /// # Parameters
+    ///
+    /// `catch`: Whether or not termination signals are caught.  True by default.
+    ///
+    /// # Returns
+    ///
+    /// This function returns the ApplicationBuilder after modifying it.
+    pub fn catch_termination_signals(mut self, catch: bool) -> Self {
+        self.catch_termination_signals = catch;
+        self
+    }
+
     /// Build an `Application` object using the `ApplicationBuilder` as configured.
     ///
     /// # Returns
@@ -803,15 +1191,206 @@ where
             .world
             .exec(|mut ev: Write<'_, EventChannel<TransEvent<T, E>>>| ev.register_reader());
 
+        let terminate = Arc::new(AtomicBool::new(false));
+        if self.catch_termination_signals {
+            register_termination_signals(&terminate);
+        }
+        self.world.add_resource(ShutdownWatchdog {
+            timeout: self.shutdown_timeout,
+            policy: Arc::new(self.shutdown_timeout_policy),
+            sender: None,
+        });
+
         Ok(CoreApplication {
             world: self.world,
             states: StateMachine::new(self.initial_state),
             reader,
             events: Vec::new(),
             ignore_window_close: self.ignore_window_close,
+            headless: self.headless,
+            time_source: self.time_source,
+            error_policy: self.error_policy,
+            shutdown_hooks: self.shutdown_hooks,
+            terminate,
             data,
             event_reader_id,
             trans_reader_id,
         })
     }
 }
+
+/// Sets the given flag when the process receives `SIGINT` or `SIGTERM`.
+#[cfg(unix)]
+fn register_termination_signals(flag: &Arc<AtomicBool>) {
+    for &signal in &[signal_hook::SIGINT, signal_hook::SIGTERM] {
+        if let Err(err) = signal_hook::flag::register(signal, flag.clone()) {
+            warn!("Failed to register handler for signal {}: {}", signal, err);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn register_termination_signals(_flag: &Arc<AtomicBool>) {
+    debug!("Catching termination signals is not supported on this platform");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::{Application, ShutdownTimeoutPolicy};
+    use crate::{
+        core::{
+            frame_limiter::FrameRateLimitStrategy,
+            timing::{Time, TimeSource},
+        },
+        prelude::{GameData, GameDataBuilder, SimpleState, SimpleTrans, StateData, Trans},
+    };
+
+    /// Records the frame number and delta time of every update and quits after `frames`.
+    struct CountFrames {
+        frames: u64,
+        times: Arc<Mutex<Vec<(u64, Duration)>>>,
+        events: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    impl CountFrames {
+        fn new(frames: u64) -> Self {
+            CountFrames {
+                frames,
+                times: Default::default(),
+                events: Default::default(),
+            }
+        }
+    }
+
+    impl SimpleState for CountFrames {
+        fn on_stop(&mut self, _: StateData<'_, GameData<'_, '_>>) {
+            self.events.lock().unwrap().push("on_stop");
+        }
+
+        fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+            let time = data.world.read_resource::<Time>();
+            let mut times = self.times.lock().unwrap();
+            times.push((time.frame_number(), time.delta_real_time()));
+            if times.len() as u64 == self.frames {
+                Trans::Quit
+            } else {
+                Trans::None
+            }
+        }
+    }
+
+    #[test]
+    fn headless_frames_use_manual_time() {
+        let state = CountFrames::new(5);
+        let times = state.times.clone();
+        let mut game = Application::build("assets/", state)
+            .unwrap()
+            .headless(true)
+            .catch_termination_signals(false)
+            .with_time_source(TimeSource::Manual(Duration::from_millis(16)))
+            .with_frame_limit(FrameRateLimitStrategy::Sleep, 1)
+            .build(GameDataBuilder::default())
+            .unwrap();
+
+        let start = Instant::now();
+        game.run();
+
+        // At one frame per second the frame limiter would take several seconds.
+        assert!(start.elapsed() < Duration::from_secs(1));
+        let times = times.lock().unwrap();
+        assert_eq!(5, times.len());
+        for (frame, &(number, delta)) in times.iter().enumerate().skip(1) {
+            assert_eq!(frame as u64, number);
+            assert_eq!(Duration::from_millis(16), delta);
+        }
+    }
+
+    #[test]
+    fn shutdown_hooks_run_in_order() {
+        let state = CountFrames::new(1);
+        let events = state.events.clone();
+        let (first, second) = (events.clone(), events.clone());
+        let mut game = Application::build("assets/", state)
+            .unwrap()
+            .headless(true)
+            .catch_termination_signals(false)
+            .with_shutdown_hook(move |_| first.lock().unwrap().push("first"))
+            .with_shutdown_hook(move |_| second.lock().unwrap().push("second"))
+            .build(GameDataBuilder::default())
+            .unwrap();
+
+        game.run();
+
+        assert_eq!(vec!["on_stop", "first", "second"], *events.lock().unwrap());
+    }
+
+    #[test]
+    fn shutdown_timeout_policy() {
+        let timed_out = Arc::new(AtomicBool::new(false));
+        let flag = timed_out.clone();
+        let finished = Arc::new(AtomicBool::new(false));
+        let hook_finished = finished.clone();
+        let mut game = Application::build("assets/", CountFrames::new(1))
+            .unwrap()
+            .headless(true)
+            .catch_termination_signals(false)
+            .with_shutdown_timeout(Duration::from_millis(10))
+            .with_shutdown_timeout_policy(ShutdownTimeoutPolicy::Custom(Box::new(move |timeout| {
+                assert_eq!(Duration::from_millis(10), timeout);
+                flag.store(true, Ordering::SeqCst);
+            })))
+            .with_shutdown_hook(move |_| {
+                thread::sleep(Duration::from_millis(200));
+                hook_finished.store(true, Ordering::SeqCst);
+            })
+            .build(GameDataBuilder::default())
+            .unwrap();
+
+        game.run();
+
+        assert!(timed_out.load(Ordering::SeqCst));
+        assert!(finished.load(Ordering::SeqCst));
+    }
+
+    /// Quits immediately and takes a while to stop.
+    struct SlowStop;
+
+    impl SimpleState for SlowStop {
+        fn on_stop(&mut self, _: StateData<'_, GameData<'_, '_>>) {
+            thread::sleep(Duration::from_millis(200));
+        }
+
+        fn update(&mut self, _: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+            Trans::Quit
+        }
+    }
+
+    #[test]
+    fn shutdown_timeout_covers_stopping_states() {
+        let timed_out = Arc::new(AtomicBool::new(false));
+        let flag = timed_out.clone();
+        let mut game = Application::build("assets/", SlowStop)
+            .unwrap()
+            .headless(true)
+            .catch_termination_signals(false)
+            .with_shutdown_timeout(Duration::from_millis(10))
+            .with_shutdown_timeout_policy(ShutdownTimeoutPolicy::Custom(Box::new(move |_| {
+                flag.store(true, Ordering::SeqCst);
+            })))
+            .build(GameDataBuilder::default())
+            .unwrap();
+
+        game.run();
+
+        assert!(timed_out.load(Ordering::SeqCst));
+    }
+}
@@ -10,6 +10,7 @@ pub type Callback = Box<dyn Fn(&mut World) + Send>;
 /// Using the `Sender` you can get using the `send_handle` method, you
 /// can add functions modifying `World` from an asynchronous context.
 /// Those callbacks will be ran sequentially without preserving ordering.
+/// To run blocking work on the thread pool and get its result back, see `TaskSpawner`.
 /// # Example
 /// ```rust,ignore
 /// // First, get a `Sender` handle.
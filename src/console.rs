@@ -0,0 +1,776 @@
+//! A drop-down developer console.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Display,
+    result::Result as StdResult,
+    str::FromStr,
+    sync::Arc,
+};
+
+use winit::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+use crate::{
+    assets::{AssetStorage, Loader},
+    callback_queue::CallbackQueue,
+    core::{
+        shrev::{EventChannel, ReaderId},
+        specs::prelude::{
+            DispatcherBuilder, Entities, Entity, Read, ReadExpect, Resources, System, World,
+            Write, WriteStorage,
+        },
+        Result, SystemBundle,
+    },
+    renderer::Hidden,
+    ui::{
+        get_default_font, Anchor, FontAsset, FontHandle, LineMode, Stretch, TextEditing,
+        UiFocused, UiText, UiTransform,
+    },
+};
+
+/// The number of lines kept in the console's scrollback.
+const SCROLLBACK_LINES: usize = 200;
+/// The number of lines displayed in the console.
+const VISIBLE_LINES: usize = 16;
+const FONT_SIZE: f32 = 16.0;
+const TEXT_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+
+/// A console command, run on the main thread with access to the `World`.
+pub type ConsoleCommand =
+    Arc<dyn Fn(&mut World, &ConsoleArgs) -> StdResult<(), String> + Send + Sync>;
+
+/// The arguments a console command was called with.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConsoleArgs {
+    args: Vec<String>,
+}
+
+impl ConsoleArgs {
+    /// Splits a command line's arguments at whitespace.
+    pub fn parse(line: &str) -> Self {
+        ConsoleArgs {
+            args: line.split_whitespace().map(str::to_owned).collect(),
+        }
+    }
+
+    /// Gets the number of arguments.
+    pub fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    /// Checks whether no arguments were given.
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+
+    /// Gets an argument as it was typed.
+    pub fn raw(&self, index: usize) -> Option<&str> {
+        self.args.get(index).map(String::as_str)
+    }
+
+    /// Parses an argument, failing with a message that can be shown in the console if it is
+    /// missing or invalid.
+    pub fn get<T>(&self, index: usize) -> StdResult<T, String>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let raw = self
+            .raw(index)
+            .ok_or_else(|| format!("Missing argument {}", index + 1))?;
+        raw.parse()
+            .map_err(|err| format!("Invalid argument '{}': {}", raw, err))
+    }
+
+    /// Parses an argument, falling back to `default` if it is missing.
+    pub fn get_or<T>(&self, index: usize, default: T) -> StdResult<T, String>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.raw(index) {
+            Some(_) => self.get(index),
+            None => Ok(default),
+        }
+    }
+}
+
+struct CommandEntry {
+    description: String,
+    command: ConsoleCommand,
+}
+
+/// The commands available in the developer console.
+///
+/// Systems and states can register their own commands, the `help` and `clear` commands are
+/// registered by default.
+///
+/// # Example
+/// ```rust,ignore
+/// world
+///     .write_resource::<ConsoleCommands>()
+///     .register("time_scale", "Sets the time scale", |world, args| {
+///         let scale = args.get::<f32>(0)?;
+///         world.write_resource::<Time>().set_time_scale(scale);
+///         Ok(())
+///     });
+/// ```
+pub struct ConsoleCommands {
+    commands: BTreeMap<String, CommandEntry>,
+}
+
+impl Default for ConsoleCommands {
+    fn default() -> Self {
+        let mut commands = ConsoleCommands {
+            commands: BTreeMap::new(),
+        };
+        commands.register("help", "Lists the available commands", |world, _| {
+            let lines = world
+                .read_resource::<ConsoleCommands>()
+                .commands
+                .iter()
+                .map(|(name, entry)| format!("{} - {}", name, entry.description))
+                .collect::<Vec<_>>();
+            let mut console = world.write_resource::<Console>();
+            for line in lines {
+                console.print(line);
+            }
+            Ok(())
+        });
+        commands.register("clear", "Clears the console", |world, _| {
+            world.write_resource::<Console>().clear();
+            Ok(())
+        });
+        commands
+    }
+}
+
+impl ConsoleCommands {
+    /// Registers a command, replacing any command with the same name.
+    ///
+    /// If the command returns an error, it is printed to the console.
+    pub fn register<F>(&mut self, name: &str, description: &str, command: F)
+    where
+        F: Fn(&mut World, &ConsoleArgs) -> StdResult<(), String> + Send + Sync + 'static,
+    {
+        self.commands.insert(
+            name.to_owned(),
+            CommandEntry {
+                description: description.to_owned(),
+                command: Arc::new(command),
+            },
+        );
+    }
+
+    /// Removes a command.
+    pub fn unregister(&mut self, name: &str) {
+        self.commands.remove(name);
+    }
+
+    /// Checks whether a command is registered.
+    pub fn contains(&self, name: &str) -> bool {
+        self.commands.contains_key(name)
+    }
+
+    /// Gets the description of a command.
+    pub fn description(&self, name: &str) -> Option<&str> {
+        self.commands
+            .get(name)
+            .map(|entry| entry.description.as_str())
+    }
+
+    /// Iterates over the names of all commands in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.commands.keys().map(String::as_str)
+    }
+
+    /// Gets the names of all commands starting with `prefix`.
+    pub fn complete(&self, prefix: &str) -> Vec<&str> {
+        self.names()
+            .filter(|name| name.starts_with(prefix))
+            .collect()
+    }
+
+    /// Gets a command.
+    pub fn get(&self, name: &str) -> Option<ConsoleCommand> {
+        self.commands.get(name).map(|entry| entry.command.clone())
+    }
+}
+
+/// The state of the developer console: whether it is open, its scrollback and the command
+/// history.
+///
+/// Commands can print their output with `Console::print`.
+#[derive(Debug, Default)]
+pub struct Console {
+    open: bool,
+    lines: VecDeque<String>,
+    history: Vec<String>,
+    dirty: bool,
+}
+
+impl Console {
+    /// Checks whether the console is shown.
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Shows or hides the console.
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+    }
+
+    /// Toggles the console.
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Adds a line to the scrollback, discarding the oldest line if it is full.
+    pub fn print<S: Into<String>>(&mut self, line: S) {
+        if self.lines.len() == SCROLLBACK_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line.into());
+        self.dirty = true;
+    }
+
+    /// Clears the scrollback.
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.dirty = true;
+    }
+
+    /// Iterates over the lines in the scrollback, oldest first.
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().map(String::as_str)
+    }
+
+    /// Gets the previously entered command lines, oldest first.
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+}
+
+struct ConsoleUi {
+    log: Entity,
+    input: Entity,
+}
+
+/// Shows the developer console, reads the command line and runs the entered commands through
+/// the `CallbackQueue`.
+///
+/// The console is toggled with the grave key by default. `Tab` completes command names and the
+/// arrow keys browse the history.
+pub struct ConsoleSystem {
+    toggle_key: VirtualKeyCode,
+    reader: Option<ReaderId<Event>>,
+    ui: Option<ConsoleUi>,
+    font: Option<FontHandle>,
+    history_index: Option<usize>,
+    was_open: bool,
+    clear_input: bool,
+}
+
+impl Default for ConsoleSystem {
+    fn default() -> Self {
+        ConsoleSystem::new(VirtualKeyCode::Grave)
+    }
+}
+
+impl ConsoleSystem {
+    /// Creates a new `ConsoleSystem` toggling the console with `toggle_key`.
+    pub fn new(toggle_key: VirtualKeyCode) -> Self {
+        ConsoleSystem {
+            toggle_key,
+            reader: None,
+            ui: None,
+            font: None,
+            history_index: None,
+            was_open: false,
+            clear_input: false,
+        }
+    }
+
+    /// Sets the font used by the console, instead of the default system font.
+    pub fn with_font(mut self, font: FontHandle) -> Self {
+        self.font = Some(font);
+        self
+    }
+}
+
+impl<'a> System<'a> for ConsoleSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, UiTransform>,
+        WriteStorage<'a, UiText>,
+        WriteStorage<'a, TextEditing>,
+        WriteStorage<'a, Hidden>,
+        Write<'a, UiFocused>,
+        Write<'a, Console>,
+        Read<'a, ConsoleCommands>,
+        Read<'a, EventChannel<Event>>,
+        ReadExpect<'a, CallbackQueue>,
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<FontAsset>>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut transforms,
+            mut texts,
+            mut editing,
+            mut hidden,
+            mut focused,
+            mut console,
+            commands,
+            events,
+            callback_queue,
+            loader,
+            fonts,
+        ): Self::SystemData,
+    ) {
+        if self.ui.is_none() {
+            let font = match self.font {
+                Some(ref font) => font.clone(),
+                None => get_default_font(&loader, &fonts),
+            };
+            self.ui = Some(create_ui(
+                &entities,
+                &mut transforms,
+                &mut texts,
+                &mut editing,
+                &mut hidden,
+                font,
+            ));
+            console.dirty = true;
+        }
+        let (log, input) = match self.ui {
+            Some(ref ui) => (ui.log, ui.input),
+            None => unreachable!(),
+        };
+
+        // The key opening the console is also typed into the input, so it is cleared one frame
+        // later, after the `UiKeyboardSystem` has seen the character.
+        if self.clear_input {
+            set_input(&mut texts, &mut editing, input, String::new());
+            self.clear_input = false;
+        }
+
+        let reader = self
+            .reader
+            .as_mut()
+            .expect("`ConsoleSystem::setup` was not called before `ConsoleSystem::run`");
+        for event in events.read(reader) {
+            let key = match *event {
+                Event::WindowEvent {
+                    event:
+                        WindowEvent::KeyboardInput {
+                            input:
+                                KeyboardInput {
+                                    state: ElementState::Pressed,
+                                    virtual_keycode: Some(key),
+                                    ..
+                                },
+                            ..
+                        },
+                    ..
+                } => key,
+                _ => continue,
+            };
+
+            if key == self.toggle_key {
+                console.toggle();
+                continue;
+            }
+            if !console.is_open() {
+                continue;
+            }
+
+            match key {
+                VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+                    let line = texts
+                        .get(input)
+                        .map(|text| text.text.trim().to_owned())
+                        .unwrap_or_default();
+                    set_input(&mut texts, &mut editing, input, String::new());
+                    self.history_index = None;
+                    if line.is_empty() {
+                        continue;
+                    }
+
+                    submit(&line, &commands, &callback_queue, &mut console);
+                }
+                VirtualKeyCode::Up | VirtualKeyCode::Down => {
+                    let history = &console.history;
+                    if history.is_empty() {
+                        continue;
+                    }
+                    let index = match (key, self.history_index) {
+                        (VirtualKeyCode::Up, None) => Some(history.len() - 1),
+                        (VirtualKeyCode::Up, Some(index)) => Some(index.saturating_sub(1)),
+                        (_, Some(index)) if index + 1 < history.len() => Some(index + 1),
+                        _ => None,
+                    };
+                    let line = index.map(|index| history[index].clone()).unwrap_or_default();
+                    set_input(&mut texts, &mut editing, input, line);
+                    self.history_index = index;
+                }
+                VirtualKeyCode::Tab => {
+                    // The `UiKeyboardSystem` moves the focus on tab, keep it on the console.
+                    focused.entity = Some(input);
+
+                    let prefix = match texts.get(input) {
+                        Some(text) if !text.text.contains(' ') => text.text.clone(),
+                        _ => continue,
+                    };
+                    let matches = commands.complete(&prefix);
+                    match matches.len() {
+                        0 => {}
+                        1 => set_input(&mut texts, &mut editing, input, format!("{} ", matches[0])),
+                        _ => {
+                            console.print(matches.join("  "));
+                            let common = common_prefix(&matches).to_owned();
+                            set_input(&mut texts, &mut editing, input, common);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if console.is_open() != self.was_open {
+            self.was_open = console.is_open();
+            if self.was_open {
+                hidden.remove(log);
+                hidden.remove(input);
+                focused.entity = Some(input);
+                self.clear_input = true;
+                self.history_index = None;
+            } else {
+                let _ = hidden.insert(log, Hidden);
+                let _ = hidden.insert(input, Hidden);
+                if focused.entity == Some(input) {
+                    focused.entity = None;
+                }
+            }
+        }
+
+        if console.dirty {
+            console.dirty = false;
+            if let Some(text) = texts.get_mut(log) {
+                let skip = console.lines.len().saturating_sub(VISIBLE_LINES);
+                text.text = console
+                    .lines
+                    .iter()
+                    .skip(skip)
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut Resources) {
+        use crate::core::specs::prelude::SystemData;
+        Self::SystemData::setup(res);
+        self.reader = Some(res.fetch_mut::<EventChannel<Event>>().register_reader());
+    }
+}
+
+/// Adds the `ConsoleSystem`, which shows the developer console. Needs to be added after the
+/// `UiBundle`.
+#[derive(Default)]
+pub struct ConsoleBundle {
+    system: ConsoleSystem,
+}
+
+impl ConsoleBundle {
+    /// Creates a new `ConsoleBundle` toggling the console with `toggle_key`.
+    pub fn new(toggle_key: VirtualKeyCode) -> Self {
+        ConsoleBundle {
+            system: ConsoleSystem::new(toggle_key),
+        }
+    }
+
+    /// Sets the font used by the console, instead of the default system font.
+    pub fn with_font(mut self, font: FontHandle) -> Self {
+        self.system = self.system.with_font(font);
+        self
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for ConsoleBundle {
+    fn build(self, builder: &mut DispatcherBuilder<'a, 'b>) -> Result<()> {
+        builder.add(self.system, "console_system", &["ui_keyboard_system"]);
+        Ok(())
+    }
+}
+
+fn create_ui(
+    entities: &Entities<'_>,
+    transforms: &mut WriteStorage<'_, UiTransform>,
+    texts: &mut WriteStorage<'_, UiText>,
+    editing: &mut WriteStorage<'_, TextEditing>,
+    hidden: &mut WriteStorage<'_, Hidden>,
+    font: FontHandle,
+) -> ConsoleUi {
+    let log_height = FONT_SIZE * VISIBLE_LINES as f32;
+    let log = entities.create();
+    let mut transform = UiTransform::new(
+        "console_log".to_owned(),
+        Anchor::TopMiddle,
+        0.0,
+        -log_height / 2.0,
+        1000.0,
+        0.0,
+        log_height,
+        0,
+    )
+    .with_stretch(Stretch::X { x_margin: 8.0 });
+    transform.opaque = false;
+    let mut text = UiText::new(font.clone(), String::new(), TEXT_COLOR, FONT_SIZE);
+    text.line_mode = LineMode::Wrap;
+    text.align = Anchor::BottomLeft;
+    let _ = transforms.insert(log, transform);
+    let _ = texts.insert(log, text);
+    let _ = hidden.insert(log, Hidden);
+
+    let input = entities.create();
+    let transform = UiTransform::new(
+        "console_input".to_owned(),
+        Anchor::TopMiddle,
+        0.0,
+        -log_height - FONT_SIZE,
+        1000.0,
+        0.0,
+        FONT_SIZE * 1.5,
+        i32::max_value(),
+    )
+    .with_stretch(Stretch::X { x_margin: 8.0 });
+    let mut text = UiText::new(font, String::new(), TEXT_COLOR, FONT_SIZE);
+    text.align = Anchor::MiddleLeft;
+    let _ = transforms.insert(input, transform);
+    let _ = texts.insert(input, text);
+    let _ = editing.insert(
+        input,
+        TextEditing::new(256, [0.0, 0.0, 0.0, 1.0], TEXT_COLOR, false),
+    );
+    let _ = hidden.insert(input, Hidden);
+
+    ConsoleUi { log, input }
+}
+
+fn set_input(
+    texts: &mut WriteStorage<'_, UiText>,
+    editing: &mut WriteStorage<'_, TextEditing>,
+    input: Entity,
+    line: String,
+) {
+    let length = line.chars().count() as isize;
+    if let Some(text) = texts.get_mut(input) {
+        text.text = line;
+    }
+    if let Some(editing) = editing.get_mut(input) {
+        editing.cursor_position = length;
+        editing.highlight_vector = 0;
+    }
+}
+
+/// Echoes an entered command line, adds it to the history and queues its command.
+fn submit(line: &str, commands: &ConsoleCommands, queue: &CallbackQueue, console: &mut Console) {
+    console.print(format!("> {}", line));
+    console.history.push(line.to_owned());
+
+    let (name, args) = match line.find(char::is_whitespace) {
+        Some(split) => (&line[..split], &line[split..]),
+        None => (line, ""),
+    };
+    let command = match commands.get(name) {
+        Some(command) => command,
+        None => {
+            console.print(format!("Unknown command '{}', try 'help'", name));
+            return;
+        }
+    };
+
+    let name = name.to_owned();
+    let args = ConsoleArgs::parse(args);
+    let sent = queue.send_handle().send(Box::new(move |world: &mut World| {
+        if let Err(err) = command(world, &args) {
+            world
+                .write_resource::<Console>()
+                .print(format!("{}: {}", name, err));
+        }
+    }));
+    if sent.is_err() {
+        error!("Failed to run console command, the `CallbackQueue` was dropped");
+    }
+}
+
+fn common_prefix<'a>(names: &[&'a str]) -> &'a str {
+    let first = names[0];
+    let mut length = first.len();
+    for name in &names[1..] {
+        length = first
+            .char_indices()
+            .zip(name.chars())
+            .take_while(|&((_, a), b)| a == b)
+            .map(|((index, a), _)| index + a.len_utf8())
+            .last()
+            .unwrap_or(0)
+            .min(length);
+    }
+    &first[..length]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{callback_queue::CallbackQueue, core::specs::prelude::World};
+
+    use super::{common_prefix, submit, Console, ConsoleArgs, ConsoleCommands};
+
+    #[derive(Default)]
+    struct Spawned(u32);
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.add_resource(Console::default());
+        world.add_resource(Spawned::default());
+        let mut commands = ConsoleCommands::default();
+        commands.register("spawn", "Spawns cubes", |world, args| {
+            let count = args.get::<u32>(0)?;
+            world.write_resource::<Spawned>().0 += count;
+            Ok(())
+        });
+        world.add_resource(commands);
+        world
+    }
+
+    fn submit_line(world: &mut World, queue: &CallbackQueue, line: &str) {
+        submit(
+            line,
+            &world.read_resource::<ConsoleCommands>(),
+            queue,
+            &mut world.write_resource::<Console>(),
+        );
+    }
+
+    /// Runs the queued callbacks, like the `CoreApplication` does every frame.
+    fn run_callbacks(world: &mut World, queue: &CallbackQueue) {
+        let callbacks = queue.receiver.try_iter().collect::<Vec<_>>();
+        for callback in callbacks {
+            callback(world);
+        }
+    }
+
+    fn lines(world: &World) -> Vec<String> {
+        world
+            .read_resource::<Console>()
+            .lines()
+            .map(str::to_owned)
+            .collect()
+    }
+
+    #[test]
+    fn parse_args() {
+        let args = ConsoleArgs::parse("  cube  0.5 ten ");
+        assert_eq!(3, args.len());
+        assert_eq!(Some("cube"), args.raw(0));
+        assert_eq!(Ok(0.5), args.get::<f32>(1));
+        assert!(args.get::<u32>(2).is_err());
+        assert!(args.get::<u32>(3).is_err());
+        assert_eq!(Ok(7), args.get_or::<u32>(3, 7));
+    }
+
+    #[test]
+    fn complete() {
+        let mut commands = ConsoleCommands::default();
+        commands.register("spawn", "", |_, _| Ok(()));
+        commands.register("speed", "", |_, _| Ok(()));
+
+        assert_eq!(vec!["speed", "spawn"], commands.complete("sp"));
+        assert_eq!(vec!["help"], commands.complete("h"));
+        assert_eq!("sp", common_prefix(&commands.complete("sp")));
+    }
+
+    #[test]
+    fn commands_run_through_the_callback_queue() {
+        let mut world = world();
+        let queue = CallbackQueue::new();
+
+        submit_line(&mut world, &queue, "spawn 3");
+        assert_eq!(0, world.read_resource::<Spawned>().0);
+
+        run_callbacks(&mut world, &queue);
+        assert_eq!(3, world.read_resource::<Spawned>().0);
+        assert_eq!(vec!["> spawn 3"], lines(&world));
+    }
+
+    #[test]
+    fn errors_are_printed() {
+        let mut world = world();
+        let queue = CallbackQueue::new();
+
+        submit_line(&mut world, &queue, "jump");
+        submit_line(&mut world, &queue, "spawn");
+        submit_line(&mut world, &queue, "spawn ten");
+        run_callbacks(&mut world, &queue);
+
+        assert_eq!(0, world.read_resource::<Spawned>().0);
+        assert_eq!(
+            vec![
+                "> jump",
+                "Unknown command 'jump', try 'help'",
+                "> spawn",
+                "> spawn ten",
+                "spawn: Missing argument 1",
+                "spawn: Invalid argument 'ten': invalid digit found in string",
+            ],
+            lines(&world)
+        );
+    }
+
+    #[test]
+    fn history_keeps_all_lines() {
+        let mut world = world();
+        let queue = CallbackQueue::new();
+
+        submit_line(&mut world, &queue, "spawn 1");
+        submit_line(&mut world, &queue, "jump");
+        submit_line(&mut world, &queue, "spawn 2");
+        run_callbacks(&mut world, &queue);
+
+        assert_eq!(
+            &["spawn 1", "jump", "spawn 2"],
+            world.read_resource::<Console>().history()
+        );
+    }
+
+    #[test]
+    fn help_and_clear() {
+        let mut world = world();
+        let queue = CallbackQueue::new();
+
+        submit_line(&mut world, &queue, "help");
+        run_callbacks(&mut world, &queue);
+        assert_eq!(
+            vec![
+                "> help",
+                "clear - Clears the console",
+                "help - Lists the available commands",
+                "spawn - Spawns cubes",
+            ],
+            lines(&world)
+        );
+
+        submit_line(&mut world, &queue, "clear");
+        run_callbacks(&mut world, &queue);
+        assert!(lines(&world).is_empty());
+        assert_eq!(
+            &["help", "clear"],
+            world.read_resource::<Console>().history()
+        );
+    }
+}
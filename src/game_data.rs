@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::{collections::HashMap, path::Path, time::Instant};
 
 use crate::{
     core::{
@@ -6,6 +6,7 @@ use crate::{
         ArcThreadPool, SystemBundle,
     },
     error::{Error, Result},
+    metrics::{FrameMetrics, SystemTimers, Timer},
     renderer::pipe::pass::Pass,
 };
 
@@ -19,25 +20,71 @@ pub trait DataInit<T> {
 ///
 /// The lifetimes are for the systems inside and can be `'static` unless a system has a borrowed
 /// field.
+///
+/// Besides the main dispatcher, which runs every frame, game data can contain named dispatcher
+/// groups (for example "gameplay", "menu" or "paused") which only run when a state asks for them.
 pub struct GameData<'a, 'b> {
     dispatcher: Dispatcher<'a, 'b>,
+    groups: HashMap<String, Dispatcher<'a, 'b>>,
+    dispatcher_timer: Option<Timer>,
+    group_timers: HashMap<String, Timer>,
 }
 
 impl<'a, 'b> GameData<'a, 'b> {
     /// Create new game data
     pub fn new(dispatcher: Dispatcher<'a, 'b>) -> Self {
-        GameData { dispatcher }
+        GameData::with_groups(dispatcher, HashMap::new())
+    }
+
+    /// Create new game data with named dispatcher groups
+    pub fn with_groups(
+        dispatcher: Dispatcher<'a, 'b>,
+        groups: HashMap<String, Dispatcher<'a, 'b>>,
+    ) -> Self {
+        GameData {
+            dispatcher,
+            groups,
+            dispatcher_timer: None,
+            group_timers: HashMap::new(),
+        }
     }
 
     /// Update game data
     pub fn update(&mut self, world: &World) {
+        let start = Instant::now();
         self.dispatcher.dispatch(&world.res);
+        if let Some(ref timer) = self.dispatcher_timer {
+            timer.record(start);
+        }
+    }
+
+    /// Update game data, then run the given dispatcher groups in order.
+    ///
+    /// Groups that were never added to the `GameDataBuilder` are skipped.
+    pub fn update_groups(&mut self, world: &World, groups: &[&str]) {
+        self.update(world);
+        for group in groups {
+            if let Some(dispatcher) = self.groups.get_mut(*group) {
+                let start = Instant::now();
+                dispatcher.dispatch(&world.res);
+                if let Some(timer) = self.group_timers.get(*group) {
+                    timer.record(start);
+                }
+            }
+        }
+    }
+
+    /// Checks whether a dispatcher group with the given name exists.
+    pub fn has_group(&self, group: &str) -> bool {
+        self.groups.contains_key(group)
     }
 }
 
 /// Builder for default game data
 pub struct GameDataBuilder<'a, 'b> {
     disp_builder: DispatcherBuilder<'a, 'b>,
+    groups: HashMap<String, DispatcherBuilder<'a, 'b>>,
+    timers: SystemTimers,
 }
 
 impl<'a, 'b> Default for GameDataBuilder<'a, 'b> {
@@ -51,6 +98,8 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
     pub fn new() -> Self {
         GameDataBuilder {
             disp_builder: DispatcherBuilder::new(),
+            groups: HashMap::new(),
+            timers: SystemTimers::new(),
         }
     }
 
@@ -121,6 +170,11 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
     /// If a dependency is referenced (by name), but has not previously been added this
     /// function will panic.
     ///
+    /// # Metrics
+    ///
+    /// The run time of every named system is measured and can be read from the `FrameMetrics`
+    /// resource.
+    ///
     /// # Examples
     ///
     /// ~~~no_run
@@ -146,7 +200,12 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
     where
         for<'c> S: System<'c> + Send + 'a,
     {
-        self.disp_builder.add(system, name, dependencies);
+        if name.is_empty() {
+            self.disp_builder.add(system, name, dependencies);
+        } else {
+            let system = self.timers.timed(system, name.to_owned());
+            self.disp_builder.add(system, name, dependencies);
+        }
         self
     }
 
@@ -172,6 +231,11 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
     ///
     /// - `S`: A type that implements the `System` trait.
     ///
+    /// # Metrics
+    ///
+    /// The run time of the system is recorded in `FrameMetrics` as `"thread_local/<n>"`, where
+    /// `n` counts the thread-local systems in the order they were added, starting at 0.
+    ///
     /// # Examples
     ///
     /// ~~~no_run
@@ -192,6 +256,8 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
     where
         for<'c> S: System<'c> + 'b,
     {
+        let name = format!("thread_local/{}", self.timers.count("thread_local/"));
+        let system = self.timers.timed(system, name);
         self.disp_builder.add_thread_local(system);
         self
     }
@@ -215,6 +281,10 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
     /// could result in any number of errors.
     /// See each individual bundle for a description of the errors it could produce.
     ///
+    /// # Metrics
+    ///
+    /// The systems of a bundle are not measured individually, their run time is included in
+    /// the `"dispatch"` timing of the `FrameMetrics`.
     pub fn with_bundle<B>(mut self, bundle: B) -> Result<Self>
     where
         B: SystemBundle<'a, 'b>,
@@ -223,6 +293,122 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
         Ok(self)
     }
 
+    /// Adds a given system to a named dispatcher group.
+    ///
+    /// Systems in a group only run while the active state lists the group in
+    /// `SimpleState::dispatcher_groups`. The group is created if it does not exist yet.
+    ///
+    /// # Parameters
+    ///
+    /// - `group`: The name of the dispatcher group.
+    /// - `system`: The system that is to be added to the group.
+    /// - `name`: A unique string to identify the system by within the group.
+    /// - `dependencies`: A list of named systems of the same group that _must_ have completed
+    ///                 running before this system is permitted to run.
+    ///
+    /// The run time of the system is recorded in `FrameMetrics` as `"group/name"`.
+    ///
+    /// # Returns
+    ///
+    /// This function returns GameDataBuilder after it has modified it.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`with`](#method.with), within the group.
+    ///
+    /// # Examples
+    ///
+    /// ~~~no_run
+    /// use amethyst::prelude::*;
+    /// use amethyst::ecs::prelude::System;
+    ///
+    /// struct NopSystem;
+    /// impl<'a> System<'a> for NopSystem {
+    ///     type SystemData = ();
+    ///     fn run(&mut self, _: Self::SystemData) {}
+    /// }
+    ///
+    /// struct Gameplay;
+    /// impl SimpleState for Gameplay {
+    ///     fn dispatcher_groups(&self) -> &[&'static str] {
+    ///         &["gameplay"]
+    ///     }
+    /// }
+    ///
+    /// GameDataBuilder::default()
+    ///     // Runs every frame, no matter which state is active.
+    ///     .with(NopSystem, "input", &[])
+    ///     // Only runs while `Gameplay` is the active state.
+    ///     .with_group_system("gameplay", NopSystem, "physics", &[])
+    ///     .with_group_system("menu", NopSystem, "menu_navigation", &[]);
+    /// ~~~
+    pub fn with_group_system<S>(
+        mut self,
+        group: &str,
+        system: S,
+        name: &str,
+        dependencies: &[&str],
+    ) -> Self
+    where
+        for<'c> S: System<'c> + Send + 'a,
+    {
+        if name.is_empty() {
+            self.group_builder(group).add(system, name, dependencies);
+        } else {
+            let system = self.timers.timed(system, format!("{}/{}", group, name));
+            self.group_builder(group).add(system, name, dependencies);
+        }
+        self
+    }
+
+    /// Adds a given thread-local system to a named dispatcher group.
+    ///
+    /// See [`with_thread_local`](#method.with_thread_local) and
+    /// [`with_group_system`](#method.with_group_system). The run time of the system is recorded
+    /// in `FrameMetrics` as `"group/thread_local/<n>"`.
+    pub fn with_group_thread_local<S>(mut self, group: &str, system: S) -> Self
+    where
+        for<'c> S: System<'c> + 'b,
+    {
+        let prefix = format!("{}/thread_local/", group);
+        let name = format!("{}{}", prefix, self.timers.count(&prefix));
+        let system = self.timers.timed(system, name);
+        self.group_builder(group).add_thread_local(system);
+        self
+    }
+
+    /// Inserts a barrier into a named dispatcher group.
+    ///
+    /// See [`with_barrier`](#method.with_barrier).
+    pub fn with_group_barrier(mut self, group: &str) -> Self {
+        self.group_builder(group).add_barrier();
+        self
+    }
+
+    /// Adds a given ECS bundle to a named dispatcher group.
+    ///
+    /// The run time of the bundle's systems is included in the `"dispatch/group"` timing of the
+    /// `FrameMetrics`.
+    ///
+    /// # Errors
+    ///
+    /// See [`with_bundle`](#method.with_bundle).
+    pub fn with_group_bundle<B>(mut self, group: &str, bundle: B) -> Result<Self>
+    where
+        B: SystemBundle<'a, 'b>,
+    {
+        bundle
+            .build(self.group_builder(group))
+            .map_err(Error::Core)?;
+        Ok(self)
+    }
+
+    fn group_builder(&mut self, group: &str) -> &mut DispatcherBuilder<'a, 'b> {
+        self.groups
+            .entry(group.to_owned())
+            .or_insert_with(DispatcherBuilder::new)
+    }
+
     /// Create a basic renderer with a single given `Pass`, and optional support for the `DrawUi` pass.
     ///
     /// Will set the clear color to black.
@@ -268,14 +454,119 @@ impl<'a, 'b> DataInit<GameData<'a, 'b>> for GameDataBuilder<'a, 'b> {
         let pool = world.read_resource::<ArcThreadPool>().clone();
 
         #[cfg(not(no_threading))]
-        let mut dispatcher = self.disp_builder.with_pool(pool).build();
+        let mut dispatcher = self.disp_builder.with_pool(pool.clone()).build();
         #[cfg(no_threading)]
         let mut dispatcher = self.disp_builder.build();
         dispatcher.setup(&mut world.res);
-        GameData::new(dispatcher)
+
+        let mut timers = self.timers;
+        let dispatcher_timer = timers.timer("dispatch".to_owned());
+        let group_timers = self
+            .groups
+            .keys()
+            .map(|name| (name.clone(), timers.timer(format!("dispatch/{}", name))))
+            .collect();
+        let groups = self
+            .groups
+            .into_iter()
+            .map(|(name, builder)| {
+                #[cfg(not(no_threading))]
+                let mut dispatcher = builder.with_pool(pool.clone()).build();
+                #[cfg(no_threading)]
+                let mut dispatcher = builder.build();
+                dispatcher.setup(&mut world.res);
+                (name, dispatcher)
+            })
+            .collect();
+
+        if !world.res.has_value::<FrameMetrics>() {
+            world.add_resource(FrameMetrics::default());
+        }
+        world.write_resource::<FrameMetrics>().add_timers(timers);
+
+        GameData {
+            dispatcher,
+            groups,
+            dispatcher_timer: Some(dispatcher_timer),
+            group_timers,
+        }
     }
 }
 
 impl DataInit<()> for () {
     fn build(self, _: &mut World) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rayon::ThreadPoolBuilder;
+
+    use super::{DataInit, GameDataBuilder};
+    use crate::{
+        core::specs::prelude::{System, World, Write},
+        metrics::FrameMetrics,
+    };
+
+    #[derive(Default)]
+    struct Runs(Vec<&'static str>);
+
+    struct Record(&'static str);
+
+    impl<'a> System<'a> for Record {
+        type SystemData = Write<'a, Runs>;
+
+        fn run(&mut self, mut runs: Self::SystemData) {
+            runs.0.push(self.0);
+        }
+    }
+
+    #[test]
+    fn only_active_groups_run() {
+        let mut world = World::new();
+        world.add_resource(Arc::new(ThreadPoolBuilder::new().build().unwrap()));
+        let mut game_data = GameDataBuilder::default()
+            .with(Record("main"), "main", &[])
+            .with_group_system("gameplay", Record("gameplay"), "gameplay", &[])
+            .with_group_system("menu", Record("menu"), "menu", &[])
+            .build(&mut world);
+        assert!(game_data.has_group("gameplay"));
+        assert!(!game_data.has_group("paused"));
+
+        game_data.update_groups(&world, &["gameplay", "paused"]);
+        assert_eq!(vec!["main", "gameplay"], world.read_resource::<Runs>().0);
+
+        game_data.update_groups(&world, &["menu"]);
+        assert_eq!(
+            vec!["main", "gameplay", "main", "menu"],
+            world.read_resource::<Runs>().0
+        );
+    }
+
+    #[test]
+    fn times_systems_and_dispatchers() {
+        let mut world = World::new();
+        world.add_resource(Arc::new(ThreadPoolBuilder::new().build().unwrap()));
+        let mut game_data = GameDataBuilder::default()
+            .with(Record("main"), "main", &[])
+            .with_thread_local(Record("local"))
+            .with_group_system("gameplay", Record("gameplay"), "gameplay", &[])
+            .with_group_thread_local("gameplay", Record("gameplay local"))
+            .build(&mut world);
+
+        game_data.update_groups(&world, &["gameplay"]);
+        let mut metrics = world.write_resource::<FrameMetrics>();
+        metrics.collect_system_timings();
+        for name in &[
+            "main",
+            "thread_local/0",
+            "dispatch",
+            "gameplay/gameplay",
+            "gameplay/thread_local/0",
+            "dispatch/gameplay",
+        ] {
+            assert_eq!(1, metrics.system(name).map_or(0, |samples| samples.len()));
+        }
+    }
+}
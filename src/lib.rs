@@ -89,18 +89,32 @@ extern crate serde_derive;
 pub use crate::core::{shred, shrev, specs as ecs};
 
 pub use self::{
-    app::{Application, ApplicationBuilder, CoreApplication},
+    app::{
+        Application, ApplicationBuilder, CoreApplication, ErrorPolicy, ShutdownHook,
+        ShutdownTimeoutPolicy,
+    },
     callback_queue::{Callback, CallbackQueue},
+    console::{
+        Console, ConsoleArgs, ConsoleBundle, ConsoleCommand, ConsoleCommands, ConsoleSystem,
+    },
     error::{Error, Result},
     game_data::{DataInit, GameData, GameDataBuilder},
-    logger::{start_logger, LevelFilter as LogLevelFilter, Logger, LoggerConfig, StdoutLog},
+    logger::{
+        start_logger, LevelFilter as LogLevelFilter, LogFileConfig, LogFormat, LogRotation, Logger,
+        LoggerConfig, StdoutLog,
+    },
+    metrics::{FrameMetrics, FramePhase, TimingSamples},
     state::{
-        EmptyState, EmptyTrans, SimpleState, SimpleTrans, State, StateData, StateMachine, Trans,
-        TransEvent,
+        EmptyState, EmptyTrans, SimpleState, SimpleTrans, State, StateData, StateMachine,
+        StateTransition, StateTransitionEvent, Trans, TransEvent,
     },
     state_event::{StateEvent, StateEventReader},
+    task::{TaskError, TaskHandle, TaskSpawner},
 };
 
+#[cfg(feature = "saveload")]
+pub use self::save_game::{SaveComponents, SaveError, SaveFormat, SaveGame, SaveMigration};
+
 #[doc(hidden)]
 pub use crate::derive::*;
 
@@ -108,8 +122,13 @@ pub mod prelude;
 
 mod app;
 mod callback_queue;
+mod console;
 mod error;
 mod game_data;
 mod logger;
+mod metrics;
+#[cfg(feature = "saveload")]
+mod save_game;
 mod state;
 mod state_event;
+mod task;
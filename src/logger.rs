@@ -1,8 +1,28 @@
 pub use log::LevelFilter;
 
-use std::{env, io, path::PathBuf, str::FromStr};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    env, fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use fern;
+use log::{Level, Record};
+
+use crate::config::{Validate, ValidationErrors};
+
+/// The frame number reported by the JSON log output, updated by the `Application` every frame.
+///
+/// `AtomicU64` isn't stable yet, so on 32 bit targets the reported number wraps around to zero
+/// after 2^32 frames, which takes more than two years at 60 frames per second.
+static FRAME_NUMBER: AtomicUsize = AtomicUsize::new(0);
 
 /// An enum that contains options for logging to the terminal.
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
@@ -15,8 +35,65 @@ pub enum StdoutLog {
     Colored,
 }
 
+/// How log records are written to a log file.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum LogFormat {
+    /// One human readable line per record, the same as the terminal output.
+    Plain,
+    /// One JSON object per line, including the frame number and the thread name.
+    Json,
+}
+
+impl Default for LogFormat {
+    fn default() -> Self {
+        LogFormat::Plain
+    }
+}
+
+/// Determines when a log file is rotated.
+///
+/// When rotated, `game.log` is renamed to `game.log.1`, `game.log.1` to `game.log.2` and so on,
+/// keeping at most `keep` old files.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogRotation {
+    /// If set, the file is rotated once it grows past this many bytes.
+    pub max_size: Option<u64>,
+    /// If set, the file is rotated once it has been written to for this many seconds.
+    pub max_age: Option<u64>,
+    /// The number of rotated files to keep.
+    pub keep: usize,
+}
+
+impl Default for LogRotation {
+    fn default() -> LogRotation {
+        LogRotation {
+            max_size: Some(10 * 1024 * 1024),
+            max_age: None,
+            keep: 5,
+        }
+    }
+}
+
+/// Configuration of an additional log file output.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LogFileConfig {
+    /// Path of the log file.
+    pub path: PathBuf,
+    /// Format of the records written to the file.
+    #[serde(default)]
+    pub format: LogFormat,
+    /// Level filter for this output. Uses `LoggerConfig::level_filter` if not set.
+    #[serde(default)]
+    pub level: Option<LevelFilter>,
+    /// If set, the file is rotated according to these rules.
+    #[serde(default)]
+    pub rotation: Option<LogRotation>,
+}
+
 /// Logger configuration object.
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct LoggerConfig {
     /// Determines whether to log to the terminal or not.
     pub stdout: StdoutLog,
@@ -26,6 +103,22 @@ pub struct LoggerConfig {
     pub log_file: Option<PathBuf>,
     /// If set, allows the config values to be overriden via the corresponding environmental variables.
     pub allow_env_override: bool,
+    /// Level filter for the terminal output. Uses `level_filter` if not set.
+    pub stdout_level: Option<LevelFilter>,
+    /// Additional log file outputs, each with its own format, level and rotation.
+    pub files: Vec<LogFileConfig>,
+    /// Level filters for individual modules, overriding the level of every output.
+    pub module_levels: BTreeMap<String, LevelFilter>,
+}
+
+impl Validate for LoggerConfig {
+    fn validate(&self, errors: &mut ValidationErrors) {
+        for (index, file) in self.files.iter().enumerate() {
+            if file.path.as_os_str().is_empty() {
+                errors.add(&format!("files.{}.path", index), "must not be empty");
+            }
+        }
+    }
 }
 
 impl Default for LoggerConfig {
@@ -35,6 +128,9 @@ impl Default for LoggerConfig {
             level_filter: LevelFilter::Debug,
             log_file: None,
             allow_env_override: true,
+            stdout_level: None,
+            files: Vec::new(),
+            module_levels: BTreeMap::new(),
         }
     }
 }
@@ -50,66 +146,91 @@ impl Default for LoggerConfig {
 ///     .start();
 /// ```
 pub struct Logger {
-    dispatch: fern::Dispatch,
+    config: LoggerConfig,
 }
 
 impl Logger {
-    fn new() -> Self {
-        let dispatch = fern::Dispatch::new().format(|out, message, record| {
-            out.finish(format_args!(
-                "[{level}][{target}] {message}",
-                level = record.level(),
-                target = record.target(),
-                message = message,
-            ))
-        });
-        Logger { dispatch }
-    }
-
     /// Create a new Logger from [`LoggerConfig`]
     pub fn from_config(mut config: LoggerConfig) -> Self {
         if config.allow_env_override {
             env_var_override(&mut config);
         }
 
-        let mut logger = Logger::new();
-        logger.dispatch = logger.dispatch.level(config.level_filter);
+        Logger { config }
+    }
+
+    /// Set individual log levels for modules.
+    pub fn level_for<T: Into<Cow<'static, str>>>(mut self, module: T, level: LevelFilter) -> Self {
+        self.config
+            .module_levels
+            .insert(module.into().into_owned(), level);
+        self
+    }
+
+    /// Starts [`Logger`] by consuming it.
+    pub fn start(self) {
+        self.into_dispatch().apply().unwrap_or_else(|_| {
+            debug!("Global logger already set, default Amethyst logger will not be used")
+        });
+    }
+
+    fn into_dispatch(self) -> fern::Dispatch {
+        let config = self.config;
+        let mut outputs = Vec::new();
 
         match config.stdout {
-            StdoutLog::Plain => logger.dispatch = logger.dispatch.chain(io::stdout()),
-            StdoutLog::Colored => {
-                logger.dispatch = logger
-                    .dispatch
-                    .chain(colored_stdout(fern::colors::ColoredLevelConfig::new()))
-            }
+            StdoutLog::Plain => outputs.push((
+                config.stdout_level,
+                fern::Dispatch::new().format(plain_format).chain(io::stdout()),
+            )),
+            StdoutLog::Colored => outputs.push((
+                config.stdout_level,
+                colored_stdout(fern::colors::ColoredLevelConfig::new()),
+            )),
             StdoutLog::Off => {}
         }
 
         if let Some(path) = config.log_file {
             match fern::log_file(path) {
-                Ok(log_file) => logger.dispatch = logger.dispatch.chain(log_file),
+                Ok(log_file) => outputs.push((
+                    None,
+                    fern::Dispatch::new().format(plain_format).chain(log_file),
+                )),
                 Err(_) => eprintln!("Unable to access the log file, as such it will not be used"),
             }
         }
 
-        logger
-    }
+        for file in config.files {
+            match file_output(&file) {
+                Ok(output) => outputs.push((file.level, output)),
+                Err(err) => eprintln!(
+                    "Unable to access the log file {}, as such it will not be used: {}",
+                    file.path.display(),
+                    err
+                ),
+            }
+        }
 
-    /// Set individual log levels for modules.
-    pub fn level_for<T: Into<std::borrow::Cow<'static, str>>>(
-        mut self,
-        module: T,
-        level: LevelFilter,
-    ) -> Self {
-        self.dispatch = self.dispatch.level_for(module, level);
-        self
-    }
+        let mut max_level = config
+            .module_levels
+            .values()
+            .cloned()
+            .fold(LevelFilter::Off, std::cmp::max);
+        let mut dispatch = fern::Dispatch::new();
+        for (level, output) in outputs {
+            let level = level.unwrap_or(config.level_filter);
+            max_level = max_level.max(level);
 
-    /// Starts [`Logger`] by consuming it.
-    pub fn start(self) {
-        self.dispatch.apply().unwrap_or_else(|_| {
-            debug!("Global logger already set, default Amethyst logger will not be used")
-        });
+            let output = config
+                .module_levels
+                .iter()
+                .fold(output.level(level), |output, (module, level)| {
+                    output.level_for(module.clone(), *level)
+                });
+            dispatch = dispatch.chain(output);
+        }
+
+        dispatch.level(max_level)
     }
 }
 
@@ -126,6 +247,11 @@ pub fn start_logger(config: LoggerConfig) {
     Logger::from_config(config).start();
 }
 
+/// Sets the frame number reported by the JSON log output, truncated to `usize`.
+pub(crate) fn set_frame_number(frame_number: u64) {
+    FRAME_NUMBER.store(frame_number as usize, Ordering::Relaxed);
+}
+
 fn env_var_override(config: &mut LoggerConfig) {
     if let Ok(var) = env::var("AMETHYST_LOG_STDOUT") {
         match var.to_lowercase().as_ref() {
@@ -145,16 +271,240 @@ fn env_var_override(config: &mut LoggerConfig) {
     }
 }
 
+fn plain_format(out: fern::FormatCallback<'_>, message: &fmt::Arguments<'_>, record: &Record<'_>) {
+    out.finish(format_args!(
+        "[{level}][{target}] {message}",
+        level = record.level(),
+        target = record.target(),
+        message = message,
+    ))
+}
+
+fn json_format(out: fern::FormatCallback<'_>, message: &fmt::Arguments<'_>, record: &Record<'_>) {
+    #[derive(Serialize)]
+    struct JsonRecord<'a> {
+        timestamp: f64,
+        level: Level,
+        target: &'a str,
+        thread: Option<&'a str>,
+        frame: u64,
+        message: String,
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs() as f64 + f64::from(time.subsec_nanos()) / 1.0e9)
+        .unwrap_or(0.0);
+    let current = thread::current();
+    let json = serde_json::to_string(&JsonRecord {
+        timestamp,
+        level: record.level(),
+        target: record.target(),
+        thread: current.name(),
+        frame: FRAME_NUMBER.load(Ordering::Relaxed) as u64,
+        message: message.to_string(),
+    });
+
+    match json {
+        Ok(json) => out.finish(format_args!("{}", json)),
+        Err(err) => eprintln!("Unable to format log record as JSON: {}", err),
+    }
+}
+
 fn colored_stdout(color_config: fern::colors::ColoredLevelConfig) -> fern::Dispatch {
     fern::Dispatch::new()
         .chain(io::stdout())
         .format(move |out, message, record| {
             let color = color_config.get_color(&record.level());
             out.finish(format_args!(
-                "{color}{message}{color_reset}",
+                "{color}[{level}][{target}] {message}{color_reset}",
                 color = format!("\x1B[{}m", color.to_fg_str()),
+                level = record.level(),
+                target = record.target(),
                 message = message,
                 color_reset = "\x1B[0m",
             ))
         })
 }
+
+fn file_output(config: &LogFileConfig) -> io::Result<fern::Dispatch> {
+    let dispatch = match config.format {
+        LogFormat::Plain => fern::Dispatch::new().format(plain_format),
+        LogFormat::Json => fern::Dispatch::new().format(json_format),
+    };
+
+    Ok(match config.rotation {
+        Some(ref rotation) => {
+            let file = RotatingFile::open(config.path.clone(), rotation.clone())?;
+            dispatch.chain(Box::new(file) as Box<dyn Write + Send>)
+        }
+        None => dispatch.chain(fern::log_file(&config.path)?),
+    })
+}
+
+/// A log file that is rotated when it grows too large or too old.
+struct RotatingFile {
+    path: PathBuf,
+    rotation: LogRotation,
+    file: File,
+    size: u64,
+    opened: Instant,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, rotation: LogRotation) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(RotatingFile {
+            path,
+            rotation,
+            file,
+            size,
+            opened: Instant::now(),
+        })
+    }
+
+    fn should_rotate(&self) -> bool {
+        let too_large = self.rotation.max_size.map_or(false, |max| self.size >= max);
+        let too_old = self
+            .rotation
+            .max_age
+            .map_or(false, |max| self.opened.elapsed() >= Duration::from_secs(max));
+        self.size > 0 && (too_large || too_old)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for n in (1..self.rotation.keep).rev() {
+            let from = rotated_path(&self.path, n);
+            if from.exists() {
+                fs::rename(&from, rotated_path(&self.path, n + 1))?;
+            }
+        }
+        if self.rotation.keep > 0 {
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        self.size = 0;
+        self.opened = Instant::now();
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    // The logger flushes after every record, so rotating here never splits a line.
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.should_rotate() {
+            self.rotate()?;
+        }
+        Ok(())
+    }
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(format!(".{}", n));
+    PathBuf::from(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        io::{self, Write},
+        path::Path,
+        process,
+        sync::{Arc, Mutex},
+    };
+
+    use log::{Level, Log, Record};
+
+    use super::{json_format, rotated_path, LogRotation, RotatingFile};
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn rotates_past_max_size() {
+        let dir = env::temp_dir().join(format!("amethyst_log_rotation_{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("game.log");
+        let rotation = LogRotation {
+            max_size: Some(10),
+            max_age: None,
+            keep: 2,
+        };
+
+        let mut file = RotatingFile::open(path.clone(), rotation).unwrap();
+        for line in &["one\n", "two\n", "three\n", "four\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+            file.flush().unwrap();
+        }
+        assert_eq!("four\n", read(&path));
+        assert_eq!("one\ntwo\nthree\n", read(&rotated_path(&path, 1)));
+
+        for line in &["five and six\n", "seven and eight\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+            file.flush().unwrap();
+        }
+        assert_eq!("", read(&path));
+        assert_eq!("seven and eight\n", read(&rotated_path(&path, 1)));
+        assert_eq!("four\nfive and six\n", read(&rotated_path(&path, 2)));
+        assert!(!rotated_path(&path, 3).exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn json_format_writes_one_object_per_line() {
+        let buffer = Buffer::default();
+        let (_, log) = fern::Dispatch::new()
+            .format(json_format)
+            .chain(Box::new(buffer.clone()) as Box<dyn Write + Send>)
+            .into_log();
+        log.log(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("game::ai")
+                .args(format_args!("lost {}", "track"))
+                .build(),
+        );
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(1, output.lines().count());
+        let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!("WARN", json["level"]);
+        assert_eq!("game::ai", json["target"]);
+        assert_eq!("lost track", json["message"]);
+        assert!(json["timestamp"].as_f64().unwrap() > 0.0);
+        assert!(json["frame"].is_u64());
+        assert!(json["thread"].is_string());
+    }
+}
@@ -0,0 +1,396 @@
+//! Frame timing metrics.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::{
+    core::timing::{duration_to_nanos, nanos_to_duration},
+    ecs::prelude::System,
+    shred::{AccessorCow, Resources, RunningTime},
+};
+
+/// The number of samples kept per timing by default.
+const DEFAULT_WINDOW: usize = 120;
+
+/// The number of system timings that can be sent before they are collected. Timings sent while
+/// the channel is full are dropped.
+const MAX_PENDING_TIMINGS: usize = 4096;
+
+/// A phase of `Application::advance_frame`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FramePhase {
+    /// Reading events and passing them to the active state.
+    HandleEvent,
+    /// Running `State::fixed_update` as many times as needed.
+    FixedUpdate,
+    /// Running `State::update`, which usually dispatches the systems.
+    Update,
+    /// Maintaining the `World`.
+    Maintain,
+    /// The whole frame, including the time spent in the frame limiter.
+    Frame,
+}
+
+/// A rolling window of timing samples, keeping at most `window` samples.
+#[derive(Clone, Debug)]
+pub struct TimingSamples {
+    samples: VecDeque<Duration>,
+    window: usize,
+}
+
+impl TimingSamples {
+    /// Creates an empty set of samples that keeps at most `window` samples, and at least one.
+    pub fn new(window: usize) -> Self {
+        let window = window.max(1);
+        TimingSamples {
+            samples: VecDeque::with_capacity(window),
+            window,
+        }
+    }
+
+    /// Adds a sample, discarding the oldest one if the window is full.
+    pub fn push(&mut self, sample: Duration) {
+        while self.samples.len() >= self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Gets the number of samples currently in the window.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Checks whether there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Gets the most recent sample.
+    pub fn last(&self) -> Option<Duration> {
+        self.samples.back().cloned()
+    }
+
+    /// Gets the average of the samples in the window.
+    pub fn average(&self) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::from_secs(0);
+        }
+
+        let total: u64 = self.samples.iter().cloned().map(duration_to_nanos).sum();
+        nanos_to_duration(total / self.samples.len() as u64)
+    }
+
+    /// Gets the longest sample in the window.
+    pub fn max(&self) -> Duration {
+        self.samples
+            .iter()
+            .cloned()
+            .max()
+            .unwrap_or_else(|| Duration::from_secs(0))
+    }
+
+    /// Gets the given percentile of the samples in the window, e.g. `95.0` for the 95th
+    /// percentile.
+    ///
+    /// ## Panics
+    /// This will panic if `percent` is not within `0.0..=100.0`.
+    pub fn percentile(&self, percent: f32) -> Duration {
+        assert!(percent >= 0.0 && percent <= 100.0);
+        if self.samples.is_empty() {
+            return Duration::from_secs(0);
+        }
+
+        let mut sorted = self.samples.iter().cloned().collect::<Vec<_>>();
+        sorted.sort();
+        let index = (percent / 100.0 * (sorted.len() - 1) as f32).round() as usize;
+        sorted[index]
+    }
+}
+
+/// A timing sample of a single system run, with the index of the system's name in its
+/// `SystemTimers`.
+type SystemTiming = (usize, Duration);
+
+/// Frame timing metrics, updated by the `Application` every frame.
+///
+/// Contains the duration of every phase of the frame, of every system and thread-local system
+/// added to the `GameDataBuilder` and of every run of its dispatchers.
+///
+/// Systems added by bundles are not measured individually, their run time is included in the
+/// timing of their dispatcher, `"dispatch"` for the main dispatcher and `"dispatch/<group>"`
+/// for dispatcher groups.
+#[derive(Debug)]
+pub struct FrameMetrics {
+    window: usize,
+    phases: HashMap<FramePhase, TimingSamples>,
+    systems: HashMap<String, TimingSamples>,
+    timers: Vec<(Vec<String>, Receiver<SystemTiming>)>,
+}
+
+impl Default for FrameMetrics {
+    fn default() -> Self {
+        FrameMetrics::new(DEFAULT_WINDOW)
+    }
+}
+
+impl FrameMetrics {
+    /// Creates new frame metrics keeping `window` samples per timing.
+    pub fn new(window: usize) -> Self {
+        FrameMetrics {
+            window,
+            phases: HashMap::new(),
+            systems: HashMap::new(),
+            timers: Vec::new(),
+        }
+    }
+
+    /// Gets the timing samples of a frame phase.
+    pub fn phase(&self, phase: FramePhase) -> Option<&TimingSamples> {
+        self.phases.get(&phase)
+    }
+
+    /// Gets the timing samples of the system with the given name.
+    pub fn system(&self, name: &str) -> Option<&TimingSamples> {
+        self.systems.get(name)
+    }
+
+    /// Iterates over the timing samples of all measured systems.
+    pub fn systems(&self) -> impl Iterator<Item = (&str, &TimingSamples)> {
+        self.systems
+            .iter()
+            .map(|(name, samples)| (name.as_str(), samples))
+    }
+
+    /// Records the duration of a frame phase.
+    ///
+    /// This should only be called by the engine.
+    pub fn record_phase(&mut self, phase: FramePhase, duration: Duration) {
+        let window = self.window;
+        self.phases
+            .entry(phase)
+            .or_insert_with(|| TimingSamples::new(window))
+            .push(duration);
+    }
+
+    /// Collects the system timings measured since the last call.
+    ///
+    /// This should only be called by the engine.
+    pub fn collect_system_timings(&mut self) {
+        let window = self.window;
+        for &(ref names, ref receiver) in &self.timers {
+            while let Ok((index, duration)) = receiver.try_recv() {
+                let name = &names[index];
+                if let Some(samples) = self.systems.get_mut(name) {
+                    samples.push(duration);
+                    continue;
+                }
+
+                let mut samples = TimingSamples::new(window);
+                samples.push(duration);
+                self.systems.insert(name.clone(), samples);
+            }
+        }
+    }
+
+    pub(crate) fn add_timers(&mut self, timers: SystemTimers) {
+        self.timers.push((timers.names, timers.receiver));
+    }
+}
+
+/// Creates the timers of systems and dispatchers.
+///
+/// The names of the timers are stored once, and every run only sends the index of the name.
+pub(crate) struct SystemTimers {
+    names: Vec<String>,
+    sender: Sender<SystemTiming>,
+    receiver: Receiver<SystemTiming>,
+}
+
+impl SystemTimers {
+    pub(crate) fn new() -> Self {
+        let (sender, receiver) = crossbeam_channel::bounded(MAX_PENDING_TIMINGS);
+        SystemTimers {
+            names: Vec::new(),
+            sender,
+            receiver,
+        }
+    }
+
+    /// Creates a timer recording run times as `name`.
+    pub(crate) fn timer(&mut self, name: String) -> Timer {
+        self.names.push(name);
+        Timer {
+            index: self.names.len() - 1,
+            sender: self.sender.clone(),
+        }
+    }
+
+    /// Wraps a system, recording its run time as `name`.
+    pub(crate) fn timed<S>(&mut self, system: S, name: String) -> TimedSystem<S> {
+        TimedSystem {
+            system,
+            timer: self.timer(name),
+        }
+    }
+
+    /// Counts the timers whose name starts with `prefix`.
+    pub(crate) fn count(&self, prefix: &str) -> usize {
+        self.names
+            .iter()
+            .filter(|name| name.starts_with(prefix))
+            .count()
+    }
+}
+
+/// Records run times for the `FrameMetrics`.
+pub(crate) struct Timer {
+    index: usize,
+    sender: Sender<SystemTiming>,
+}
+
+impl Timer {
+    /// Records the time elapsed since `start`.
+    pub(crate) fn record(&self, start: Instant) {
+        let _ = self.sender.try_send((self.index, start.elapsed()));
+    }
+}
+
+/// Wraps a system to measure how long each run takes.
+pub(crate) struct TimedSystem<S> {
+    system: S,
+    timer: Timer,
+}
+
+impl<'a, S> System<'a> for TimedSystem<S>
+where
+    S: System<'a>,
+{
+    type SystemData = S::SystemData;
+
+    fn run(&mut self, data: Self::SystemData) {
+        let start = Instant::now();
+        self.system.run(data);
+        self.timer.record(start);
+    }
+
+    fn running_time(&self) -> RunningTime {
+        self.system.running_time()
+    }
+
+    fn accessor<'b>(&'b self) -> AccessorCow<'a, 'b, Self> {
+        match self.system.accessor() {
+            AccessorCow::Ref(accessor) => AccessorCow::Ref(accessor),
+            AccessorCow::Owned(accessor) => AccessorCow::Owned(accessor),
+        }
+    }
+
+    fn setup(&mut self, res: &mut Resources) {
+        self.system.setup(res);
+    }
+
+    fn dispose(self, res: &mut Resources) {
+        self.system.dispose(res);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{FrameMetrics, SystemTimers, TimingSamples};
+    use crate::{
+        ecs::prelude::{RunNow, System, World},
+        shred::Resources,
+    };
+
+    #[test]
+    fn rolling_window() {
+        let mut samples = TimingSamples::new(4);
+        for millis in 1..=6 {
+            samples.push(Duration::from_millis(millis));
+        }
+
+        assert_eq!(4, samples.len());
+        assert_eq!(Some(Duration::from_millis(6)), samples.last());
+        assert_eq!(Duration::from_micros(4500), samples.average());
+        assert_eq!(Duration::from_millis(6), samples.max());
+    }
+
+    #[test]
+    fn percentile() {
+        let mut samples = TimingSamples::new(101);
+        for millis in (0..=100).rev() {
+            samples.push(Duration::from_millis(millis));
+        }
+
+        assert_eq!(Duration::from_millis(0), samples.percentile(0.0));
+        assert_eq!(Duration::from_millis(50), samples.percentile(50.0));
+        assert_eq!(Duration::from_millis(95), samples.percentile(95.0));
+        assert_eq!(Duration::from_millis(100), samples.percentile(100.0));
+    }
+
+    #[test]
+    fn empty_window_keeps_one_sample() {
+        let mut samples = TimingSamples::new(0);
+        for millis in 1..=3 {
+            samples.push(Duration::from_millis(millis));
+        }
+
+        assert_eq!(1, samples.len());
+        assert_eq!(Some(Duration::from_millis(3)), samples.last());
+    }
+
+    struct Nop;
+
+    impl<'a> System<'a> for Nop {
+        type SystemData = ();
+
+        fn run(&mut self, _: ()) {}
+    }
+
+    #[test]
+    fn collects_system_timings() {
+        let world = World::new();
+        let mut timers = SystemTimers::new();
+        let mut first = timers.timed(Nop, "first".to_owned());
+        let mut second = timers.timed(Nop, "second".to_owned());
+        let mut metrics = FrameMetrics::new(4);
+        metrics.add_timers(timers);
+
+        for _ in 0..3 {
+            first.run_now(&world.res);
+        }
+        second.run_now(&world.res);
+        metrics.collect_system_timings();
+
+        assert_eq!(3, metrics.system("first").unwrap().len());
+        assert_eq!(1, metrics.system("second").unwrap().len());
+        assert!(metrics.system("third").is_none());
+    }
+
+    struct Disposed;
+
+    impl<'a> System<'a> for Disposed {
+        type SystemData = ();
+
+        fn run(&mut self, _: ()) {}
+
+        fn dispose(self, res: &mut Resources) {
+            res.insert(1u32);
+        }
+    }
+
+    #[test]
+    fn forwards_dispose() {
+        let mut world = World::new();
+        let system = SystemTimers::new().timed(Disposed, "disposed".to_owned());
+        system.dispose(&mut world.res);
+
+        assert_eq!(1, *world.read_resource::<u32>());
+    }
+}
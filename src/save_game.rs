@@ -0,0 +1,722 @@
+//! Saving and loading the state of marked entities.
+
+use std::{
+    collections::HashMap,
+    error::Error as StdError,
+    fmt, fs, io,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+use serde::{
+    de::{self, DeserializeSeed, Deserializer, IgnoredAny, SeqAccess, Visitor},
+    ser::{Serialize, Serializer},
+};
+
+use crate::{
+    core::ParentSaveloadError,
+    ecs::{
+        error::NoError,
+        prelude::{Component, Entities, ReadStorage, World, Write, WriteStorage},
+        saveload::{ConvertSaveload, DeserializeComponents, Marker, SerializeComponents},
+    },
+    shred::SystemData,
+};
+
+/// A migration step, converting the raw contents of a save file written by one version of the
+/// game to the next version.
+pub type SaveMigration = Box<dyn Fn(&[u8]) -> Result<Vec<u8>, SaveError> + Send + Sync>;
+
+/// Error raised while saving or loading a game.
+#[derive(Debug)]
+pub enum SaveError {
+    /// Forward to the `std::io::Error` error.
+    File(io::Error),
+    /// Failed to write or read a RON save file.
+    Ron(String),
+    /// Failed to write or read a binary save file.
+    Bincode(bincode::Error),
+    /// The save file was written by a newer version of the game.
+    UnsupportedVersion {
+        /// The version of the save file.
+        found: u32,
+        /// The newest version this game can read.
+        current: u32,
+    },
+    /// There is no migration registered for a save file version.
+    MissingMigration(u32),
+    /// A migration failed.
+    Migration(String),
+    /// A component could not be converted for saving or loading.
+    Component(String),
+    /// The name of a save slot can't be used as a file name.
+    InvalidSlot(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            SaveError::File(ref err) => write!(f, "{}", err),
+            SaveError::Ron(ref msg) => write!(f, "{}", msg),
+            SaveError::Bincode(ref err) => write!(f, "{}", err),
+            SaveError::UnsupportedVersion { found, current } => write!(
+                f,
+                "Save file version {} is newer than the supported version {}",
+                found, current
+            ),
+            SaveError::MissingMigration(version) => write!(
+                f,
+                "No migration registered for save file version {}",
+                version
+            ),
+            SaveError::Migration(ref msg) => write!(f, "Migration failed: {}", msg),
+            SaveError::Component(ref msg) => write!(f, "{}", msg),
+            SaveError::InvalidSlot(ref slot) => write!(
+                f,
+                "Invalid save slot name {:?}, only letters, digits, spaces, `-` and `_` are allowed",
+                slot
+            ),
+        }
+    }
+}
+
+impl StdError for SaveError {
+    fn description(&self) -> &str {
+        match *self {
+            SaveError::File(_) => "Save file error",
+            SaveError::Ron(_) => "RON save file error",
+            SaveError::Bincode(_) => "Binary save file error",
+            SaveError::UnsupportedVersion { .. } => "Unsupported save file version",
+            SaveError::MissingMigration(_) => "Missing save file migration",
+            SaveError::Migration(_) => "Save file migration error",
+            SaveError::Component(_) => "Component conversion error",
+            SaveError::InvalidSlot(_) => "Invalid save slot name",
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn StdError> {
+        match *self {
+            SaveError::File(ref err) => Some(err),
+            SaveError::Bincode(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> Self {
+        SaveError::File(e)
+    }
+}
+
+impl From<ron::de::Error> for SaveError {
+    fn from(e: ron::de::Error) -> Self {
+        SaveError::Ron(e.to_string())
+    }
+}
+
+impl From<ron::ser::Error> for SaveError {
+    fn from(e: ron::ser::Error) -> Self {
+        SaveError::Ron(e.to_string())
+    }
+}
+
+impl From<bincode::Error> for SaveError {
+    fn from(e: bincode::Error) -> Self {
+        SaveError::Bincode(e)
+    }
+}
+
+impl From<NoError> for SaveError {
+    fn from(e: NoError) -> Self {
+        match e {}
+    }
+}
+
+impl From<ParentSaveloadError> for SaveError {
+    fn from(e: ParentSaveloadError) -> Self {
+        SaveError::Component(e.to_string())
+    }
+}
+
+/// The format save files are written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveFormat {
+    /// Human readable RON, stored with the `ron` extension.
+    Ron,
+    /// Compact binary encoding, stored with the `sav` extension.
+    Binary,
+}
+
+impl SaveFormat {
+    /// Gets the file extension used for this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            SaveFormat::Ron => "ron",
+            SaveFormat::Binary => "sav",
+        }
+    }
+}
+
+impl Default for SaveFormat {
+    fn default() -> Self {
+        SaveFormat::Ron
+    }
+}
+
+/// A set of component types which are written to and read from save files, implemented for
+/// tuples of components like `(Transform, Parent, Named)`.
+///
+/// Components that are `Clone`, `Serialize` and `Deserialize` are supported out of the box.
+/// Components referring to other entities need to implement `ConvertSaveload` with an `Error`
+/// that converts into `SaveError`, like `Parent` does.
+pub trait SaveComponents<M: Marker> {
+    /// Registers the components, markers and the marker allocator in the `World`.
+    fn setup(world: &mut World);
+
+    /// Serializes the components of all entities marked with `M`.
+    fn serialize<S: Serializer>(world: &World, serializer: S) -> Result<S::Ok, S::Error>;
+
+    /// Deserializes entities and their components, reusing the entities that are already
+    /// marked with the same marker.
+    fn deserialize<'de, D: Deserializer<'de>>(
+        world: &World,
+        deserializer: D,
+    ) -> Result<(), D::Error>;
+}
+
+macro_rules! impl_save_components {
+    ($($component:ident),*) => {
+        impl<M, $($component),*> SaveComponents<M> for ($($component,)*)
+        where
+            M: Marker,
+            M::Allocator: Default,
+            $(
+                $component: Component + ConvertSaveload<M>,
+                SaveError: From<<$component as ConvertSaveload<M>>::Error>,
+            )*
+        {
+            fn setup(world: &mut World) {
+                <(
+                    Entities<'_>,
+                    WriteStorage<'_, M>,
+                    Write<'_, M::Allocator>,
+                    ($(WriteStorage<'_, $component>,)*),
+                ) as SystemData<'_>>::setup(&mut world.res);
+            }
+
+            fn serialize<S: Serializer>(world: &World, serializer: S) -> Result<S::Ok, S::Error> {
+                let (entities, markers, storages) = world.system_data::<(
+                    Entities<'_>,
+                    ReadStorage<'_, M>,
+                    ($(ReadStorage<'_, $component>,)*),
+                )>();
+                SerializeComponents::<SaveError, M>::serialize(
+                    &storages,
+                    &entities,
+                    &markers,
+                    serializer,
+                )
+            }
+
+            fn deserialize<'de, D: Deserializer<'de>>(
+                world: &World,
+                deserializer: D,
+            ) -> Result<(), D::Error> {
+                let (entities, mut markers, mut allocator, mut storages) = world.system_data::<(
+                    Entities<'_>,
+                    WriteStorage<'_, M>,
+                    Write<'_, M::Allocator>,
+                    ($(WriteStorage<'_, $component>,)*),
+                )>();
+                DeserializeComponents::<SaveError, M>::deserialize(
+                    &mut storages,
+                    &entities,
+                    &mut markers,
+                    &mut allocator,
+                    deserializer,
+                )
+            }
+        }
+    };
+}
+
+impl_save_components!(A);
+impl_save_components!(A, B);
+impl_save_components!(A, B, C);
+impl_save_components!(A, B, C, D);
+impl_save_components!(A, B, C, D, E);
+impl_save_components!(A, B, C, D, E, F);
+impl_save_components!(A, B, C, D, E, F, G);
+impl_save_components!(A, B, C, D, E, F, G, H);
+impl_save_components!(A, B, C, D, E, F, G, H, I);
+impl_save_components!(A, B, C, D, E, F, G, H, I, J);
+impl_save_components!(A, B, C, D, E, F, G, H, I, J, K);
+impl_save_components!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+/// Saves and loads entities marked with `M` into named save slots.
+///
+/// A save file contains the save version followed by the marked entities and the components
+/// listed in the `SaveComponents` set. When a file written by an older version of the game is
+/// loaded, the registered migrations are run one version at a time until the file is current.
+///
+/// Save files are stored in the per-user data directory of the platform, e.g.
+/// `~/.local/share/<game>/saves` on Linux.
+///
+/// # Example
+/// ```rust,ignore
+/// let save_game = SaveGame::<U64Marker>::new("my_game")
+///     .with_version(2)
+///     .with_migration(1, |bytes| Ok(upgrade_from_v1(bytes)));
+///
+/// world.create_entity().with(Transform::default()).marked::<U64Marker>().build();
+/// save_game.save::<(Transform, Parent, Named)>(&mut world, "quicksave")?;
+/// save_game.load::<(Transform, Parent, Named)>(&mut world, "quicksave")?;
+/// ```
+pub struct SaveGame<M> {
+    directory: PathBuf,
+    format: SaveFormat,
+    version: u32,
+    migrations: HashMap<u32, SaveMigration>,
+    marker: PhantomData<M>,
+}
+
+impl<M> SaveGame<M>
+where
+    M: Marker,
+{
+    /// Creates a new `SaveGame` storing the save slots of `game_name` in the per-user data
+    /// directory. Falls back to a `saves` directory in the working directory if the platform
+    /// has no data directory.
+    pub fn new(game_name: &str) -> Self {
+        let directory = match dirs::data_dir() {
+            Some(dir) => dir.join(game_name).join("saves"),
+            None => {
+                warn!("No data directory found, storing save games in the working directory");
+                PathBuf::from("saves")
+            }
+        };
+        SaveGame::with_directory(directory)
+    }
+
+    /// Creates a new `SaveGame` storing the save slots in `directory`.
+    pub fn with_directory<P: Into<PathBuf>>(directory: P) -> Self {
+        SaveGame {
+            directory: directory.into(),
+            format: SaveFormat::default(),
+            version: 1,
+            migrations: HashMap::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Sets the format save files are written in. Defaults to `SaveFormat::Ron`.
+    pub fn with_format(mut self, format: SaveFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets the current save version, which is written to new save files. Defaults to `1`.
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Registers a migration from save files of version `from` to the next version.
+    ///
+    /// The migration receives the raw contents of the save file and has to return the contents
+    /// in the format of a newer version, including the new version number.
+    pub fn with_migration<F>(mut self, from: u32, migration: F) -> Self
+    where
+        F: Fn(&[u8]) -> Result<Vec<u8>, SaveError> + Send + Sync + 'static,
+    {
+        self.migrations.insert(from, Box::new(migration));
+        self
+    }
+
+    /// Gets the directory save slots are stored in.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Gets the path of the file backing a save slot.
+    ///
+    /// Slot names are used as file names, so they may only contain letters, digits, spaces,
+    /// `-` and `_`. Other names, like `../settings`, fail with `SaveError::InvalidSlot`.
+    pub fn slot_path(&self, slot: &str) -> Result<PathBuf, SaveError> {
+        let valid = slot
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '_');
+        if slot.trim().is_empty() || !valid {
+            return Err(SaveError::InvalidSlot(slot.to_owned()));
+        }
+
+        Ok(self
+            .directory
+            .join(format!("{}.{}", slot, self.format.extension())))
+    }
+
+    /// Lists the names of all existing save slots.
+    pub fn slots(&self) -> Result<Vec<String>, SaveError> {
+        if !self.directory.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut slots = Vec::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(self.format.extension()) {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                slots.push(name.to_owned());
+            }
+        }
+        slots.sort();
+        Ok(slots)
+    }
+
+    /// Checks whether a save slot exists.
+    pub fn exists(&self, slot: &str) -> bool {
+        self.slot_path(slot).map_or(false, |path| path.exists())
+    }
+
+    /// Deletes a save slot.
+    pub fn delete(&self, slot: &str) -> Result<(), SaveError> {
+        fs::remove_file(self.slot_path(slot)?)?;
+        Ok(())
+    }
+
+    /// Saves all entities marked with `M` and their components `C` into a slot, replacing the
+    /// slot's previous contents.
+    pub fn save<C>(&self, world: &mut World, slot: &str) -> Result<(), SaveError>
+    where
+        C: SaveComponents<M>,
+    {
+        let path = self.slot_path(slot)?;
+        C::setup(world);
+        let contents = SaveFileOut::<C, M> {
+            world,
+            marker: PhantomData,
+        };
+        let file = (self.version, contents);
+        let bytes = match self.format {
+            SaveFormat::Ron => {
+                ron::ser::to_string_pretty(&file, Default::default())?.into_bytes()
+            }
+            SaveFormat::Binary => bincode::serialize(&file)?,
+        };
+
+        // Write to a temporary file first, so a failed save does not corrupt the slot.
+        fs::create_dir_all(&self.directory)?;
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, bytes)?;
+        fs::rename(&temp, &path)?;
+        Ok(())
+    }
+
+    /// Loads the entities and their components `C` from a slot into the `World`.
+    ///
+    /// Entities whose marker already exists in the `World` are updated, all other entities are
+    /// created.
+    pub fn load<C>(&self, world: &mut World, slot: &str) -> Result<(), SaveError>
+    where
+        C: SaveComponents<M>,
+    {
+        let mut bytes = fs::read(self.slot_path(slot)?)?;
+        let mut version = self.read_version(&bytes)?;
+        if version > self.version {
+            return Err(SaveError::UnsupportedVersion {
+                found: version,
+                current: self.version,
+            });
+        }
+
+        while version < self.version {
+            let migration = self
+                .migrations
+                .get(&version)
+                .ok_or(SaveError::MissingMigration(version))?;
+            info!("Migrating save slot '{}' from version {}", slot, version);
+            bytes = migration(&bytes)?;
+
+            let migrated = self.read_version(&bytes)?;
+            if migrated <= version {
+                return Err(SaveError::Migration(format!(
+                    "migration from version {} produced version {}",
+                    version, migrated
+                )));
+            }
+            version = migrated;
+        }
+
+        C::setup(world);
+        let seed = SaveFileSeed::<C, M> {
+            world,
+            marker: PhantomData,
+        };
+        match self.format {
+            SaveFormat::Ron => {
+                let mut deserializer = ron::de::Deserializer::from_bytes(&bytes)?;
+                seed.deserialize(&mut deserializer)?;
+                deserializer.end()?;
+            }
+            SaveFormat::Binary => bincode::config().deserialize_seed(seed, &bytes)?,
+        }
+        Ok(())
+    }
+
+    fn read_version(&self, bytes: &[u8]) -> Result<u32, SaveError> {
+        match self.format {
+            SaveFormat::Ron => {
+                let (version, _): (u32, IgnoredAny) = ron::de::from_bytes(bytes)?;
+                Ok(version)
+            }
+            // The binary encoding of a tuple starts with its first element.
+            SaveFormat::Binary => Ok(bincode::deserialize(bytes)?),
+        }
+    }
+}
+
+struct SaveFileOut<'a, C, M> {
+    world: &'a World,
+    marker: PhantomData<(C, M)>,
+}
+
+impl<'a, C, M> Serialize for SaveFileOut<'a, C, M>
+where
+    C: SaveComponents<M>,
+    M: Marker,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        C::serialize(self.world, serializer)
+    }
+}
+
+/// Deserializes the `(version, entities)` tuple of a save file into the `World`.
+struct SaveFileSeed<'a, C, M> {
+    world: &'a World,
+    marker: PhantomData<(C, M)>,
+}
+
+impl<'de, 'a, C, M> DeserializeSeed<'de> for SaveFileSeed<'a, C, M>
+where
+    C: SaveComponents<M>,
+    M: Marker,
+{
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_tuple(2, self)
+    }
+}
+
+impl<'de, 'a, C, M> Visitor<'de> for SaveFileSeed<'a, C, M>
+where
+    C: SaveComponents<M>,
+    M: Marker,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a save file containing a version and entities")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        seq.next_element::<u32>()?
+            .ok_or_else(|| de::Error::invalid_length(0, &"a save file version"))?;
+        seq.next_element_seed(EntitiesSeed::<C, M> {
+            world: self.world,
+            marker: PhantomData,
+        })?
+        .ok_or_else(|| de::Error::invalid_length(1, &"a list of saved entities"))
+    }
+}
+
+struct EntitiesSeed<'a, C, M> {
+    world: &'a World,
+    marker: PhantomData<(C, M)>,
+}
+
+impl<'de, 'a, C, M> DeserializeSeed<'de> for EntitiesSeed<'a, C, M>
+where
+    C: SaveComponents<M>,
+    M: Marker,
+{
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        C::deserialize(self.world, deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, process};
+
+    use super::{SaveComponents, SaveError, SaveFormat, SaveGame};
+    use crate::{
+        core::{
+            specs::{
+                prelude::{Builder, World},
+                saveload::{MarkedBuilder, U64Marker},
+            },
+            Named, Parent, Transform,
+        },
+        ecs::prelude::Join,
+    };
+
+    type Saved = (Transform, Parent, Named);
+
+    fn directory(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("amethyst_save_game_{}_{}", name, process::id()))
+    }
+
+    fn world() -> World {
+        let mut world = World::new();
+        <Saved as SaveComponents<U64Marker>>::setup(&mut world);
+        let parent = world
+            .create_entity()
+            .with(Named::new("parent"))
+            .with(Transform::default())
+            .marked::<U64Marker>()
+            .build();
+        world
+            .create_entity()
+            .with(Named::new("child"))
+            .with(Parent { entity: parent })
+            .marked::<U64Marker>()
+            .build();
+        world
+    }
+
+    fn names(world: &World) -> Vec<String> {
+        let mut names = (&world.read_storage::<Named>())
+            .join()
+            .map(|named| named.name.to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn unmarked_parent_fails() {
+        let dir = directory("unmarked");
+        let save_game = SaveGame::<U64Marker>::with_directory(&dir);
+
+        let mut world = World::new();
+        <Saved as SaveComponents<U64Marker>>::setup(&mut world);
+        let parent = world.create_entity().with(Named::new("parent")).build();
+        world
+            .create_entity()
+            .with(Named::new("child"))
+            .with(Parent { entity: parent })
+            .marked::<U64Marker>()
+            .build();
+
+        let result = save_game.save::<Saved>(&mut world, "slot");
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("is not marked for saving"));
+        assert!(!save_game.exists("slot"));
+        assert!(!dir.exists());
+    }
+
+    fn round_trip(format: SaveFormat) {
+        let dir = directory(&format!("{:?}", format));
+        let save_game = SaveGame::<U64Marker>::with_directory(&dir).with_format(format);
+
+        save_game.save::<Saved>(&mut world(), "slot").unwrap();
+        assert_eq!(vec!["slot".to_owned()], save_game.slots().unwrap());
+
+        let mut loaded = World::new();
+        save_game.load::<Saved>(&mut loaded, "slot").unwrap();
+        assert_eq!(vec!["child", "parent"], names(&loaded));
+        assert_eq!(1, (&loaded.read_storage::<Parent>()).join().count());
+
+        save_game.delete("slot").unwrap();
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn round_trip_ron() {
+        round_trip(SaveFormat::Ron);
+    }
+
+    #[test]
+    fn round_trip_binary() {
+        round_trip(SaveFormat::Binary);
+    }
+
+    #[test]
+    fn migrates_old_saves() {
+        let dir = directory("migration");
+        SaveGame::<U64Marker>::with_directory(&dir)
+            .save::<Saved>(&mut world(), "slot")
+            .unwrap();
+
+        // Version 2 renamed the child and version 3 didn't change the file.
+        let save_game = SaveGame::<U64Marker>::with_directory(&dir)
+            .with_version(3)
+            .with_migration(1, |bytes| {
+                let contents = String::from_utf8_lossy(bytes)
+                    .replacen("1,", "2,", 1)
+                    .replace("\"child\"", "\"renamed\"");
+                Ok(contents.into_bytes())
+            })
+            .with_migration(2, |bytes| {
+                Ok(String::from_utf8_lossy(bytes)
+                    .replacen("2,", "3,", 1)
+                    .into_bytes())
+            });
+
+        let mut loaded = World::new();
+        save_game.load::<Saved>(&mut loaded, "slot").unwrap();
+        assert_eq!(vec!["parent", "renamed"], names(&loaded));
+
+        let missing = SaveGame::<U64Marker>::with_directory(&dir).with_version(2);
+        match missing.load::<Saved>(&mut World::new(), "slot") {
+            Err(SaveError::MissingMigration(1)) => {}
+            other => panic!("Expected a missing migration, got {:?}", other),
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn newer_version_fails() {
+        let dir = directory("newer");
+        SaveGame::<U64Marker>::with_directory(&dir)
+            .with_version(3)
+            .save::<Saved>(&mut world(), "slot")
+            .unwrap();
+
+        let save_game = SaveGame::<U64Marker>::with_directory(&dir).with_version(2);
+        match save_game.load::<Saved>(&mut World::new(), "slot") {
+            Err(SaveError::UnsupportedVersion {
+                found: 3,
+                current: 2,
+            }) => {}
+            other => panic!("Expected an unsupported version, got {:?}", other),
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_slot_names_fail() {
+        let dir = directory("invalid");
+        let save_game = SaveGame::<U64Marker>::with_directory(&dir);
+
+        for slot in &["../escape", "/tmp/absolute", "sub/slot", "", "..", "C:save"] {
+            match save_game.save::<Saved>(&mut world(), slot) {
+                Err(SaveError::InvalidSlot(_)) => {}
+                other => panic!("Expected an invalid slot for {:?}, got {:?}", slot, other),
+            }
+            assert!(!save_game.exists(slot));
+        }
+        assert!(save_game.slot_path("Quick save_2").is_ok());
+        assert!(!dir.exists());
+    }
+}
@@ -2,7 +2,12 @@
 
 use amethyst_input::is_close_requested;
 
-use crate::{ecs::prelude::World, GameData, StateEvent};
+use crate::{
+    app::ShutdownWatchdog,
+    core::shrev::EventChannel,
+    ecs::{error::BoxedErr, prelude::World},
+    GameData, StateEvent,
+};
 
 use std::fmt::Result as FmtResult;
 use std::fmt::{Display, Formatter};
@@ -55,10 +60,52 @@ pub enum Trans<T, E> {
     Push(Box<dyn State<T, E>>),
     /// Remove the current state on the stack and insert a different one.
     Switch(Box<dyn State<T, E>>),
+    /// Stop and remove all states and push a new state onto the empty stack.
+    Replace(Box<dyn State<T, E>>),
+    /// Stop and remove all states and push the given states onto the empty stack, in order.
+    /// The last state becomes the active one. An empty stack stops the state machine.
+    NewStack(Vec<Box<dyn State<T, E>>>),
+    /// Execute all the given transitions in order, within the same frame.
+    Sequence(Vec<Trans<T, E>>),
     /// Stop and remove all states and shut down the engine.
     Quit,
 }
 
+/// The kind of change of the state stack reported by a `StateTransitionEvent`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateTransition {
+    /// The state machine was started with its initial state.
+    Start,
+    /// A state was pushed onto the stack.
+    Push,
+    /// The active state was removed from the stack.
+    Pop,
+    /// The active state was replaced by a different one.
+    Switch,
+    /// All states were removed and a new state was pushed onto the stack.
+    Replace,
+    /// All states were removed and a new stack of states was pushed.
+    NewStack,
+    /// All states were removed and the state machine was shut down.
+    Stop,
+}
+
+/// Event published on the `EventChannel<StateTransitionEvent>` resource every time the
+/// `StateMachine` changes its state stack.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateTransitionEvent {
+    /// How the state stack changed.
+    pub transition: StateTransition,
+    /// The name of the state that was active before the transition, `None` for
+    /// `StateTransition::Start`.
+    pub previous: Option<String>,
+    /// The name of the state that is active after the transition, `None` if the stack is
+    /// empty.
+    pub active: Option<String>,
+    /// The number of states on the stack after the transition.
+    pub depth: usize,
+}
+
 /// Event queue to trigger state `Trans` from other places than a `State`'s methods.
 /// # Example:
 /// ```rust, ignore
@@ -77,6 +124,11 @@ pub type SimpleTrans = Trans<GameData<'static, 'static>, StateEvent>;
 
 /// A trait which defines game states that can be used by the state machine.
 pub trait State<T, E: Send + Sync + 'static> {
+    /// The name of the state, reported in `StateTransitionEvent`s. Defaults to `"State"`.
+    fn name(&self) -> &str {
+        "State"
+    }
+
     /// Executed when the game state begins.
     fn on_start(&mut self, _data: StateData<'_, T>) {}
 
@@ -94,6 +146,13 @@ pub trait State<T, E: Send + Sync + 'static> {
         Trans::None
     }
 
+    /// Executed for every error reported through the `Errors` resource when the application
+    /// uses `ErrorPolicy::State`. Logs the error by default.
+    fn handle_error(&mut self, _data: StateData<'_, T>, error: BoxedErr) -> Trans<T, E> {
+        error!("{}", error);
+        Trans::None
+    }
+
     /// Executed repeatedly at stable, predictable intervals (1/60th of a second
     /// by default),
     /// if this is the active state.
@@ -121,6 +180,11 @@ pub trait State<T, E: Send + Sync + 'static> {
 
 /// An empty `State` trait. It contains no `StateData` or custom `StateEvent`.
 pub trait EmptyState {
+    /// The name of the state, reported in `StateTransitionEvent`s. Defaults to `"State"`.
+    fn name(&self) -> &str {
+        "State"
+    }
+
     /// Executed when the game state begins.
     fn on_start(&mut self, _data: StateData<'_, ()>) {}
 
@@ -146,6 +210,13 @@ pub trait EmptyState {
         }
     }
 
+    /// Executed for every error reported through the `Errors` resource when the application
+    /// uses `ErrorPolicy::State`. Logs the error by default.
+    fn handle_error(&mut self, _data: StateData<'_, ()>, error: BoxedErr) -> EmptyTrans {
+        error!("{}", error);
+        Trans::None
+    }
+
     /// Executed repeatedly at stable, predictable intervals (1/60th of a second
     /// by default).
     fn fixed_update(&mut self, _data: StateData<'_, ()>) -> EmptyTrans {
@@ -170,6 +241,11 @@ pub trait EmptyState {
 }
 
 impl<T: EmptyState> State<(), StateEvent> for T {
+    /// The name of the state, reported in `StateTransitionEvent`s.
+    fn name(&self) -> &str {
+        self.name()
+    }
+
     /// Executed when the game state begins.
     fn on_start(&mut self, data: StateData<'_, ()>) {
         self.on_start(data)
@@ -195,6 +271,12 @@ impl<T: EmptyState> State<(), StateEvent> for T {
         self.handle_event(data, event)
     }
 
+    /// Executed for every error reported through the `Errors` resource when the application
+    /// uses `ErrorPolicy::State`.
+    fn handle_error(&mut self, data: StateData<'_, ()>, error: BoxedErr) -> EmptyTrans {
+        self.handle_error(data, error)
+    }
+
     /// Executed repeatedly at stable, predictable intervals (1/60th of a second
     /// by default).
     fn fixed_update(&mut self, data: StateData<'_, ()>) -> EmptyTrans {
@@ -224,6 +306,11 @@ impl<T: EmptyState> State<(), StateEvent> for T {
 
 /// A simple `State` trait. It contains `GameData` as its `StateData` and no custom `StateEvent`.
 pub trait SimpleState {
+    /// The name of the state, reported in `StateTransitionEvent`s. Defaults to `"State"`.
+    fn name(&self) -> &str {
+        "State"
+    }
+
     /// Executed when the game state begins.
     fn on_start(&mut self, _data: StateData<'_, GameData<'_, '_>>) {}
 
@@ -253,6 +340,17 @@ pub trait SimpleState {
         }
     }
 
+    /// Executed for every error reported through the `Errors` resource when the application
+    /// uses `ErrorPolicy::State`. Logs the error by default.
+    fn handle_error(
+        &mut self,
+        _data: StateData<'_, GameData<'_, '_>>,
+        error: BoxedErr,
+    ) -> SimpleTrans {
+        error!("{}", error);
+        Trans::None
+    }
+
     /// Executed repeatedly at stable, predictable intervals (1/60th of a second
     /// by default).
     fn fixed_update(&mut self, _data: StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
@@ -274,11 +372,22 @@ pub trait SimpleState {
     /// even when this is not the active state,
     /// as long as this state is on the [StateMachine](struct.StateMachine.html)'s state-stack.
     fn shadow_update(&mut self, _data: StateData<'_, GameData<'_, '_>>) {}
+
+    /// Names of the `GameData` dispatcher groups that run, in order, after the main dispatcher
+    /// while this is the active state. No groups run by default.
+    fn dispatcher_groups(&self) -> &[&'static str] {
+        &[]
+    }
 }
 
 impl<T: SimpleState> State<GameData<'static, 'static>, StateEvent> for T {
     //pub trait SimpleState<'a,'b>: State<GameData<'a,'b>,()> {
 
+    /// The name of the state, reported in `StateTransitionEvent`s.
+    fn name(&self) -> &str {
+        self.name()
+    }
+
     /// Executed when the game state begins.
     fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
         self.on_start(data)
@@ -308,6 +417,16 @@ impl<T: SimpleState> State<GameData<'static, 'static>, StateEvent> for T {
         self.handle_event(data, event)
     }
 
+    /// Executed for every error reported through the `Errors` resource when the application
+    /// uses `ErrorPolicy::State`.
+    fn handle_error(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        error: BoxedErr,
+    ) -> SimpleTrans {
+        self.handle_error(data, error)
+    }
+
     /// Executed repeatedly at stable, predictable intervals (1/60th of a second
     /// by default).
     fn fixed_update(&mut self, data: StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
@@ -317,7 +436,8 @@ impl<T: SimpleState> State<GameData<'static, 'static>, StateEvent> for T {
     /// Executed on every frame immediately, as fast as the engine will allow (taking into account the frame rate limit).
     fn update(&mut self, mut data: StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
         let r = self.update(&mut data);
-        data.data.update(&data.world);
+        data.data
+            .update_groups(&data.world, self.dispatcher_groups());
         r
     }
 
@@ -367,8 +487,10 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
                 .state_stack
                 .last_mut()
                 .ok_or(StateError::NoStatesPresent)?;
-            state.on_start(data);
+            let StateData { world, data } = data;
+            state.on_start(StateData { world, data });
             self.running = true;
+            self.publish(world, StateTransition::Start, None);
         }
         Ok(())
     }
@@ -386,6 +508,19 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
         }
     }
 
+    /// Passes a single error to the active state to handle.
+    pub fn handle_error(&mut self, data: StateData<'_, T>, error: BoxedErr) {
+        let StateData { world, data } = data;
+        if self.running {
+            let trans = match self.state_stack.last_mut() {
+                Some(state) => state.handle_error(StateData { world, data }, error),
+                None => Trans::None,
+            };
+
+            self.transition(trans, StateData { world, data });
+        }
+    }
+
     /// Updates the currently active state at a steady, fixed interval.
     pub fn fixed_update(&mut self, data: StateData<'_, T>) {
         let StateData { world, data } = data;
@@ -430,6 +565,14 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
                 Trans::Pop => self.pop(data),
                 Trans::Push(state) => self.push(state, data),
                 Trans::Switch(state) => self.switch(state, data),
+                Trans::Replace(state) => self.replace(state, data),
+                Trans::NewStack(states) => self.new_stack(states, data),
+                Trans::Sequence(sequence) => {
+                    let StateData { world, data } = data;
+                    for trans in sequence {
+                        self.transition(trans, StateData { world, data });
+                    }
+                }
                 Trans::Quit => self.stop(data),
             }
         }
@@ -438,6 +581,7 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
     /// Removes the current state on the stack and inserts a different one.
     fn switch(&mut self, state: Box<dyn State<T, E>>, data: StateData<'_, T>) {
         if self.running {
+            let previous = self.active_name();
             let StateData { world, data } = data;
             if let Some(mut state) = self.state_stack.pop() {
                 state.on_stop(StateData { world, data });
@@ -448,12 +592,14 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
             //State was just pushed, thus pop will always succeed
             let state = self.state_stack.last_mut().unwrap();
             state.on_start(StateData { world, data });
+            self.publish(world, StateTransition::Switch, previous);
         }
     }
 
     /// Pauses the active state and pushes a new state onto the state stack.
     fn push(&mut self, state: Box<dyn State<T, E>>, data: StateData<'_, T>) {
         if self.running {
+            let previous = self.active_name();
             let StateData { world, data } = data;
             if let Some(state) = self.state_stack.last_mut() {
                 state.on_pause(StateData { world, data });
@@ -464,6 +610,7 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
             //State was just pushed, thus pop will always succeed
             let state = self.state_stack.last_mut().unwrap();
             state.on_start(StateData { world, data });
+            self.publish(world, StateTransition::Push, previous);
         }
     }
 
@@ -471,6 +618,7 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
     /// stack (if any).
     fn pop(&mut self, data: StateData<'_, T>) {
         if self.running {
+            let previous = self.active_name();
             let StateData { world, data } = data;
             if let Some(mut state) = self.state_stack.pop() {
                 state.on_stop(StateData { world, data });
@@ -481,24 +629,103 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
             } else {
                 self.running = false;
             }
+            self.publish(world, StateTransition::Pop, previous);
+        }
+    }
+
+    /// Stops and removes all states and pushes a new state onto the empty stack.
+    fn replace(&mut self, state: Box<dyn State<T, E>>, data: StateData<'_, T>) {
+        if self.running {
+            let previous = self.active_name();
+            let StateData { world, data } = data;
+            while let Some(mut state) = self.state_stack.pop() {
+                state.on_stop(StateData { world, data });
+            }
+
+            self.state_stack.push(state);
+
+            //State was just pushed, thus pop will always succeed
+            let state = self.state_stack.last_mut().unwrap();
+            state.on_start(StateData { world, data });
+            self.publish(world, StateTransition::Replace, previous);
+        }
+    }
+
+    /// Stops and removes all states and pushes the given states onto the empty stack.
+    fn new_stack(&mut self, states: Vec<Box<dyn State<T, E>>>, data: StateData<'_, T>) {
+        if self.running {
+            let previous = self.active_name();
+            let StateData { world, data } = data;
+            while let Some(mut state) = self.state_stack.pop() {
+                state.on_stop(StateData { world, data });
+            }
+
+            for state in states {
+                if let Some(state) = self.state_stack.last_mut() {
+                    state.on_pause(StateData { world, data });
+                }
+
+                self.state_stack.push(state);
+
+                //State was just pushed, thus pop will always succeed
+                let state = self.state_stack.last_mut().unwrap();
+                state.on_start(StateData { world, data });
+            }
+
+            if self.state_stack.is_empty() {
+                self.running = false;
+            }
+            self.publish(world, StateTransition::NewStack, previous);
+        }
+    }
+
+    fn active_name(&self) -> Option<String> {
+        self.state_stack.last().map(|state| state.name().to_owned())
+    }
+
+    /// Writes a `StateTransitionEvent` to its event channel, if the channel was added to the
+    /// world.
+    fn publish(&self, world: &World, transition: StateTransition, previous: Option<String>) {
+        if let Some(mut channel) = world
+            .res
+            .try_fetch_mut::<EventChannel<StateTransitionEvent>>()
+        {
+            channel.single_write(StateTransitionEvent {
+                transition,
+                previous,
+                active: self.active_name(),
+                depth: self.state_stack.len(),
+            });
         }
     }
 
     /// Shuts the state machine down.
     pub(crate) fn stop(&mut self, data: StateData<'_, T>) {
         if self.running {
+            // Stopping the states counts towards the shutdown timeout.
+            if let Some(mut watchdog) = data.world.res.try_fetch_mut::<ShutdownWatchdog>() {
+                watchdog.start();
+            }
+
+            let previous = self.active_name();
             let StateData { world, data } = data;
             while let Some(mut state) = self.state_stack.pop() {
                 state.on_stop(StateData { world, data });
             }
 
             self.running = false;
+            self.publish(world, StateTransition::Stop, previous);
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
     use super::*;
 
     struct State1(u8);
@@ -521,6 +748,30 @@ mod tests {
         }
     }
 
+    struct Counted(Arc<AtomicUsize>, &'static str);
+
+    impl State<(), ()> for Counted {
+        fn name(&self) -> &str {
+            self.1
+        }
+
+        fn on_start(&mut self, _: StateData<'_, ()>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn on_stop(&mut self, _: StateData<'_, ()>) {
+            self.0.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    struct FailingState;
+
+    impl State<(), ()> for FailingState {
+        fn handle_error(&mut self, _: StateData<'_, ()>, _: BoxedErr) -> Trans<(), ()> {
+            Trans::Quit
+        }
+    }
+
     #[test]
     fn switch_pop() {
         use crate::ecs::prelude::World;
@@ -539,4 +790,78 @@ mod tests {
         sm.update(StateData::new(&mut world, &mut ()));
         assert!(!sm.is_running());
     }
+
+    #[test]
+    fn handle_error_quit() {
+        use crate::ecs::prelude::World;
+
+        let mut world = World::new();
+
+        let mut sm = StateMachine::new(FailingState);
+        sm.start(StateData::new(&mut world, &mut ())).unwrap();
+        assert!(sm.is_running());
+
+        sm.handle_error(
+            StateData::new(&mut world, &mut ()),
+            BoxedErr(Box::from("system failure")),
+        );
+        assert!(!sm.is_running());
+    }
+
+    #[test]
+    fn replace_and_sequence() {
+        let mut world = World::new();
+        world.add_resource(EventChannel::<StateTransitionEvent>::new());
+        let mut reader = world
+            .write_resource::<EventChannel<StateTransitionEvent>>()
+            .register_reader();
+
+        let started = Arc::new(AtomicUsize::new(0));
+        let mut sm = StateMachine::new(Counted(started.clone(), "menu"));
+        sm.start(StateData::new(&mut world, &mut ())).unwrap();
+
+        sm.transition(
+            Trans::Sequence(vec![
+                Trans::Push(Box::new(Counted(started.clone(), "game"))),
+                Trans::Push(Box::new(Counted(started.clone(), "pause"))),
+            ]),
+            StateData::new(&mut world, &mut ()),
+        );
+        assert_eq!(3, started.load(Ordering::SeqCst));
+
+        sm.transition(
+            Trans::Replace(Box::new(Counted(started.clone(), "credits"))),
+            StateData::new(&mut world, &mut ()),
+        );
+        assert_eq!(1, started.load(Ordering::SeqCst));
+        assert!(sm.is_running());
+
+        sm.transition(Trans::NewStack(vec![]), StateData::new(&mut world, &mut ()));
+        assert_eq!(0, started.load(Ordering::SeqCst));
+        assert!(!sm.is_running());
+
+        let events = world
+            .read_resource::<EventChannel<StateTransitionEvent>>()
+            .read(&mut reader)
+            .cloned()
+            .collect::<Vec<_>>();
+        let event = |transition, previous: Option<&str>, active: Option<&str>, depth| {
+            StateTransitionEvent {
+                transition,
+                previous: previous.map(str::to_owned),
+                active: active.map(str::to_owned),
+                depth,
+            }
+        };
+        assert_eq!(
+            vec![
+                event(StateTransition::Start, None, Some("menu"), 1),
+                event(StateTransition::Push, Some("menu"), Some("game"), 2),
+                event(StateTransition::Push, Some("game"), Some("pause"), 3),
+                event(StateTransition::Replace, Some("pause"), Some("credits"), 1),
+                event(StateTransition::NewStack, Some("credits"), None, 0),
+            ],
+            events
+        );
+    }
 }
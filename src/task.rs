@@ -0,0 +1,248 @@
+use std::{
+    cell::RefCell,
+    error::Error as StdError,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crossbeam_channel::Sender;
+
+use crate::{
+    assets::{Error as AssetError, Progress, Tracker},
+    callback_queue::{Callback, CallbackQueue},
+    core::{specs::World, ArcThreadPool},
+};
+
+/// Used to number tasks, so failed tasks can be told apart in `ProgressCounter::errors`.
+static TASK_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// The error a task spawned with the `TaskSpawner` fails with.
+///
+/// All error types and strings convert into it, so tasks can use `?` and `.into()`.
+pub type TaskError = Box<dyn StdError + Send + Sync>;
+
+/// A handle to a task spawned with the `TaskSpawner`, which can be used to cancel it.
+///
+/// The same handle is passed to the task itself, so long running work can check
+/// `is_cancelled` and stop early. Dropping the handle does not cancel the task.
+#[derive(Clone, Debug, Default)]
+pub struct TaskHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl TaskHandle {
+    /// Cancels the task. If its result has not been delivered yet, the completion closure will
+    /// not run and the task's tracker, if any, is notified of the failure.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Checks whether the task was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Runs blocking work on the engine's thread pool and delivers the result back to the main
+/// thread, where it is passed to a completion closure together with the `World`.
+///
+/// The `TaskSpawner` is added as a resource by the `Application`, systems can access it with
+/// `ReadExpect<'a, TaskSpawner>`.
+///
+/// # Example
+/// ```rust,ignore
+/// let spawner = world.read_resource::<TaskSpawner>();
+/// let handle = spawner.spawn(
+///     |_handle| Ok(std::fs::read_to_string("level.txt")?),
+///     |world, level| {
+///         world.add_resource(Level::parse(&level));
+///     },
+/// );
+/// ```
+#[derive(Clone)]
+pub struct TaskSpawner {
+    pool: ArcThreadPool,
+    sender: Sender<Callback>,
+}
+
+impl TaskSpawner {
+    /// Creates a new `TaskSpawner`, delivering results through the given `CallbackQueue`.
+    pub fn new(pool: ArcThreadPool, queue: &CallbackQueue) -> Self {
+        TaskSpawner {
+            pool,
+            sender: queue.send_handle(),
+        }
+    }
+
+    /// Spawns `work` on the thread pool. Once it succeeds, `on_complete` is run on the main
+    /// thread with the result. Errors are logged.
+    pub fn spawn<T, W, C>(&self, work: W, on_complete: C) -> TaskHandle
+    where
+        T: Send + 'static,
+        W: FnOnce(&TaskHandle) -> Result<T, TaskError> + Send + 'static,
+        C: FnOnce(&mut World, T) + Send + 'static,
+    {
+        self.spawn_tracked((), "", work, on_complete)
+    }
+
+    /// Spawns `work` on the thread pool and reports its progress, like the `Loader` does for
+    /// assets. The task counts as finished once `on_complete` has run on the main thread with
+    /// the result.
+    pub fn spawn_tracked<T, P, W, C>(
+        &self,
+        mut progress: P,
+        name: &str,
+        work: W,
+        on_complete: C,
+    ) -> TaskHandle
+    where
+        T: Send + 'static,
+        P: Progress,
+        W: FnOnce(&TaskHandle) -> Result<T, TaskError> + Send + 'static,
+        C: FnOnce(&mut World, T) + Send + 'static,
+    {
+        progress.add_assets(1);
+        let tracker = Box::new(progress.create_tracker()) as Box<dyn Tracker>;
+        let id = TASK_ID.fetch_add(1, Ordering::Relaxed) as u32;
+        let name = name.to_owned();
+        let handle = TaskHandle::default();
+        let task_handle = handle.clone();
+        let sender = self.sender.clone();
+
+        self.pool.spawn(move || {
+            let result = if task_handle.is_cancelled() {
+                Err("Task was cancelled".into())
+            } else {
+                work(&task_handle)
+            };
+
+            // `Callback` is an `Fn`, so the result has to be moved out of a cell.
+            let payload = RefCell::new(Some((result, on_complete, tracker)));
+            let callback: Callback = Box::new(move |world| {
+                let (result, on_complete, tracker) = match payload.borrow_mut().take() {
+                    Some(payload) => payload,
+                    None => return,
+                };
+
+                match result {
+                    Ok(_) if task_handle.is_cancelled() => {
+                        tracker.fail(id, "Task", name.clone(), "Task was cancelled".into())
+                    }
+                    Ok(value) => {
+                        on_complete(world, value);
+                        tracker.success();
+                    }
+                    Err(err) => tracker.fail(
+                        id,
+                        "Task",
+                        name.clone(),
+                        AssetError::with_boxed_chain(err, "Task failed"),
+                    ),
+                }
+            });
+
+            if sender.send(callback).is_err() {
+                error!("Failed to deliver task result, the `CallbackQueue` was dropped");
+            }
+        });
+
+        handle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use rayon::ThreadPoolBuilder;
+
+    use super::TaskSpawner;
+    use crate::{assets::ProgressCounter, callback_queue::CallbackQueue, core::specs::World};
+
+    fn spawner() -> (TaskSpawner, CallbackQueue) {
+        let pool = Arc::new(ThreadPoolBuilder::new().num_threads(1).build().unwrap());
+        let queue = CallbackQueue::new();
+        (TaskSpawner::new(pool, &queue), queue)
+    }
+
+    /// Waits for the result of a task and runs its callback.
+    fn deliver(queue: &CallbackQueue, world: &mut World) {
+        let callback = queue
+            .receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Task result was not delivered");
+        callback(world);
+    }
+
+    #[derive(Default)]
+    struct Answer(u32);
+
+    #[test]
+    fn delivers_result() {
+        let (spawner, queue) = spawner();
+        let mut world = World::new();
+        let mut progress = ProgressCounter::new();
+
+        spawner.spawn_tracked(
+            &mut progress,
+            "answer",
+            |_| Ok(6 * 7),
+            |world, answer| world.add_resource(Answer(answer)),
+        );
+        deliver(&queue, &mut world);
+
+        assert_eq!(42, world.read_resource::<Answer>().0);
+        assert!(progress.is_complete());
+        assert_eq!(0, progress.num_failed());
+    }
+
+    #[test]
+    fn reports_errors() {
+        let (spawner, queue) = spawner();
+        let mut world = World::new();
+        let mut progress = ProgressCounter::new();
+
+        spawner.spawn_tracked(
+            &mut progress,
+            "broken",
+            |_| Err("Level is broken".into()),
+            |world, answer| world.add_resource(Answer(answer)),
+        );
+        deliver(&queue, &mut world);
+
+        assert!(!world.res.has_value::<Answer>());
+        assert_eq!(1, progress.num_failed());
+        let errors = progress.errors();
+        assert_eq!("broken", errors[0].asset_name);
+        assert_eq!(
+            "Level is broken",
+            errors[0].error.iter().nth(1).unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn cancelled_task_is_not_completed() {
+        let (spawner, queue) = spawner();
+        let mut world = World::new();
+        let mut progress = ProgressCounter::new();
+        let (start, started) = crossbeam_channel::bounded::<()>(0);
+
+        let handle = spawner.spawn_tracked(
+            &mut progress,
+            "cancelled",
+            move |_| {
+                started.recv().unwrap();
+                Ok(1)
+            },
+            |world, answer| world.add_resource(Answer(answer)),
+        );
+        handle.cancel();
+        start.send(()).unwrap();
+        deliver(&queue, &mut world);
+
+        assert!(!world.res.has_value::<Answer>());
+        assert_eq!(1, progress.num_failed());
+    }
+}